@@ -0,0 +1,84 @@
+//! Exponential backoff with jitter for reconnecting to the broker.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::Duration;
+
+/// Delays between connection attempts, doubling from `base` up to `max`.
+///
+/// Each delay is shortened by a random share of up to `jitter` of itself, so a fleet of clients
+/// that lost the broker at the same moment doesn't reconnect in lockstep.
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    jitter: f64,
+    attempt: u32,
+    rng: StdRng,
+}
+
+impl Backoff {
+    /// `jitter` is clamped between 0 (no randomization) and 1 (anywhere between zero and the
+    /// full delay).
+    pub fn new(base: Duration, max: Duration, jitter: f64, seed: u64) -> Self {
+        Backoff {
+            base,
+            max,
+            jitter: jitter.clamp(0.0, 1.0),
+            attempt: 0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Delay before the next attempt.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self
+            .base
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        delay.mul_f64(1.0 - self.jitter * self.rng.gen::<f64>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: Duration = Duration::from_millis(100);
+    const MAX: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn delays_fall_within_the_jittered_range() {
+        for seed in 0..100 {
+            let mut backoff = Backoff::new(BASE, MAX, 0.25, seed);
+            for attempt in 0..10 {
+                let full = (BASE * 2u32.pow(attempt)).min(MAX);
+                let delay = backoff.next_delay();
+                assert!(
+                    delay <= full && delay >= full.mul_f64(0.75),
+                    "seed {seed}, attempt {attempt}: {delay:?} outside of {full:?} - 25%"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_spread_the_delays() {
+        let first_delays: Vec<_> = (0..10)
+            .map(|seed| Backoff::new(BASE, MAX, 0.5, seed).next_delay())
+            .collect();
+
+        assert!(first_delays.iter().any(|delay| *delay != first_delays[0]));
+    }
+
+    #[test]
+    fn no_jitter_gives_the_plain_exponential_backoff() {
+        let mut backoff = Backoff::new(BASE, MAX, 0.0, 0);
+        let delays: Vec<_> = (0..8).map(|_| backoff.next_delay()).collect();
+
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1600, 3200, 5000, 5000].map(Duration::from_millis)
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Decoding of incoming MQTT payloads into sensor readings.
+
+use crate::reading::Reading;
+use std::collections::HashMap;
+
+/// Why an incoming payload didn't produce a reading.
+#[derive(Debug)]
+pub enum PayloadError {
+    /// The payload is not a `Reading` JSON document and was skipped, for this reason.
+    Unparseable(String),
+    /// Strict mode saw `count` unparseable payloads in a row and gave up, the last one for the
+    /// reason in `error`.
+    TooManyFailures { count: u32, error: String },
+    /// The reading's sequence number is not past the last one seen for its sensor: it's a copy
+    /// redelivered after a reconnect, or an older reading that arrived late.
+    Duplicate { id: u32, seq: u64 },
+}
+
+/// Turns MQTT publishes into `Reading`s.
+///
+/// By default payloads that don't parse are skipped quietly, since a shared broker carries plenty
+/// of unrelated traffic. In strict mode each of them is logged at error level with its topic, and
+/// `max_failures` consecutive failures abort the client so a bad topic filter is noticed early.
+///
+/// Readings carrying a sequence number are delivered at most once per `(id, seq)`: MQTT only
+/// guarantees at-least-once delivery for QoS 1, so a publish in flight during a reconnect can
+/// arrive twice. Sequence numbers have to increase for each sensor, so a publisher restarting
+/// from a lower number is ignored until it catches up.
+pub struct Bridge {
+    strict: bool,
+    max_failures: Option<u32>,
+    consecutive_failures: u32,
+    last_seq: HashMap<u32, u64>,
+}
+
+impl Bridge {
+    pub fn new(strict: bool, max_failures: Option<u32>) -> Self {
+        Bridge {
+            strict,
+            max_failures,
+            consecutive_failures: 0,
+            last_seq: HashMap::new(),
+        }
+    }
+
+    pub fn handle(&mut self, topic: &str, payload: &[u8]) -> Result<Reading, PayloadError> {
+        match Reading::from_payload(payload) {
+            Ok(reading) => {
+                self.consecutive_failures = 0;
+                if let Some(seq) = reading.seq {
+                    match self.last_seq.get(&reading.data.id) {
+                        Some(&last) if seq <= last => {
+                            return Err(PayloadError::Duplicate {
+                                id: reading.data.id,
+                                seq,
+                            })
+                        }
+                        _ => self.last_seq.insert(reading.data.id, seq),
+                    };
+                }
+                Ok(reading)
+            }
+            Err(e) => {
+                self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                if !self.strict {
+                    return Err(PayloadError::Unparseable(e.to_string()));
+                }
+
+                eprintln!("Error: unparseable payload on topic {}: {}", topic, e);
+                match self.max_failures {
+                    Some(max) if self.consecutive_failures >= max => {
+                        Err(PayloadError::TooManyFailures {
+                            count: self.consecutive_failures,
+                            error: e.to_string(),
+                        })
+                    }
+                    _ => Err(PayloadError::Unparseable(e.to_string())),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sensors_common::{Geolocation, SensorData, SensorType, SensorValue};
+
+    const READING: &[u8] = br#"{"id":1,"type_":"Temperature","geolocation":{"lat":10,"lon":20},"value":"21","timestamp":1680000000}"#;
+
+    #[test]
+    fn parses_sensor_data() {
+        let mut bridge = Bridge::new(true, None);
+
+        let reading = bridge.handle("sensors/1", READING).unwrap();
+
+        assert_eq!(
+            reading,
+            Reading {
+                data: SensorData {
+                    id: 1,
+                    type_: SensorType::Temperature,
+                    geolocation: Geolocation { lat: 10, lon: 20 },
+                    value: SensorValue::Number(21),
+                    timestamp: 1680000000,
+                    derived: false,
+                    quality: None,
+                },
+                seq: None,
+            }
+        );
+    }
+
+    #[test]
+    fn drops_a_redelivered_reading() {
+        let mut bridge = Bridge::new(true, None);
+        let reading = |id: u32, seq: u64| {
+            format!(
+                r#"{{"id":{id},"type_":"Digital","geolocation":{{"lat":1,"lon":2}},"value":"true","timestamp":3,"seq":{seq}}}"#
+            )
+        };
+
+        assert_eq!(
+            bridge
+                .handle("sensors/1", reading(1, 5).as_bytes())
+                .unwrap()
+                .seq,
+            Some(5)
+        );
+        // The same publish delivered again after a reconnect.
+        assert!(matches!(
+            bridge.handle("sensors/1", reading(1, 5).as_bytes()),
+            Err(PayloadError::Duplicate { id: 1, seq: 5 })
+        ));
+        // Sequence numbers are tracked per sensor.
+        assert!(bridge.handle("sensors/2", reading(2, 5).as_bytes()).is_ok());
+        assert!(bridge.handle("sensors/1", reading(1, 6).as_bytes()).is_ok());
+        assert!(matches!(
+            bridge.handle("sensors/1", reading(1, 4).as_bytes()),
+            Err(PayloadError::Duplicate { id: 1, seq: 4 })
+        ));
+    }
+
+    #[test]
+    fn readings_without_a_sequence_number_are_never_dropped() {
+        let mut bridge = Bridge::new(true, None);
+
+        assert!(bridge.handle("sensors/1", READING).is_ok());
+        assert!(bridge.handle("sensors/1", READING).is_ok());
+    }
+
+    #[test]
+    fn strict_mode_rejects_non_json_payloads() {
+        let mut bridge = Bridge::new(true, Some(2));
+
+        assert!(matches!(
+            bridge.handle("bedroom/temperature", b"Publish: 1"),
+            Err(PayloadError::Unparseable(_))
+        ));
+        assert!(matches!(
+            bridge.handle("bedroom/temperature", b"Publish: 2"),
+            Err(PayloadError::TooManyFailures { count: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn a_valid_reading_resets_the_failure_count() {
+        let mut bridge = Bridge::new(true, Some(2));
+
+        assert!(bridge.handle("sensors/1", b"not json").is_err());
+        assert!(bridge.handle("sensors/1", READING).is_ok());
+        assert!(matches!(
+            bridge.handle("sensors/1", b"not json"),
+            Err(PayloadError::Unparseable(_))
+        ));
+    }
+
+    #[test]
+    fn lenient_mode_never_gives_up() {
+        let mut bridge = Bridge::new(false, Some(1));
+
+        for _ in 0..3 {
+            assert!(matches!(
+                bridge.handle("sensors/1", b"not json"),
+                Err(PayloadError::Unparseable(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Preservation of payloads that don't parse, for later forensic analysis.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Serialize;
+use std::fmt::Display;
+
+/// Payload republished to the dead-letter topic for each payload that didn't parse, as JSON.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Envelope {
+    /// Topic the payload was received on.
+    pub source_topic: String,
+    /// The payload as received, in base64 since it needn't be UTF-8.
+    pub payload_base64: String,
+    /// Why the payload didn't parse.
+    pub error: String,
+}
+
+impl Envelope {
+    pub fn new(source_topic: &str, payload: &[u8], error: impl Display) -> Self {
+        Envelope {
+            source_topic: source_topic.to_string(),
+            payload_base64: STANDARD.encode(payload),
+            error: error.to_string(),
+        }
+    }
+
+    pub fn to_payload(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Envelope serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{Bridge, PayloadError};
+
+    #[test]
+    fn wraps_a_malformed_payload() {
+        let mut bridge = Bridge::new(false, None);
+
+        let error = match bridge.handle("bedroom/temperature", b"Publish: 1") {
+            Err(PayloadError::Unparseable(error)) => error,
+            result => panic!("unexpected result {:?}", result),
+        };
+
+        assert_eq!(
+            Envelope::new("bedroom/temperature", b"Publish: 1", error).to_payload(),
+            br#"{"source_topic":"bedroom/temperature","payload_base64":"UHVibGlzaDogMQ==","error":"expected value at line 1 column 1"}"#
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Suppression of repeated readings from chatty devices.
+
+use crate::lru::Lru;
+use sensors_common::{SensorData, SensorType, SensorValue};
+use std::time::{Duration, Instant};
+
+/// Last reading forwarded for a sensor.
+struct Forwarded {
+    value: SensorValue,
+    at: Instant,
+    suppressed: u32,
+}
+
+/// Drops a reading whose value equals the last one forwarded for the same `(id, type_)` less than
+/// `window` ago.
+///
+/// The last reading is kept for at most `capacity` sensors, forgetting the least recently heard
+/// from when a new sensor reports, so memory stays bounded across a fleet with churn. A sensor
+/// forgotten this way has its next reading forwarded even if it repeats the previous value.
+pub struct Dedup {
+    window: Duration,
+    last: Lru<(u32, SensorType), Forwarded>,
+}
+
+impl Dedup {
+    /// Panics if `capacity` is 0.
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Dedup {
+            window,
+            last: Lru::new(capacity),
+        }
+    }
+
+    /// Whether `reading`, received at `now`, should be forwarded.
+    ///
+    /// Returns the number of readings of the same sensor suppressed since the previous forwarded
+    /// one, or `None` if this reading is suppressed too.
+    pub fn forward(&mut self, reading: &SensorData, now: Instant) -> Option<u32> {
+        let key = (reading.id, reading.type_.clone());
+        if let Some(last) = self.last.get_mut(&key) {
+            if last.value == reading.value && now.duration_since(last.at) < self.window {
+                last.suppressed += 1;
+                return None;
+            }
+        }
+
+        let suppressed = self
+            .last
+            .insert(
+                key,
+                Forwarded {
+                    value: reading.value,
+                    at: now,
+                    suppressed: 0,
+                },
+            )
+            .map_or(0, |last| last.suppressed);
+        Some(suppressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sensors_common::Geolocation;
+
+    fn reading(id: u32, value: u32) -> SensorData {
+        SensorData {
+            id,
+            type_: SensorType::Temperature,
+            geolocation: Geolocation { lat: 10, lon: 20 },
+            value: SensorValue::Number(value),
+            timestamp: 1680000000,
+            derived: false,
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn forwards_identical_readings_once_per_window() {
+        let mut dedup = Dedup::new(Duration::from_millis(1000), 16);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let forwarded: Vec<_> = [
+            (reading(1, 21), at(0)),
+            (reading(1, 21), at(100)),
+            (reading(1, 21), at(200)),
+            (reading(2, 21), at(300)),
+            (reading(1, 22), at(400)),
+            (reading(1, 22), at(500)),
+            (reading(1, 22), at(1400)),
+        ]
+        .iter()
+        .map(|(reading, now)| dedup.forward(reading, *now))
+        .collect();
+
+        assert_eq!(
+            forwarded,
+            vec![Some(0), None, None, Some(0), Some(2), None, Some(1)]
+        );
+    }
+
+    #[test]
+    fn forwards_a_repeated_reading_of_an_evicted_sensor() {
+        let mut dedup = Dedup::new(Duration::from_millis(1000), 2);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let forwarded: Vec<_> = [
+            (reading(1, 21), at(0)),
+            (reading(2, 21), at(100)),
+            (reading(1, 21), at(200)),
+            // Evicts sensor 2, which was heard from least recently.
+            (reading(3, 21), at(300)),
+            (reading(1, 21), at(400)),
+            (reading(2, 21), at(500)),
+        ]
+        .iter()
+        .map(|(reading, now)| dedup.forward(reading, *now))
+        .collect();
+
+        assert_eq!(
+            forwarded,
+            vec![Some(0), Some(0), None, Some(0), None, Some(0)]
+        );
+    }
+}
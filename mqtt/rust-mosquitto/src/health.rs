@@ -0,0 +1,80 @@
+//! Health of the client itself, published for fleet monitoring.
+
+use serde::Serialize;
+use std::{fmt::Display, time::Instant};
+
+/// Health payload published to the health topic of the client, as JSON.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub uptime_secs: u64,
+    pub messages_handled: u64,
+    /// Most recent error, `null` if there wasn't any.
+    pub last_error: Option<String>,
+}
+
+/// Counters the client keeps about itself, reported periodically so that a central monitor can
+/// track every bridge instance.
+pub struct Health {
+    started: Instant,
+    messages_handled: u64,
+    last_error: Option<String>,
+}
+
+impl Health {
+    /// Start counting from `now`, when the client started.
+    pub fn new(now: Instant) -> Self {
+        Health {
+            started: now,
+            messages_handled: 0,
+            last_error: None,
+        }
+    }
+
+    /// Count a message received from the broker.
+    pub fn handled(&mut self) {
+        self.messages_handled = self.messages_handled.saturating_add(1);
+    }
+
+    pub fn error(&mut self, error: impl Display) {
+        self.last_error = Some(error.to_string());
+    }
+
+    pub fn report(&self, now: Instant) -> Report {
+        Report {
+            uptime_secs: now.saturating_duration_since(self.started).as_secs(),
+            messages_handled: self.messages_handled,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+impl Report {
+    pub fn to_payload(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Report serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn reports_the_counters_as_json() {
+        let start = Instant::now();
+        let mut health = Health::new(start);
+        assert_eq!(
+            health.report(start).to_payload(),
+            br#"{"uptime_secs":0,"messages_handled":0,"last_error":null}"#
+        );
+
+        health.handled();
+        health.handled();
+        health.error("unparseable payload on topic sensors/1");
+
+        assert_eq!(
+            health.report(start + Duration::from_secs(90)).to_payload(),
+            br#"{"uptime_secs":90,"messages_handled":2,"last_error":"unparseable payload on topic sensors/1"}"#
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Retained liveness status of the sensors the bridge hears from.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Payload of the status of a live sensor.
+pub const ONLINE: &[u8] = b"online";
+
+/// A retained message to publish for the status of a sensor.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Status {
+    pub topic: String,
+    /// `ONLINE`, or empty to clear the retained status.
+    pub payload: Vec<u8>,
+}
+
+/// Tracks which sensors are live, to keep a retained status per sensor on the broker.
+///
+/// A sensor goes live with its first reading, when `ONLINE` is published to its status topic, and
+/// stale once it hasn't sent a reading for `timeout`, when the retained status is cleared with an
+/// empty payload. Clients subscribing to the status topics learn which sensors are live right away
+/// instead of waiting for their next reading.
+pub struct Liveness {
+    topic_template: String,
+    timeout: Duration,
+    last_seen: HashMap<u32, Instant>,
+}
+
+impl Liveness {
+    /// `topic_template` is the status topic of every sensor, with `{id}` standing for its id.
+    pub fn new(topic_template: String, timeout: Duration) -> Self {
+        Liveness {
+            topic_template,
+            timeout,
+            last_seen: HashMap::new(),
+        }
+    }
+
+    pub fn topic(&self, id: u32) -> String {
+        self.topic_template.replace("{id}", &id.to_string())
+    }
+
+    /// Record a reading of sensor `id` received at `now`, returning its `ONLINE` status if it
+    /// wasn't live.
+    pub fn seen(&mut self, id: u32, now: Instant) -> Option<Status> {
+        match self.last_seen.insert(id, now) {
+            Some(_) => None,
+            None => Some(Status {
+                topic: self.topic(id),
+                payload: ONLINE.to_vec(),
+            }),
+        }
+    }
+
+    /// Forget the sensors that haven't sent a reading for `timeout` at `now`, returning the
+    /// statuses clearing theirs.
+    pub fn expire(&mut self, now: Instant) -> Vec<Status> {
+        let timeout = self.timeout;
+        let mut stale: Vec<u32> = self
+            .last_seen
+            .iter()
+            .filter(|(_, seen)| now.saturating_duration_since(**seen) >= timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        stale.sort_unstable();
+
+        stale
+            .into_iter()
+            .map(|id| {
+                self.last_seen.remove(&id);
+                Status {
+                    topic: self.topic(id),
+                    payload: Vec::new(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publishes_online_on_the_first_reading_and_clears_it_once_stale() {
+        let mut liveness = Liveness::new("sensors/{id}/status".into(), Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let online = |id| Status {
+            topic: format!("sensors/{}/status", id),
+            payload: b"online".to_vec(),
+        };
+        let cleared = |id| Status {
+            topic: format!("sensors/{}/status", id),
+            payload: Vec::new(),
+        };
+
+        assert_eq!(liveness.seen(1, at(0)), Some(online(1)));
+        assert_eq!(liveness.seen(2, at(10)), Some(online(2)));
+        assert_eq!(liveness.seen(1, at(30)), None);
+        assert_eq!(liveness.expire(at(69)), vec![]);
+
+        assert_eq!(liveness.expire(at(70)), vec![cleared(2)]);
+        assert_eq!(liveness.expire(at(90)), vec![cleared(1)]);
+        assert_eq!(liveness.expire(at(200)), vec![]);
+
+        // A sensor that reports again goes live again.
+        assert_eq!(liveness.seen(2, at(200)), Some(online(2)));
+    }
+}
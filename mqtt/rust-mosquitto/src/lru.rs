@@ -0,0 +1,101 @@
+//! Bounded map evicting its least recently used entries.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+/// Map holding at most `capacity` entries, evicting the least recently used one to make room for
+/// a new key. Both `get_mut` and `insert` count as a use of the key.
+pub struct Lru<K, V> {
+    capacity: usize,
+    /// Entries with the tick of their last use.
+    entries: HashMap<K, (V, u64)>,
+    /// Keys by the tick of their last use, oldest first.
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> Lru<K, V> {
+    /// Panics if `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "an LRU cache needs room for at least one entry"
+        );
+        Lru {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let tick = self.next_tick();
+        let (value, used) = self.entries.get_mut(key)?;
+        let key = self
+            .order
+            .remove(used)
+            .expect("every entry has its tick in the order");
+        *used = tick;
+        self.order.insert(tick, key);
+        Some(value)
+    }
+
+    /// Insert `value` for `key`, returning the value it replaces, and evicting the least recently
+    /// used entry if `key` is new and the cache is full.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let tick = self.next_tick();
+        let replaced = match self.entries.insert(key.clone(), (value, tick)) {
+            Some((replaced, used)) => {
+                self.order.remove(&used);
+                Some(replaced)
+            }
+            None => {
+                if self.entries.len() > self.capacity {
+                    if let Some((_, oldest)) = self.order.pop_first() {
+                        self.entries.remove(&oldest);
+                    }
+                }
+                None
+            }
+        };
+        self.order.insert(tick, key);
+        replaced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_least_recently_used_key_when_full() {
+        let mut lru = Lru::new(3);
+        lru.insert(1, "a");
+        lru.insert(2, "b");
+        lru.insert(3, "c");
+
+        // Using 1 makes 2 the least recently used.
+        assert_eq!(lru.get_mut(&1), Some(&mut "a"));
+        assert_eq!(lru.insert(4, "d"), None);
+        assert_eq!(lru.get_mut(&2), None);
+
+        // Replacing 3 uses it too, leaving 1 the least recently used.
+        assert_eq!(lru.insert(3, "C"), Some("c"));
+        lru.insert(5, "e");
+        assert_eq!(lru.get_mut(&1), None);
+
+        assert_eq!(lru.get_mut(&4), Some(&mut "d"));
+        assert_eq!(lru.get_mut(&3), Some(&mut "C"));
+        assert_eq!(lru.get_mut(&5), Some(&mut "e"));
+        lru.insert(6, "f");
+        assert_eq!(lru.get_mut(&4), None);
+    }
+}
@@ -1,38 +1,489 @@
-use rumqtt::{MqttClient, MqttOptions, Notification, QoS};
-use std::{thread, time::Duration};
+mod backoff;
+mod bridge;
+mod deadletter;
+mod dedup;
+mod health;
+mod liveness;
+mod lru;
+mod ratelimit;
+mod reading;
+mod simulate;
+
+use backoff::Backoff;
+use bridge::{Bridge, PayloadError};
+use clap::{ArgAction, Parser};
+use deadletter::Envelope;
+use dedup::Dedup;
+use health::Health;
+use liveness::{Liveness, Status};
+use ratelimit::RateLimiter;
+use rumqtt::{ClientError, MqttClient, MqttOptions, Notification, QoS, ReconnectOptions};
+use simulate::Simulator;
+use std::{
+    process,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 const MQTT_CLIENT_ID: &str = "test-pubsub1";
 const MQTT_ADDRESS: &str = "localhost";
 const MQTT_PORT: u16 = 1883;
 const MQTT_TOPIC: &str = "bedroom/temperature";
+/// Topics the simulated sensors publish on, as `sensors/<id>/<type>`.
+const SIMULATED_TOPICS: &str = "sensors/#";
+/// First delay before trying to connect to the broker again, doubled after every failed attempt.
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Parser, Debug)]
+#[command(about = "MQTT research client for the sensors oracle")]
+struct Args {
+    /// Log every payload that is not a `Reading` JSON document at error level, with its topic
+    /// and parse error, instead of skipping it silently.
+    #[arg(long)]
+    strict: bool,
+
+    /// In strict mode, exit with a non-zero status after this many consecutive unparseable
+    /// payloads.
+    #[arg(long, requires = "strict")]
+    max_failures: Option<u32>,
+
+    /// Don't forward a reading whose value equals the last one forwarded for the same sensor id
+    /// and type less than this many milliseconds ago.
+    #[arg(long)]
+    dedup_window_ms: Option<u64>,
+
+    /// Number of sensors whose last reading is kept for deduplication. Past it the sensor heard
+    /// from least recently is forgotten, and its next reading forwarded even if it repeats the
+    /// last one.
+    #[arg(long, default_value_t = 10_000, value_parser = clap::value_parser!(u64).range(1..))]
+    dedup_capacity: u64,
+
+    /// Publish synthetic `SensorData` readings of virtual sensors instead of test strings.
+    #[arg(long)]
+    simulate: bool,
+
+    /// Number of virtual sensors in simulation mode.
+    #[arg(long, default_value_t = 4, requires = "simulate")]
+    virtual_sensors: u32,
+
+    /// Seed of the simulation, for reproducible readings.
+    #[arg(long, default_value_t = 0, requires = "simulate")]
+    seed: u64,
+
+    /// Share of each delay between attempts to connect to the broker that is randomized, between
+    /// 0 and 1. With the default of 0.5 the client waits between half and all of the exponential
+    /// backoff delay, so clients that lost the broker together don't reconnect in lockstep.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_fraction)]
+    reconnect_jitter: f64,
+
+    /// Start a clean session with the broker. With `false` the broker keeps the subscriptions of
+    /// `MQTT_CLIENT_ID` and queues the QoS 1 and 2 messages published while it's disconnected, and
+    /// hands them over once the client reconnects, after the backoff of `--reconnect-jitter`.
+    /// With `true` they are dropped on disconnect and messages published meanwhile are never
+    /// delivered.
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    clean_session: bool,
+
+    /// Publish a retained `online` status for each sensor to this topic, with `{id}` replaced by
+    /// its id, e.g. `sensors/{id}/status`, and clear it when the sensor goes stale.
+    #[arg(long, value_parser = parse_topic_template)]
+    status_topic: Option<String>,
+
+    /// Seconds without a reading after which a sensor is stale and its status is cleared.
+    #[arg(long, default_value_t = 60, requires = "status_topic")]
+    status_timeout_secs: u64,
+
+    /// Publish at most this many messages per second, spacing them evenly, for brokers that
+    /// throttle or disconnect clients publishing in bursts. Unlimited by default.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_rate: Option<u32>,
+
+    /// Publish the health of the client as JSON, with its uptime, the number of messages it
+    /// handled and its last error, every this many seconds. Off by default.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    health_interval_secs: Option<u64>,
+
+    /// Topic the health of the client is published to, with `{id}` replaced by `MQTT_CLIENT_ID`.
+    #[arg(
+        long,
+        default_value = "clients/{id}/health",
+        value_parser = parse_topic_template,
+        requires = "health_interval_secs"
+    )]
+    health_topic: String,
+
+    /// Republish each payload that doesn't parse as a reading to this topic, as JSON with the
+    /// topic it came from, the payload in base64 and the parse error, to keep it for debugging.
+    #[arg(long)]
+    dead_letter_topic: Option<String>,
+}
+
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("`{}` is not a number between 0 and 1", s)),
+    }
+}
+
+fn parse_topic_template(s: &str) -> Result<String, String> {
+    match s.contains("{id}") {
+        true => Ok(s.to_string()),
+        false => Err(format!("`{}` doesn't contain `{{id}}`", s)),
+    }
+}
+
+/// Publishes through a shared `MqttClient`, waiting as long as `--max-rate` requires first. The
+/// client is swapped for a new one on every reconnect, for all clones of the publisher.
+#[derive(Clone)]
+struct Publisher {
+    client: Arc<Mutex<MqttClient>>,
+    limiter: Option<Arc<Mutex<RateLimiter>>>,
+}
+
+impl Publisher {
+    fn new(client: MqttClient, max_rate: Option<u32>) -> Self {
+        let limiter =
+            max_rate.map(|rate| Arc::new(Mutex::new(RateLimiter::new(rate, Instant::now()))));
+        Publisher {
+            client: Arc::new(Mutex::new(client)),
+            limiter,
+        }
+    }
+
+    /// Publish through `client` from now on, after reconnecting to the broker.
+    fn reconnected(&self, client: MqttClient) {
+        *self.client.lock().unwrap() = client;
+    }
+
+    fn publish(
+        &mut self,
+        topic: impl Into<String>,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<(), Box<ClientError>> {
+        if let Some(limiter) = &self.limiter {
+            // Wait without holding the lock: the token is taken, and other publishers wait for
+            // the next ones.
+            let delay = limiter.lock().unwrap().acquire(Instant::now());
+            thread::sleep(delay);
+        }
+        self.client
+            .lock()
+            .unwrap()
+            .publish(topic, qos, retain, payload)
+            .map_err(Box::new)
+    }
+}
+
+fn publish_status(publisher: &mut Publisher, status: Status) {
+    if let Err(e) = publisher.publish(status.topic, QoS::AtLeastOnce, true, status.payload) {
+        println!("Error: failed to publish sensor status: {:?}", e);
+    }
+}
+
+/// Publish `payload`, received on `source_topic` and unparseable because of `error`, to the
+/// dead-letter topic.
+fn publish_dead_letter(
+    dead_letter: &mut Option<(String, Publisher)>,
+    source_topic: &str,
+    payload: &[u8],
+    error: &str,
+) {
+    let Some((topic, publisher)) = dead_letter else {
+        return;
+    };
+    // A subscription covering the dead-letter topic would otherwise bring each envelope back as
+    // yet another unparseable payload.
+    if source_topic == topic {
+        return;
+    }
+    let envelope = Envelope::new(source_topic, payload, error);
+    if let Err(e) = publisher.publish(
+        topic.as_str(),
+        QoS::AtLeastOnce,
+        false,
+        envelope.to_payload(),
+    ) {
+        println!("Error: failed to publish dead letter: {:?}", e);
+    }
+}
+
+/// Options of the connection to the broker. rumqtt is kept from reconnecting by itself after a
+/// fixed delay: the notifications end when the connection is lost, and `main` reconnects through
+/// `connect_with_backoff` and its jitter instead.
+fn mqtt_options(clean_session: bool) -> MqttOptions {
+    MqttOptions::new(MQTT_CLIENT_ID, MQTT_ADDRESS, MQTT_PORT)
+        .set_clean_session(clean_session)
+        .set_reconnect_opts(ReconnectOptions::Never)
+}
 
-fn connect_to_mqtt() -> Result<(MqttClient, rumqtt::Receiver<Notification>), rumqtt::ConnectError> {
-    let mqtt_options = MqttOptions::new(MQTT_CLIENT_ID, MQTT_ADDRESS, MQTT_PORT);
-    MqttClient::start(mqtt_options)
+fn connect_to_mqtt(
+    clean_session: bool,
+) -> Result<(MqttClient, rumqtt::Receiver<Notification>), Box<rumqtt::ConnectError>> {
+    MqttClient::start(mqtt_options(clean_session)).map_err(Box::new)
+}
+
+/// Connect to the broker, retrying with a jittered exponential backoff until it succeeds.
+fn connect_with_backoff(
+    jitter: f64,
+    clean_session: bool,
+) -> (MqttClient, rumqtt::Receiver<Notification>) {
+    let mut backoff = Backoff::new(
+        RECONNECT_BASE_DELAY,
+        RECONNECT_MAX_DELAY,
+        jitter,
+        rand::random(),
+    );
+    loop {
+        match connect_to_mqtt(clean_session) {
+            Ok(connection) => return connection,
+            Err(e) => {
+                let delay = backoff.next_delay();
+                println!("Error: {:?}, retrying in {:?}", e, delay);
+                thread::sleep(delay);
+            }
+        }
+    }
+}
+
+/// Connect to the broker with `connect_with_backoff` and subscribe to the topics of the client.
+/// The simulated topics are subscribed to as well when `simulate` is set.
+fn connect(
+    jitter: f64,
+    clean_session: bool,
+    simulate: bool,
+) -> (MqttClient, rumqtt::Receiver<Notification>) {
+    let (mut mqtt_client, notifications) = connect_with_backoff(jitter, clean_session);
+    let mut topics = vec![MQTT_TOPIC];
+    if simulate {
+        topics.push(SIMULATED_TOPICS);
+    }
+    for topic in topics {
+        // Only fails if the connection is lost already, which ends the notifications and
+        // reconnects.
+        if let Err(e) = mqtt_client.subscribe(topic, QoS::AtLeastOnce) {
+            println!("Error: failed to subscribe to {}: {:?}", topic, e);
+        }
+    }
+    (mqtt_client, notifications)
 }
 
 fn main() {
-    match connect_to_mqtt() {
-        Ok((mut mqtt_client, notifications)) => {
-            mqtt_client.subscribe(MQTT_TOPIC, QoS::AtLeastOnce).unwrap();
-            let sleep_time = Duration::from_secs(1);
+    let args = Args::parse();
+
+    let (mqtt_client, mut notifications) =
+        connect(args.reconnect_jitter, args.clean_session, args.simulate);
+    let publisher = Publisher::new(mqtt_client, args.max_rate);
+    let sleep_time = Duration::from_secs(1);
 
-            thread::spawn(move || {
-                for i in 0..100 {
-                    let payload = format!("Publish: {}", i);
+    let mut liveness = args.status_topic.map(|topic| {
+        let timeout = Duration::from_secs(args.status_timeout_secs);
+        let liveness = Arc::new(Mutex::new(Liveness::new(topic, timeout)));
+        let expiring = Arc::clone(&liveness);
+        let mut status_client = publisher.clone();
+        thread::spawn(move || loop {
+            thread::sleep(sleep_time);
+
+            let stale = expiring.lock().unwrap().expire(Instant::now());
+            for status in stale {
+                publish_status(&mut status_client, status);
+            }
+        });
+        (liveness, publisher.clone())
+    });
+
+    let health = Arc::new(Mutex::new(Health::new(Instant::now())));
+    if let Some(secs) = args.health_interval_secs {
+        let topic = args.health_topic.replace("{id}", MQTT_CLIENT_ID);
+        let reporting = Arc::clone(&health);
+        let mut health_client = publisher.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(secs));
+
+            let report = reporting.lock().unwrap().report(Instant::now());
+            if let Err(e) =
+                health_client.publish(&topic, QoS::AtLeastOnce, false, report.to_payload())
+            {
+                println!("Error: failed to publish client health: {:?}", e);
+                reporting.lock().unwrap().error(format!("{:?}", e));
+            }
+        });
+    }
+
+    let mut dead_letter = args
+        .dead_letter_topic
+        .map(|topic| (topic, publisher.clone()));
+
+    if args.simulate {
+        // Number the readings from the current time so that they keep increasing across restarts
+        // of the client, and subscribers don't drop them as duplicates.
+        let first_seq = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_millis() as u64);
+        let mut simulator = Simulator::new(args.virtual_sensors, args.seed, first_seq);
+        let mut publisher = publisher.clone();
+        thread::spawn(move || loop {
+            thread::sleep(sleep_time);
+
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_millis() as u64);
+            for (topic, reading) in simulator.step(timestamp) {
+                if let Err(e) =
+                    publisher.publish(topic, QoS::AtLeastOnce, false, reading.to_payload())
+                {
+                    println!("Error: failed to publish simulated reading: {:?}", e);
+                }
+            }
+        });
+    } else {
+        let mut publisher = publisher.clone();
+        thread::spawn(move || {
+            for i in 0..100 {
+                let payload = format!("Publish: {}", i);
 
-                    thread::sleep(sleep_time);
+                thread::sleep(sleep_time);
 
-                    mqtt_client
-                        .publish(MQTT_TOPIC, QoS::AtLeastOnce, false, payload)
-                        .unwrap();
+                if let Err(e) = publisher.publish(MQTT_TOPIC, QoS::AtLeastOnce, false, payload) {
+                    println!("Error: failed to publish test message: {:?}", e);
                 }
-            });
+            }
+        });
+    }
 
-            for notification in notifications {
-                println!("Notification: {:?}", notification)
+    let mut bridge = Bridge::new(args.strict, args.max_failures);
+    let mut dedup = args
+        .dedup_window_ms
+        .map(|ms| Dedup::new(Duration::from_millis(ms), args.dedup_capacity as usize));
+    // The notifications end when the connection to the broker is lost, see `mqtt_options`.
+    loop {
+        for notification in notifications.iter() {
+            match notification {
+                Notification::Publish(publish) => {
+                    health.lock().unwrap().handled();
+                    match bridge.handle(&publish.topic_name, &publish.payload) {
+                        Ok(reading) => {
+                            if let Some((liveness, status_client)) = &mut liveness {
+                                let online = liveness
+                                    .lock()
+                                    .unwrap()
+                                    .seen(reading.data.id, Instant::now());
+                                if let Some(status) = online {
+                                    publish_status(status_client, status);
+                                }
+                            }
+                            let forward = dedup
+                                .as_mut()
+                                .map_or(Some(0), |d| d.forward(&reading.data, Instant::now()));
+                            if let Some(suppressed) = forward {
+                                if suppressed > 0 {
+                                    println!(
+                                        "Suppressed {} duplicate readings of sensor {} ({:?})",
+                                        suppressed, reading.data.id, reading.data.type_
+                                    );
+                                }
+                                println!("Reading: {:?}", reading);
+                            }
+                        }
+                        Err(PayloadError::Unparseable(error)) => {
+                            // Only an error in strict mode, otherwise it's unrelated traffic.
+                            if args.strict {
+                                health.lock().unwrap().error(format_args!(
+                                    "unparseable payload on topic {}",
+                                    publish.topic_name
+                                ));
+                            }
+                            publish_dead_letter(
+                                &mut dead_letter,
+                                &publish.topic_name,
+                                &publish.payload,
+                                &error,
+                            );
+                        }
+                        Err(PayloadError::Duplicate { id, seq }) => {
+                            println!("Dropped duplicate reading {} of sensor {}", seq, id)
+                        }
+                        Err(PayloadError::TooManyFailures { count, error }) => {
+                            publish_dead_letter(
+                                &mut dead_letter,
+                                &publish.topic_name,
+                                &publish.payload,
+                                &error,
+                            );
+                            eprintln!("Error: {} consecutive unparseable payloads", count);
+                            process::exit(1);
+                        }
+                    }
+                }
+                Notification::Disconnection => {
+                    health.lock().unwrap().error("disconnected from the broker");
+                    println!("Notification: {:?}", Notification::Disconnection);
+                }
+                notification => println!("Notification: {:?}", notification),
             }
         }
-        Err(e) => println!("Error: {:?}", e),
+
+        println!("Reconnecting to the broker");
+        let (mqtt_client, reconnected) =
+            connect(args.reconnect_jitter, args.clean_session, args.simulate);
+        publisher.reconnected(mqtt_client);
+        notifications = reconnected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean_session(args: &[&str]) -> bool {
+        let args = Args::try_parse_from([&["rust-mosquitto"], args].concat()).unwrap();
+        mqtt_options(args.clean_session).clean_session()
+    }
+
+    #[test]
+    fn reconnects_through_the_backoff_instead_of_rumqtt() {
+        for clean_session in [true, false] {
+            assert_eq!(
+                mqtt_options(clean_session).reconnect_opts(),
+                ReconnectOptions::Never
+            );
+        }
+    }
+
+    #[test]
+    fn clean_session_defaults_to_true() {
+        assert!(clean_session(&[]));
+    }
+
+    #[test]
+    fn status_topic_needs_an_id_placeholder() {
+        let parse = |topic| Args::try_parse_from(["rust-mosquitto", "--status-topic", topic]);
+        assert_eq!(
+            parse("sensors/{id}/status")
+                .unwrap()
+                .status_topic
+                .as_deref(),
+            Some("sensors/{id}/status")
+        );
+        assert!(parse("sensors/status").is_err());
+    }
+
+    #[test]
+    fn health_reporting_is_off_by_default() {
+        let args = Args::try_parse_from(["rust-mosquitto"]).unwrap();
+        assert_eq!(args.health_interval_secs, None);
+        assert_eq!(args.health_topic, "clients/{id}/health");
+        assert!(
+            Args::try_parse_from(["rust-mosquitto", "--health-topic", "clients/{id}/up"]).is_err()
+        );
+    }
+
+    #[test]
+    fn clean_session_can_be_turned_off() {
+        assert!(!clean_session(&["--clean-session", "false"]));
+        assert!(clean_session(&["--clean-session", "true"]));
     }
 }
@@ -0,0 +1,75 @@
+//! Pacing of publishes for brokers that throttle or disconnect chatty clients.
+
+use std::time::{Duration, Instant};
+
+/// Token bucket holding a single token, refilled at `rate` tokens per second.
+///
+/// Every publish takes a token. One that finds the bucket empty is scheduled for when its token
+/// will have been refilled, so publishes are spread `1 / rate` seconds apart and no window of a
+/// second ever sees more than `rate` of them, however bursty the callers are.
+pub struct RateLimiter {
+    rate: f64,
+    /// Tokens in the bucket at `updated`, negative for publishes already scheduled ahead.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Panics if `rate` is 0.
+    pub fn new(rate: u32, now: Instant) -> Self {
+        assert!(rate > 0, "the rate must be positive");
+        RateLimiter {
+            rate: rate.into(),
+            tokens: 1.0,
+            updated: now,
+        }
+    }
+
+    /// Take a token for a publish at `now`, returning how long to wait before sending it.
+    pub fn acquire(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(1.0) - 1.0;
+        self.updated = self.updated.max(now);
+        match self.tokens < 0.0 {
+            true => Duration::from_secs_f64(-self.tokens / self.rate),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreads_a_burst_at_the_configured_rate() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(10, start);
+
+        // 50 publishes all asked for at once, each sent once its delay is over.
+        let sent: Vec<Duration> = (0..50).map(|_| limiter.acquire(start)).collect();
+
+        assert_eq!(sent[0], Duration::ZERO);
+        for window in 0..5 {
+            let from = Duration::from_secs(window);
+            let in_window = sent
+                .iter()
+                .filter(|at| **at >= from && **at < from + Duration::from_secs(1))
+                .count();
+            assert_eq!(in_window, 10, "second {}", window);
+        }
+    }
+
+    #[test]
+    fn does_not_delay_publishes_under_the_rate() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(2, start);
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(limiter.acquire(at(0)), Duration::ZERO);
+        assert_eq!(limiter.acquire(at(500)), Duration::ZERO);
+        assert_eq!(limiter.acquire(at(1500)), Duration::ZERO);
+        // Idle time doesn't build up a burst.
+        assert_eq!(limiter.acquire(at(1750)), Duration::from_millis(250));
+    }
+}
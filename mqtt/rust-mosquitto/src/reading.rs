@@ -0,0 +1,75 @@
+//! MQTT payload of a sensor reading.
+
+use sensors_common::SensorData;
+use serde::{Deserialize, Serialize};
+
+/// A reading as published over MQTT: a `SensorData` in the JSON shape of the sensors API, with an
+/// optional sequence number alongside its fields.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reading {
+    #[serde(flatten)]
+    pub data: SensorData,
+    /// Sequence number set by the publisher, increasing with every reading it publishes, so that
+    /// a subscriber can drop the copies MQTT redelivers after a reconnect. Left out of the JSON,
+    /// and by the API, when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+}
+
+impl Reading {
+    /// Decode a single reading from an MQTT payload.
+    pub fn from_payload(payload: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(payload)
+    }
+
+    /// Encode the reading as an MQTT payload, in the same shape `from_payload` decodes.
+    pub fn to_payload(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Reading serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sensors_common::{Geolocation, Percent, SensorType, SensorValue};
+
+    #[test]
+    fn round_trips_through_a_payload() {
+        let reading = Reading {
+            data: SensorData {
+                id: 7,
+                type_: SensorType::Humidity,
+                geolocation: Geolocation {
+                    lat: 4581,
+                    lon: 1523,
+                },
+                value: SensorValue::Percent(Percent::from_percent(55)),
+                timestamp: 1680000000,
+                derived: false,
+                quality: Some(3),
+            },
+            seq: Some(12),
+        };
+
+        assert_eq!(
+            reading.to_payload(),
+            br#"{"id":7,"type_":"Humidity","geolocation":{"lat":4581,"lon":1523},"value":"55","timestamp":1680000000,"derived":false,"quality":3,"seq":12}"#
+        );
+        assert_eq!(
+            Reading::from_payload(&reading.to_payload()).unwrap(),
+            reading
+        );
+    }
+
+    #[test]
+    fn accepts_the_api_value_formats() {
+        let reading = Reading::from_payload(
+            br#"{"id":1,"type_":"Temperature","geolocation":{"lat":10,"lon":20},"value":21,"timestamp":"2023-03-28T02:00:00+02:00"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(reading.data.value, SensorValue::Number(21));
+        assert_eq!(reading.data.timestamp, 1_679_961_600_000);
+        assert_eq!(reading.seq, None);
+    }
+}
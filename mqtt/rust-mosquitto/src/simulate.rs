@@ -0,0 +1,149 @@
+//! Synthetic sensor readings for load testing without hardware.
+
+use crate::reading::Reading;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sensors_common::{Geolocation, Percent, SensorData, SensorType, SensorValue};
+
+const TYPES: [SensorType; 4] = [
+    SensorType::Temperature,
+    SensorType::Humidity,
+    SensorType::Pressure,
+    SensorType::Digital,
+];
+
+/// Chance of a digital sensor toggling between two readings.
+const TOGGLE_PROBABILITY: f64 = 0.1;
+
+/// A virtual device and the value it last reported.
+struct VirtualSensor {
+    id: u32,
+    type_: SensorType,
+    geolocation: Geolocation,
+    value: SensorValue,
+}
+
+/// Generates readings of `count` virtual sensors, cycling through the sensor types.
+///
+/// Numeric values follow a bounded random walk, in °C for temperatures, % for humidity and Pa for
+/// pressure; digital values toggle now and then. The same seed gives the same readings.
+///
+/// Readings are numbered from `first_seq` on, in the order they're generated.
+pub struct Simulator {
+    rng: StdRng,
+    sensors: Vec<VirtualSensor>,
+    next_seq: u64,
+}
+
+impl Simulator {
+    pub fn new(count: u32, seed: u64, first_seq: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let sensors = (0..count)
+            .map(|id| {
+                let type_ = TYPES[id as usize % TYPES.len()].clone();
+                let value = match type_ {
+                    SensorType::Temperature => SensorValue::Number(rng.gen_range(15..=25)),
+                    SensorType::Humidity => {
+                        SensorValue::Percent(Percent::from_percent(rng.gen_range(30..=60)))
+                    }
+                    SensorType::Pressure => SensorValue::Number(rng.gen_range(100_000..=102_000)),
+                    SensorType::Digital => SensorValue::Bool(rng.gen()),
+                    SensorType::HeatIndex | SensorType::Custom(_) => unreachable!("not in TYPES"),
+                };
+                VirtualSensor {
+                    id,
+                    type_,
+                    geolocation: Geolocation {
+                        lat: rng.gen_range(-Geolocation::MAX_LAT..=Geolocation::MAX_LAT),
+                        lon: rng.gen_range(-Geolocation::MAX_LON..=Geolocation::MAX_LON),
+                    },
+                    value,
+                }
+            })
+            .collect();
+
+        Simulator {
+            rng,
+            sensors,
+            next_seq: first_seq,
+        }
+    }
+
+    /// Advance every sensor by one step and return its `(topic, reading)`.
+    pub fn step(&mut self, timestamp: u64) -> Vec<(String, Reading)> {
+        let rng = &mut self.rng;
+        let next_seq = &mut self.next_seq;
+        self.sensors
+            .iter_mut()
+            .map(|sensor| {
+                sensor.value = match (&sensor.type_, sensor.value) {
+                    (SensorType::Temperature, SensorValue::Number(x)) => {
+                        SensorValue::Number(walk(rng, x, 1, 0, 45))
+                    }
+                    (SensorType::Humidity, SensorValue::Percent(p)) => {
+                        let x = walk(rng, p.deconstruct().into(), 2, 0, 100);
+                        SensorValue::Percent(Percent::from_percent(x as u8))
+                    }
+                    (SensorType::Pressure, SensorValue::Number(x)) => {
+                        SensorValue::Number(walk(rng, x, 50, 95_000, 105_000))
+                    }
+                    (_, SensorValue::Bool(b)) => {
+                        SensorValue::Bool(b ^ rng.gen_bool(TOGGLE_PROBABILITY))
+                    }
+                    (_, value) => value,
+                };
+
+                let topic = format!("sensors/{}/{:?}", sensor.id, sensor.type_).to_lowercase();
+                let reading = Reading {
+                    data: SensorData {
+                        id: sensor.id,
+                        type_: sensor.type_.clone(),
+                        geolocation: sensor.geolocation,
+                        value: sensor.value,
+                        timestamp,
+                        derived: false,
+                        quality: None,
+                    },
+                    seq: Some(*next_seq),
+                };
+                *next_seq += 1;
+                (topic, reading)
+            })
+            .collect()
+    }
+}
+
+/// Move `value` by at most `step` in either direction, staying within `min..=max`.
+fn walk(rng: &mut StdRng, value: u32, step: u32, min: u32, max: u32) -> u32 {
+    let delta = rng.gen_range(0..=2 * step);
+    (value + delta).saturating_sub(step).clamp(min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_payloads_deserialize_as_readings() {
+        let mut simulator = Simulator::new(8, 42, 0);
+
+        for timestamp in 0..10 {
+            for (topic, reading) in simulator.step(timestamp) {
+                assert_eq!(
+                    Reading::from_payload(&reading.to_payload()).unwrap(),
+                    reading
+                );
+                assert!(topic.starts_with(&format!("sensors/{}/", reading.data.id)));
+            }
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_readings() {
+        let mut a = Simulator::new(4, 7, 0);
+        let mut b = Simulator::new(4, 7, 0);
+
+        for timestamp in 0..5 {
+            assert_eq!(a.step(timestamp), b.step(timestamp));
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Off-chain mirror of the `sensors-oracle` offchain worker: polls the sensors API, checks the
+//! readings decode, and forwards them to an ingestion endpoint. Lets the pipeline be exercised
+//! without running a node.
+
+use clap::Parser;
+use reqwest::Client;
+use sensors_common::SensorData;
+use std::{fmt, time::Duration};
+
+const SENSORS_API: &str = "https://sensors-api.vercel.app/api/v1/sensors";
+
+#[derive(Parser, Debug)]
+#[command(about = "Polls the sensors API and forwards the readings to an ingestion endpoint")]
+struct Args {
+    /// Sensors API to poll.
+    #[arg(long, default_value = SENSORS_API)]
+    api_url: String,
+
+    /// Endpoint the decoded readings are POSTed to, as a JSON array.
+    #[arg(long)]
+    ingest_url: String,
+
+    /// Seconds between two polls.
+    #[arg(long, default_value_t = 6)]
+    interval_secs: u64,
+}
+
+#[derive(Debug)]
+enum PollError {
+    Http(reqwest::Error),
+    Decode(serde_json::Error),
+}
+
+impl fmt::Display for PollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PollError::Http(e) => write!(f, "HTTP request failed: {}", e),
+            PollError::Decode(e) => write!(f, "undecodable sensors API response: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for PollError {
+    fn from(e: reqwest::Error) -> Self {
+        PollError::Http(e)
+    }
+}
+
+/// Fetch the readings from `api_url` and POST them to `ingest_url`, returning how many were
+/// forwarded. Nothing is forwarded if the response doesn't decode.
+async fn poll_once(client: &Client, api_url: &str, ingest_url: &str) -> Result<usize, PollError> {
+    let body = client
+        .get(api_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let readings: Vec<SensorData> = serde_json::from_slice(&body).map_err(PollError::Decode)?;
+
+    client
+        .post(ingest_url)
+        .json(&readings)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(readings.len())
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let client = Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .expect("HTTP client configuration is valid");
+
+    let mut interval = tokio::time::interval(Duration::from_secs(args.interval_secs));
+    loop {
+        interval.tick().await;
+        match poll_once(&client, &args.api_url, &args.ingest_url).await {
+            Ok(count) => println!("Forwarded {} readings", count),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sensors_common::{Geolocation, Percent, SensorType, SensorValue};
+
+    const RESPONSE: &[u8] = br#"[
+        {"id":1,"type_":"Temperature","geolocation":{"lat":10,"lon":20},"value":"21","timestamp":1680000000},
+        {"id":2,"type_":"Digital","geolocation":{"lat":11,"lon":21},"value":"true","timestamp":1680000001},
+        {"id":3,"type_":"Humidity","geolocation":{"lat":12,"lon":22},"value":45,"timestamp":"2023-03-28T10:40:01Z","quality":2}
+    ]"#;
+
+    fn parse(body: &[u8]) -> Vec<SensorData> {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    #[test]
+    fn parses_a_sample_response() {
+        let readings = parse(RESPONSE);
+
+        assert_eq!(
+            readings,
+            vec![
+                SensorData {
+                    id: 1,
+                    type_: SensorType::Temperature,
+                    geolocation: Geolocation { lat: 10, lon: 20 },
+                    value: SensorValue::Number(21),
+                    timestamp: 1680000000,
+                    derived: false,
+                    quality: None,
+                },
+                SensorData {
+                    id: 2,
+                    type_: SensorType::Digital,
+                    geolocation: Geolocation { lat: 11, lon: 21 },
+                    value: SensorValue::Bool(true),
+                    timestamp: 1680000001,
+                    derived: false,
+                    quality: None,
+                },
+                SensorData {
+                    id: 3,
+                    type_: SensorType::Humidity,
+                    geolocation: Geolocation { lat: 12, lon: 22 },
+                    value: SensorValue::Percent(Percent::from_percent(45)),
+                    timestamp: 1_680_000_001_000,
+                    derived: false,
+                    quality: Some(2),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn forwarded_readings_keep_the_api_shape() {
+        let readings = parse(RESPONSE);
+
+        let forwarded = serde_json::to_vec(&readings).unwrap();
+
+        assert_eq!(parse(&forwarded), readings);
+    }
+}
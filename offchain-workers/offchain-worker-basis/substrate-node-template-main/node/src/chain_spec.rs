@@ -154,5 +154,6 @@ fn testnet_genesis(
 			key: Some(root_key),
 		},
 		transaction_payment: Default::default(),
+		sensors_oracle: Default::default(),
 	}
 }
@@ -3,6 +3,7 @@ use frame_support::pallet_macros::*;
 #[pallet_section]
 mod calls {
 
+	use frame_support::storage::{with_transaction, TransactionOutcome};
 	use frame_system;
 	use sp_std::vec::Vec;
 
@@ -10,29 +11,137 @@ mod calls {
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         #[pallet::call_index(0)]
-        #[pallet::weight((0, Pays::No))]
+        #[pallet::weight((
+            Pallet::<T>::update_sensors_data_weight(
+                updated_data.len() as u32,
+                updated_data.len() as u32,
+                0,
+                0,
+            ),
+            Pays::No
+        ))]
         pub fn update_sensors_data(
             origin: OriginFor<T>,
             updated_data: Vec<SensorData>,
+            checksum: [u8; 32],
         ) -> DispatchResultWithPostInfo {
             // Retrieve sender of the transaction.
             let who = ensure_signed(origin)?;
 
+            // Catch batches mangled between the offchain worker and inclusion.
+            ensure!(
+                Self::readings_checksum(&updated_data) == checksum,
+                Error::<T>::ChecksumMismatch
+            );
+
             match Self::is_authority(&who) {
                 true => {
-                    for sensor in updated_data {
-                        Self::add_sensor_data(sensor);
+                    <LastSubmission<T>>::insert(&who, <frame_system::Pallet<T>>::block_number());
+                    // A reading listed twice would be counted twice by the debounce and the
+                    // rejection counts.
+                    let mut updated_data = updated_data;
+                    let collapsed = Self::dedup_readings(&mut updated_data);
+                    if collapsed > 0 {
+                        log::warn!("Collapsed {} duplicate readings in a batch", collapsed);
+                    }
+                    let verbosity = Self::event_verbosity();
+                    let latest = Self::latest_timestamp();
+                    let readings = updated_data.len() as u32;
+                    let atomic = Self::atomic_batches();
+                    // Stable, so readings of the same priority keep the order they were sent in.
+                    updated_data.sort_by_cached_key(|sensor| {
+                        core::cmp::Reverse(Self::type_priority(&sensor.type_))
+                    });
+                    // Readings are validated against the state left by the ones before them in
+                    // the batch, so an all-or-nothing batch is stored in a storage transaction,
+                    // rolled back if any of its readings is rejected or skipped.
+                    let batch = with_transaction(|| {
+                        let (mut count, mut rejections, mut freshness) = (0, Vec::new(), 0);
+                        let (mut skipped, mut held) = (0, 0);
+                        let mut remaining = Self::remaining_writes();
+                        for (processed, sensor) in updated_data.into_iter().enumerate() {
+                            match Self::validate_reading(&sensor) {
+                                Ok(()) if Self::is_duplicate(&sensor) => (),
+                                Ok(()) if remaining == 0 => {
+                                    skipped = readings - processed as u32;
+                                    break;
+                                }
+                                Ok(()) => {
+                                    let timestamp = sensor.timestamp;
+                                    // Readings held back by the debounce aren't accepted yet.
+                                    if !Self::add_sensor_data(sensor) {
+                                        held += 1;
+                                        continue;
+                                    }
+                                    freshness = u64::saturating_add(
+                                        freshness,
+                                        Self::reading_freshness(latest, timestamp).into(),
+                                    );
+                                    count += 1;
+                                    remaining -= 1;
+                                }
+                                Err(reason) => {
+                                    if !atomic {
+                                        Self::reject_reading(&sensor, reason, verbosity);
+                                    }
+                                    rejections.push((sensor, reason));
+                                }
+                            }
+                        }
+                        <BlockWrites<T>>::put((
+                            <frame_system::Pallet<T>>::block_number(),
+                            T::MaxWritesPerBlock::get().saturating_sub(remaining),
+                        ));
+                        let rollback = atomic && (!rejections.is_empty() || skipped > 0);
+                        let outcome =
+                            Ok::<_, DispatchError>((count, rejections, freshness, skipped, held));
+                        match rollback {
+                            true => TransactionOutcome::Rollback(outcome),
+                            false => TransactionOutcome::Commit(outcome),
+                        }
+                    })?;
+                    let (mut count, rejections, mut freshness, skipped, held) = batch;
+                    let rolled_back = atomic && (!rejections.is_empty() || skipped > 0);
+                    if rolled_back {
+                        // Reported once rolled back, or their events and counts would be too.
+                        (count, freshness) = (0, 0);
+                        for (sensor, reason) in &rejections {
+                            Self::reject_reading(sensor, *reason, verbosity);
+                        }
                     }
+                    if skipped > 0 {
+                        // None of the readings of a batch rolled back are processed.
+                        let processed = if rolled_back { 0 } else { readings - skipped };
+                        Self::deposit_event(Event::BatchPartiallyProcessed {
+                            processed,
+                            skipped: readings - processed,
+                        });
+                    }
+                    let rejected = rejections.len() as u32;
+                    if verbosity.per_batch() {
+                        Self::deposit_event(Event::SensorsDataUpdated {
+                            count,
+                            seq: Self::next_event_seq(),
+                        });
+                    }
+                    Self::update_trust_score(&who, count, rejected, freshness);
+                    <LastBatchResult<T>>::insert(
+                        &who,
+                        BatchResult { accepted: count, rejected },
+                    );
+
+                    // Authorized OCWs don't need to pay fees
+                    let weight =
+                        Self::update_sensors_data_weight(readings, count, rejected, held);
+                    Ok((Some(weight), Pays::No).into())
                 }
-                false => return Err(Error::<T>::NotAuthority.into()),
+                false => Err(Error::<T>::NotAuthority.into()),
             }
-
-            // Authorized OCWs don't need to pay fees
-            Ok(Pays::No.into())
         }
 
         #[pallet::call_index(1)]
-        #[pallet::weight(0)]
+        // The authorities, then them and the last submission of the authority.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 2))]
         pub fn add_authority(
             origin: OriginFor<T>,
             authority: T::AccountId,
@@ -44,21 +153,13 @@ mod calls {
                 Error::<T>::AlreadyAuthority
             );
 
-            let mut authorities = <Authorities<T>>::get();
-            match authorities.try_push(authority.clone()) {
-                Ok(()) => (),
-                Err(_) => return Err(Error::<T>::TooManyAuthorities.into()),
-            };
-
-            Authorities::<T>::set(authorities);
-
-            Self::deposit_event(Event::AuthorityAdded { authority });
+            Self::ensure_authority(&authority)?;
 
             Ok(().into())
         }
 
         #[pallet::call_index(2)]
-        #[pallet::weight(0)]
+        #[pallet::weight(Pallet::<T>::remove_authority_weight(1))]
         pub fn remove_authority(
             origin: OriginFor<T>,
             authority: T::AccountId,
@@ -72,12 +173,501 @@ mod calls {
                 Some(index) => authorities.swap_remove(index),
                 None => return Err(Error::<T>::NotAuthority.into()),
             };
+            ensure!(
+                authorities.len() as u32 >= T::MinAuthorities::get(),
+                Error::<T>::BelowMinAuthorities
+            );
+
+            Authorities::<T>::set(authorities);
+            Self::forget_authority(&authority);
+
+            Self::deposit_event(Event::AuthorityRemoved { authority });
+            Self::clamp_confirmation_threshold();
+
+            Ok(().into())
+        }
+
+        #[pallet::call_index(3)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_confirmation_threshold(
+            origin: OriginFor<T>,
+            threshold: u32,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            let authorities = <Authorities<T>>::decode_len().unwrap_or(0) as u32;
+            ensure!(
+                (1..=authorities).contains(&threshold),
+                Error::<T>::InvalidConfirmationThreshold
+            );
+
+            ConfirmationThreshold::<T>::put(threshold);
+
+            Self::deposit_event(Event::ThresholdChanged { threshold });
+
+            Ok(().into())
+        }
+
+        #[pallet::call_index(4)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_sensor_metadata(
+            origin: OriginFor<T>,
+            id: SensorIdOf,
+            info: SensorInfo,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            SensorMetadata::<T>::insert(id, info);
+
+            Self::deposit_event(Event::SensorMetadataSet { id });
+
+            Ok(().into())
+        }
+
+        /// Check that storage and events work by writing a synthetic reading for
+        /// `SELF_TEST_SENSOR_ID` through the normal ingestion path and reading it back.
+        #[pallet::call_index(5)]
+        // The reading stored like a written reading of a batch, then taking it back with its
+        // history, the latest reading of its type and the reading count.
+        #[pallet::weight(T::DbWeight::get().reads_writes(12, 11))]
+        pub fn run_self_test(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            match Self::self_test() {
+                true => Self::deposit_event(Event::SelfTestPassed),
+                false => Self::deposit_event(Event::SelfTestFailed),
+            }
+
+            Ok(().into())
+        }
+
+        /// Set which events are emitted for incoming readings.
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_event_verbosity(
+            origin: OriginFor<T>,
+            verbosity: EventVerbosity,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            Verbosity::<T>::put(verbosity);
+
+            Self::deposit_event(Event::EventVerbosityChanged { verbosity });
+
+            Ok(().into())
+        }
+
+        /// Reset the number of rejected readings of every reason to zero.
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::DbWeight::get().writes(RejectReason::ALL.len() as u64))]
+        pub fn reset_rejection_counts(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            // Keyed by reason, so there is at most one count per reason to remove.
+            for reason in RejectReason::ALL {
+                RejectionCounts::<T>::remove(reason);
+            }
+
+            Self::deposit_event(Event::RejectionCountsReset);
+
+            Ok(().into())
+        }
+
+        /// Set the bounds outside of which readings of a sensor raise an alert. Clears them if
+        /// both are `None`.
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_alert_threshold(
+            origin: OriginFor<T>,
+            id: SensorIdOf,
+            type_: SensorType,
+            low: Option<u32>,
+            high: Option<u32>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            match (low, high) {
+                (None, None) => AlertThresholds::<T>::remove(id, &type_),
+                bounds => AlertThresholds::<T>::insert(id, &type_, bounds),
+            }
+
+            Self::deposit_event(Event::AlertThresholdSet { id, type_ });
+
+            Ok(().into())
+        }
+
+        #[pallet::call_index(9)]
+        // The authorities, then them and the last submission of every added authority.
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1 + authorities.len() as u64))]
+        pub fn add_authorities(
+            origin: OriginFor<T>,
+            authorities: Vec<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            let mut current = <Authorities<T>>::get();
+            for authority in &authorities {
+                ensure!(!current.contains(authority), Error::<T>::AlreadyAuthority);
+                Self::push_authority(&mut current, authority)?;
+            }
+            Authorities::<T>::set(current);
+
+            Ok(().into())
+        }
+
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_require_registration(
+            origin: OriginFor<T>,
+            required: bool,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            RequireRegistration::<T>::put(required);
+
+            Self::deposit_event(Event::RequireRegistrationSet { required });
+
+            Ok(().into())
+        }
+
+        /// Store batches all-or-nothing, see `AtomicBatches`.
+        #[pallet::call_index(16)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_atomic_batches(
+            origin: OriginFor<T>,
+            atomic: bool,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            AtomicBatches::<T>::put(atomic);
+
+            Self::deposit_event(Event::AtomicBatchesSet { atomic });
+
+            Ok(().into())
+        }
+
+        /// Set the priority of the readings of `type_`, see `TypePriority`. A `priority` of 0
+        /// removes the entry.
+        #[pallet::call_index(18)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_type_priority(
+            origin: OriginFor<T>,
+            type_: SensorType,
+            priority: u8,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            match priority {
+                0 => TypePriority::<T>::remove(&type_),
+                _ => TypePriority::<T>::insert(&type_, priority),
+            }
+
+            Self::deposit_event(Event::TypePrioritySet { type_, priority });
+
+            Ok(().into())
+        }
+
+        /// Set the endpoint the readings of `type_` are fetched from, see `TypeEndpoints`. `None`
+        /// fetches them from `DefaultApiEndpoint` again.
+        ///
+        /// The endpoint has to be an `http` or `https` URL, see `is_http_url`, of at most
+        /// `MAX_ENDPOINT_LEN` bytes, so that a typo doesn't leave the worker unable to fetch.
+        #[pallet::call_index(19)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_type_endpoint(
+            origin: OriginFor<T>,
+            type_: SensorType,
+            endpoint: Option<Vec<u8>>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            let endpoint = match endpoint {
+                Some(url) => {
+                    ensure!(
+                        core::str::from_utf8(&url).is_ok_and(is_http_url),
+                        Error::<T>::InvalidEndpoint
+                    );
+                    let url: BoundedVec<u8, ConstU32<MAX_ENDPOINT_LEN>> =
+                        url.try_into().map_err(|_| Error::<T>::InvalidEndpoint)?;
+                    TypeEndpoints::<T>::insert(&type_, &url);
+                    Some(url)
+                }
+                None => {
+                    TypeEndpoints::<T>::remove(&type_);
+                    None
+                }
+            };
+
+            Self::deposit_event(Event::TypeEndpointSet { type_, endpoint });
+
+            Ok(().into())
+        }
+
+        /// Set the interval at which a sensor is expected to report, see `is_stale`. `None` falls
+        /// back to `DefaultExpectedInterval`.
+        #[pallet::call_index(17)]
+        #[pallet::weight(T::DbWeight::get().writes(1))]
+        pub fn set_expected_interval(
+            origin: OriginFor<T>,
+            id: SensorIdOf,
+            type_: SensorType,
+            interval: Option<u64>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            match interval {
+                Some(interval) => ExpectedInterval::<T>::insert(id, &type_, interval),
+                None => ExpectedInterval::<T>::remove(id, &type_),
+            }
+
+            Self::deposit_event(Event::ExpectedIntervalSet { id, type_, interval });
+
+            Ok(().into())
+        }
+
+        /// Only store a new state of a digital sensor once it's been reported in `count`
+        /// consecutive submissions. A `count` of 0 or 1 stores every state change right away.
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::DbWeight::get().writes(2))]
+        pub fn set_debounce(
+            origin: OriginFor<T>,
+            id: SensorIdOf,
+            count: u32,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            match count {
+                0 | 1 => DebounceCounts::<T>::remove(id),
+                count => DebounceCounts::<T>::insert(id, count),
+            }
+            DigitalDebounce::<T>::remove(id);
+
+            Self::deposit_event(Event::DebounceSet { id, count });
+
+            Ok(().into())
+        }
+
+        /// Remove every account of `authorities` that is an authority, skipping the others.
+        /// Fails without removing any if fewer than `MinAuthorities` would be left.
+        #[pallet::call_index(12)]
+        #[pallet::weight(Pallet::<T>::remove_authority_weight(authorities.len() as u32))]
+        pub fn remove_authorities(
+            origin: OriginFor<T>,
+            authorities: Vec<T::AccountId>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            let mut current = <Authorities<T>>::get();
+            let mut removed = Vec::new();
+            for authority in authorities {
+                if let Some(index) = current.iter().position(|a| a == &authority) {
+                    current.swap_remove(index);
+                    removed.push(authority);
+                }
+            }
+            ensure!(
+                current.len() as u32 >= T::MinAuthorities::get(),
+                Error::<T>::BelowMinAuthorities
+            );
+            Authorities::<T>::set(current);
+
+            for authority in removed {
+                Self::forget_authority(&authority);
+                Self::deposit_event(Event::AuthorityRemoved { authority });
+            }
+            Self::clamp_confirmation_threshold();
+
+            Ok(().into())
+        }
+
+        /// Store a reading signed by at least `ConfirmationThreshold` authorities, as
+        /// `(public key, signature)` pairs over `signed_reading_payload(reading)`. Anyone can
+        /// submit it, since the signatures are what authorize the reading.
+        ///
+        /// Fails if a signature is invalid, doesn't come from an authority, or comes from an
+        /// authority that already signed. The reading is then validated like the ones of
+        /// `update_sensors_data`, and counts against `MaxWritesPerBlock` like them.
+        #[pallet::call_index(13)]
+        #[pallet::weight(Pallet::<T>::submit_with_signatures_weight(signatures.len() as u32))]
+        pub fn submit_with_signatures(
+            origin: OriginFor<T>,
+            reading: SensorData,
+            signatures: BoundedVec<(T::Public, T::Signature), T::MaxAuthorities>,
+        ) -> DispatchResultWithPostInfo {
+            ensure_signed(origin)?;
+
+            let payload = Self::signed_reading_payload(&reading);
+            let mut signers: Vec<T::AccountId> = Vec::new();
+            for (public, signature) in signatures {
+                ensure!(
+                    <T::AuthorityId as AppCrypto<T::Public, T::Signature>>::verify(
+                        &payload,
+                        public.clone(),
+                        signature
+                    ),
+                    Error::<T>::InvalidSignature
+                );
+                let signer = public.into_account();
+                ensure!(Self::is_authority(&signer), Error::<T>::NotAuthority);
+                ensure!(!signers.contains(&signer), Error::<T>::DuplicateSignature);
+                signers.push(signer);
+            }
+            ensure!(
+                signers.len() as u32 >= Self::confirmation_threshold(),
+                Error::<T>::NotEnoughSignatures
+            );
+
+            match Self::validate_reading(&reading) {
+                Ok(()) if Self::is_duplicate(&reading) => (),
+                Ok(()) => {
+                    let remaining = Self::remaining_writes();
+                    ensure!(remaining >= 1, Error::<T>::BlockWritesExhausted);
+                    Self::add_sensor_data(reading);
+                    <BlockWrites<T>>::put((
+                        <frame_system::Pallet<T>>::block_number(),
+                        T::MaxWritesPerBlock::get().saturating_sub(remaining - 1),
+                    ));
+                }
+                Err(reason) => Self::reject_reading(&reading, reason, Self::event_verbosity()),
+            }
+
+            Ok(().into())
+        }
+
+        /// Store the channels of a multi-channel reading of an authority, all of them or none if
+        /// any is rejected. They are validated like the readings of `update_sensors_data`, and
+        /// those already stored are skipped.
+        #[pallet::call_index(20)]
+        #[pallet::weight((
+            Pallet::<T>::submit_multi_reading_weight(
+                reading.channels.len() as u32,
+                reading.channels.len() as u32,
+                0,
+            ),
+            Pays::No
+        ))]
+        pub fn submit_multi_reading(
+            origin: OriginFor<T>,
+            reading: MultiReading,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Self::is_authority(&who), Error::<T>::NotAuthority);
+            ensure!(
+                reading.has_distinct_channels(),
+                Error::<T>::InvalidMultiReading
+            );
+            <LastSubmission<T>>::insert(&who, <frame_system::Pallet<T>>::block_number());
+
+            let channels = reading.channels.len() as u32;
+            let verbosity = Self::event_verbosity();
+            let rejections: Vec<_> = reading
+                .readings()
+                .filter_map(|sensor| Some((Self::validate_reading(&sensor).err()?, sensor)))
+                .collect();
+            if !rejections.is_empty() {
+                for (reason, sensor) in &rejections {
+                    Self::reject_reading(sensor, *reason, verbosity);
+                }
+                let rejected = rejections.len() as u32;
+                let weight = Self::submit_multi_reading_weight(channels, 0, rejected);
+                return Ok((Some(weight), Pays::No).into());
+            }
+
+            let fresh: Vec<_> =
+                reading.readings().filter(|sensor| !Self::is_duplicate(sensor)).collect();
+            let written = fresh.len() as u32;
+            let remaining = Self::remaining_writes();
+            ensure!(remaining >= written, Error::<T>::BlockWritesExhausted);
+            if written > 0 {
+                for sensor in fresh {
+                    Self::add_sensor_data(sensor);
+                }
+                <BlockWrites<T>>::put((
+                    <frame_system::Pallet<T>>::block_number(),
+                    T::MaxWritesPerBlock::get().saturating_sub(remaining - written),
+                ));
+                if verbosity.per_batch() {
+                    Self::deposit_event(Event::MultiReadingAdded {
+                        id: reading.id,
+                        channels: written,
+                    });
+                }
+                <MultiReadings<T>>::insert(reading.id, reading);
+            }
+
+            let weight = Self::submit_multi_reading_weight(channels, written, 0);
+            Ok((Some(weight), Pays::No).into())
+        }
+
+        /// Propose the removal of `candidate`, an authority that hasn't submitted a batch for
+        /// more than `SilenceThreshold` blocks, for root to ratify with `ratify_remove_authority`.
+        /// Sent by the offchain workers of the other authorities, each with at most one proposal
+        /// pending.
+        #[pallet::call_index(14)]
+        // The authorities, the proposal of the candidate and of the proposer and the last
+        // submission of the candidate, then both proposals.
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 2))]
+        pub fn propose_remove_authority(
+            origin: OriginFor<T>,
+            candidate: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            ensure!(Self::is_authority(&who), Error::<T>::NotAuthority);
+            ensure!(Self::is_authority(&candidate), Error::<T>::NotAuthority);
+            ensure!(
+                !RemovalProposals::<T>::contains_key(&candidate),
+                Error::<T>::RemovalAlreadyProposed
+            );
+            ensure!(
+                !PendingProposals::<T>::contains_key(&who),
+                Error::<T>::ProposalPending
+            );
+            ensure!(
+                Self::is_silent(&candidate, <frame_system::Pallet<T>>::block_number()),
+                Error::<T>::AuthorityNotSilent
+            );
+
+            RemovalProposals::<T>::insert(&candidate, &who);
+            PendingProposals::<T>::insert(&who, &candidate);
+
+            Self::deposit_event(Event::RemovalProposed { candidate, proposer: who });
+
+            Ok(().into())
+        }
+
+        /// Remove an authority proposed for removal with `propose_remove_authority`.
+        #[pallet::call_index(15)]
+        #[pallet::weight(Pallet::<T>::remove_authority_weight(1))]
+        pub fn ratify_remove_authority(
+            origin: OriginFor<T>,
+            candidate: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            ensure_root(origin)?;
+
+            ensure!(
+                RemovalProposals::<T>::contains_key(&candidate),
+                Error::<T>::NoRemovalProposal
+            );
+
+            let mut authorities = <Authorities<T>>::get();
+            match authorities.iter().position(|a| a == &candidate) {
+                Some(index) => authorities.swap_remove(index),
+                None => return Err(Error::<T>::NotAuthority.into()),
+            };
+            ensure!(
+                authorities.len() as u32 >= T::MinAuthorities::get(),
+                Error::<T>::BelowMinAuthorities
+            );
 
             Authorities::<T>::set(authorities);
+            Self::forget_authority(&candidate);
 
-            Self::deposit_event(Event::AuthorityAdded { authority });
+            Self::deposit_event(Event::AuthorityRemoved { authority: candidate });
+            Self::clamp_confirmation_threshold();
 
             Ok(().into())
         }
     }
-}
\ No newline at end of file
+}
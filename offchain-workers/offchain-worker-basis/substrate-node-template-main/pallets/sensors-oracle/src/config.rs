@@ -22,8 +22,75 @@ mod config {
         #[pallet::constant]
         type GracePeriod: Get<BlockNumberFor<Self>>;
 
+        /// Number of blocks an authority can go without submitting a batch before the offchain
+        /// workers of the other authorities propose its removal with `propose_remove_authority`.
+        #[pallet::no_default]
+        #[pallet::constant]
+        type SilenceThreshold: Get<BlockNumberFor<Self>>;
+
+        /// Endpoint of the sensors API the offchain worker fetches the latest readings of every
+        /// sensor from, e.g. `SENSORS_API_URL`. The readings of a single sensor are served under
+        /// `<endpoint>/<id>`, in the same format.
+        #[pallet::no_default]
+        type DefaultApiEndpoint: Get<&'static str>;
+
         /// Maximum number of authorities.
         #[pallet::constant]
         type MaxAuthorities: Get<u32>;
+
+        /// Minimum number of authorities `remove_authority` and `remove_authorities` leave in
+        /// place, so governance can't leave the oracle without anyone to update it. 0 allows
+        /// removing every authority.
+        #[pallet::constant]
+        type MinAuthorities: Get<u32>;
+
+        /// Maximum number of readings kept in the history of each sensor.
+        #[pallet::constant]
+        type MaxHistory: Get<u32>;
+
+        /// Maximum number of readings returned by a single `sensor_history_paged` call.
+        #[pallet::constant]
+        type MaxHistoryPage: Get<u32>;
+
+        /// How far back, in the units of reading timestamps, `on_idle` keeps readings in
+        /// `SensorHistory`. Older readings are pruned relative to the newest reading stored.
+        #[pallet::constant]
+        type HistoryRetention: Get<u64>;
+
+        /// Whether `on_idle` also removes the latest reading of a sensor from `Sensors` once it's
+        /// older than `HistoryRetention`, so queries don't return readings of dead devices. The
+        /// metadata of the sensor is kept, so it can report again.
+        #[pallet::constant]
+        type AutoExpire: Get<bool>;
+
+        /// Number of consecutive failures to fetch the sensors data after which the offchain
+        /// worker backs off, skipping an exponentially growing number of blocks until a fetch
+        /// succeeds again.
+        #[pallet::constant]
+        type MaxConsecutiveFailures: Get<u32>;
+
+        /// Interval, in the units of reading timestamps, at which `is_stale` expects a sensor to
+        /// report unless `set_expected_interval` sets one for it.
+        #[pallet::constant]
+        type DefaultExpectedInterval: Get<u64>;
+
+        /// Number of expected intervals a sensor can go without reporting before `is_stale`
+        /// considers it stale, to allow for late and missed readings.
+        #[pallet::constant]
+        type StalenessTolerance: Get<u32>;
+
+        /// Number of readings `update_sensors_data` writes per block. A batch hitting it is cut
+        /// short, its remaining readings skipped and reported in `BatchPartiallyProcessed`; see
+        /// `TypePriority` for which readings go first.
+        #[pallet::constant]
+        type MaxWritesPerBlock: Get<u32>;
+
+        /// Handler called with every reading stored by `add_sensor_data`.
+        type OnSensorUpdate: SensorUpdateHandler<SensorData>;
+
+        /// Current time, e.g. `pallet_timestamp`, which `stale_sensors` measures the age of
+        /// readings against.
+        #[pallet::no_default]
+        type UnixTime: UnixTime;
     }
 }
@@ -9,6 +9,44 @@ mod errors {
 		AlreadyAuthority,
 		TooManyAuthorities,
 		DeserializeError,
-		FailedSignedTransaction,
+		/// The offchain worker could not submit the sensors data, for the given reason.
+		SubmitFailed(SubmitFailure),
+		/// The offchain worker has no key of the pallet's `KEY_TYPE` in the local keystore.
+		NoLocalKey,
+		/// The offchain worker could not fetch the sensors data from the API.
+		HttpFetchFailed,
+		/// The API sent a gzip-encoded response that could not be decompressed.
+		DecompressFailed,
+		/// The checksum submitted with a batch doesn't match its readings.
+		ChecksumMismatch,
+		/// The confirmation threshold must be between 1 and the number of authorities.
+		InvalidConfirmationThreshold,
+		/// The offchain worker found no authorities on chain, so no account could submit the
+		/// sensors data.
+		NoAuthoritiesConfigured,
+		/// Removing the authorities would leave fewer than `MinAuthorities`.
+		BelowMinAuthorities,
+		/// A signature submitted with a reading doesn't match it.
+		InvalidSignature,
+		/// An authority signed a reading more than once.
+		DuplicateSignature,
+		/// Fewer authorities than the confirmation threshold signed a reading.
+		NotEnoughSignatures,
+		/// The authority proposed for removal submitted a batch within `SilenceThreshold` blocks.
+		AuthorityNotSilent,
+		/// The removal of the authority is already proposed.
+		RemovalAlreadyProposed,
+		/// Nobody proposed the removal of the authority.
+		NoRemovalProposal,
+		/// The authority already has a removal proposal pending, see `PendingProposals`.
+		ProposalPending,
+		/// The endpoint of a sensor type isn't an `http` or `https` URL of at most
+		/// `MAX_ENDPOINT_LEN` bytes.
+		InvalidEndpoint,
+		/// A multi-channel reading has no channels, or several of the same type.
+		InvalidMultiReading,
+		/// `MaxWritesPerBlock` doesn't leave room in the block for every channel of a
+		/// multi-channel reading, or for a reading confirmed by signatures.
+		BlockWritesExhausted,
 	}
 }
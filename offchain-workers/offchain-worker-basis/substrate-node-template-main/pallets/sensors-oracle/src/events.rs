@@ -9,7 +9,58 @@ mod events {
 		AuthorityAdded { authority: T::AccountId },
 		/// Event generated when an authority is removed.
 		AuthorityRemoved { authority: T::AccountId },
-        /// Event generated when new sensor data is added.
-        SensorDataAdded { id: u32, type_: SensorType },
+        /// Event generated when new sensor data is added, with its `EventSeq` sequence number.
+        SensorDataAdded { id: u32, type_: SensorType, seq: u64 },
+		/// Event generated when a batch of sensor data is added, with the number of readings
+		/// stored from it and its `EventSeq` sequence number.
+		SensorsDataUpdated { count: u32, seq: u64 },
+		/// Event generated when a reading of a batch is dropped while the rest is stored.
+		ReadingRejected { id: u32, type_: SensorType, reason: RejectReason },
+		/// Event generated when a reading is converted to the canonical unit of its type, with the
+		/// unit and value it was reported in. Emitted whatever the event verbosity.
+		SensorDataConverted { id: u32, type_: SensorType, raw_unit: Unit, raw_value: SensorValue },
+		/// Event generated when the metadata of a sensor is set.
+		SensorMetadataSet { id: u32 },
+		/// Event generated when `run_self_test` read back the synthetic reading it wrote.
+		SelfTestPassed,
+		/// Event generated when `run_self_test` didn't read back the synthetic reading it wrote.
+		SelfTestFailed,
+		/// Event generated when the confirmation threshold is changed.
+		ThresholdChanged { threshold: u32 },
+		/// Event generated when the event verbosity is changed.
+		EventVerbosityChanged { verbosity: EventVerbosity },
+		/// Event generated when the rejected reading counts are reset.
+		RejectionCountsReset,
+		/// Event generated when the alert bounds of a sensor are set or cleared.
+		AlertThresholdSet { id: u32, type_: SensorType },
+		/// Event generated when a reading falls below the low or above the high alert bound of
+		/// its sensor.
+		ThresholdBreached { id: u32, type_: SensorType, value: SensorValue, bound: u32 },
+		/// Event generated when requiring sensors to be registered is switched on or off.
+		RequireRegistrationSet { required: bool },
+		/// Event generated when storing batches all-or-nothing is switched on or off.
+		AtomicBatchesSet { atomic: bool },
+		/// Event generated when the priority of a sensor type is set.
+		TypePrioritySet { type_: SensorType, priority: u8 },
+		/// Event generated when the endpoint of a sensor type is set or cleared.
+		TypeEndpointSet {
+			type_: SensorType,
+			endpoint: Option<BoundedVec<u8, ConstU32<MAX_ENDPOINT_LEN>>>,
+		},
+		/// Event generated when `MaxWritesPerBlock` cuts a batch short: `processed` of its
+		/// readings were handled and the remaining `skipped` ones were not stored.
+		BatchPartiallyProcessed { processed: u32, skipped: u32 },
+		/// Event generated when the channels of a multi-channel reading are stored, with the
+		/// number of them that weren't already stored.
+		MultiReadingAdded { id: u32, channels: u32 },
+		/// Event generated when the expected reporting interval of a sensor is set or cleared.
+		ExpectedIntervalSet { id: u32, type_: SensorType, interval: Option<u64> },
+		/// Event generated when the debounce count of a digital sensor is set or cleared.
+		DebounceSet { id: u32, count: u32 },
+		/// Event generated when `on_idle` removes the latest reading of a sensor older than
+		/// `HistoryRetention`.
+		SensorExpired { id: u32, type_: SensorType },
+		/// Event generated when an authority proposes the removal of a silent authority.
+		RemovalProposed { candidate: T::AccountId, proposer: T::AccountId },
 	}
 }
\ No newline at end of file
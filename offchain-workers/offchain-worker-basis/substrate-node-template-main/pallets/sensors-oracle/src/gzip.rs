@@ -0,0 +1,82 @@
+//! Minimal gzip (RFC 1952) encoder for request bodies and decoder for API responses, usable in
+//! `no_std`.
+
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec_with_limit};
+use sp_std::vec::Vec;
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// Upper bound on the size of a decompressed body, so a small malicious response can't make the
+/// worker allocate without limit.
+pub const MAX_DECOMPRESSED_LEN: usize = 1024 * 1024;
+
+/// Deflate compression level of `compress`, miniz's default balance of speed and size.
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Compress `data` into a single-member gzip stream, without a file name or timestamp.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // Magic bytes, deflate, no flags, no modification time, no extra flags, unknown OS.
+    let mut gzipped = sp_std::vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    gzipped.extend(compress_to_vec(data, COMPRESSION_LEVEL));
+    gzipped.extend(crc32(data).to_le_bytes());
+    gzipped.extend((data.len() as u32).to_le_bytes());
+    gzipped
+}
+
+/// CRC-32 of the gzip trailer (IEEE 802.3, reflected), computed bit by bit rather than with a
+/// table, as bodies are at most `MAX_DECOMPRESSED_LEN` bytes.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ u32::from(*byte), |crc, _| match crc & 1 {
+            1 => (crc >> 1) ^ 0xedb8_8320,
+            _ => crc >> 1,
+        })
+    })
+}
+
+/// Decompress a single-member gzip stream.
+///
+/// Returns `None` if the header is malformed, the deflate stream is corrupt or decompresses to
+/// more than `MAX_DECOMPRESSED_LEN` bytes, or the CRC-32 or the length in the trailer doesn't
+/// match the decompressed body.
+pub fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    let (header, mut rest) = split(data, 10)?;
+    // Magic bytes, then compression method 8 (deflate).
+    if header[..3] != [0x1f, 0x8b, 8] {
+        return None;
+    }
+    let flags = header[3];
+
+    if flags & FEXTRA != 0 {
+        let (len, tail) = split(rest, 2)?;
+        rest = split(tail, u16::from_le_bytes([len[0], len[1]]) as usize)?.1;
+    }
+    if flags & FNAME != 0 {
+        rest = skip_zero_terminated(rest)?;
+    }
+    if flags & FCOMMENT != 0 {
+        rest = skip_zero_terminated(rest)?;
+    }
+    if flags & FHCRC != 0 {
+        rest = split(rest, 2)?.1;
+    }
+
+    let (deflated, trailer) = split(rest, rest.len().checked_sub(8)?)?;
+    let body = decompress_to_vec_with_limit(deflated, MAX_DECOMPRESSED_LEN).ok()?;
+
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    (crc == crc32(&body) && size == body.len() as u32).then_some(body)
+}
+
+fn split(data: &[u8], at: usize) -> Option<(&[u8], &[u8])> {
+    (at <= data.len()).then(|| data.split_at(at))
+}
+
+fn skip_zero_terminated(data: &[u8]) -> Option<&[u8]> {
+    let end = data.iter().position(|b| *b == 0)?;
+    Some(&data[end + 1..])
+}
@@ -4,20 +4,30 @@ mod calls;
 mod config;
 mod errors;
 mod events;
+#[cfg(feature = "ocw")]
+mod gzip;
+#[cfg(feature = "ocw")]
+mod ocw;
 pub mod types;
 
 use crate::types::*;
 
-use crate::pallet::{Authorities, Sensors};
+use crate::pallet::{
+    AlertThresholds, Authorities, DebounceCounts, DigitalDebounce, LatestByType, RejectionCounts,
+    SensorHistory, SensorMetadata, Sensors,
+};
 
-use frame_support::{pallet_macros::*, pallet_prelude::*};
+use frame_support::{pallet_macros::*, pallet_prelude::*, traits::UnixTime};
 use frame_system::{
-    self as system,
-    offchain::{AppCrypto, CreateSignedTransaction, SendSignedTransaction, Signer},
+    offchain::{AppCrypto, CreateSignedTransaction},
     pallet_prelude::*,
 };
 use sp_core::crypto::KeyTypeId;
-use sp_runtime::offchain::{http, Duration};
+use sp_runtime::{
+    offchain::{storage::StorageValueRef, StorageKind},
+    traits::{IdentifyAccount, One, Saturating},
+    FixedPointNumber, FixedU128, PerThing, Permill,
+};
 use sp_std::vec::Vec;
 
 #[cfg(test)]
@@ -64,6 +74,128 @@ pub mod crypto {
     }
 }
 
+/// Context the authorities sign readings in, see `Pallet::signed_reading_payload`.
+pub const SIGNED_READING_CONTEXT: &[u8] = b"sensors-oracle::reading";
+
+/// Weight of checking one signature of `submit_with_signatures`, an sr25519 verification of
+/// about 50µs on reference hardware.
+pub const SIGNATURE_VERIFICATION_WEIGHT: Weight =
+    Weight::from_parts(50 * frame_support::weights::constants::WEIGHT_REF_TIME_PER_MICROS, 0);
+
+/// Highest trust score of an authority, see `Pallet::trust_score`.
+pub const MAX_TRUST_SCORE: u32 = 1_000;
+
+/// Sensor id reserved for the synthetic reading written by `run_self_test`.
+pub const SELF_TEST_SENSOR_ID: u32 = u32::MAX;
+
+/// Persistent offchain local storage key of the `RequestConfig` used by the worker to call the
+/// sensors API.
+///
+/// The value is the SCALE encoding of `RequestConfig`: the method as one byte (`0x00` for GET,
+/// `0x01` for POST), then the extra headers as a vector of `(name, value)` byte strings, then the
+/// optional body. Operators set it with the `offchain_localStorageSet` RPC (kind `PERSISTENT`).
+/// When it's unset a plain GET is sent; an undecodable value is logged and ignored.
+pub const REQUEST_CONFIG_KEY: &[u8] = b"sensors-oracle::request-config";
+
+/// Endpoint of the public sensors API, for `Config::DefaultApiEndpoint`.
+pub const SENSORS_API_URL: &str = "https://sensors-api.vercel.app/api/v1/sensors";
+
+/// Persistent offchain local storage key of the sensor ids to fetch one by one, as a SCALE encoded
+/// `Vec<u32>`. When unset or empty all the sensors are fetched at once from the API endpoint.
+/// Sensors that fail to be fetched are skipped.
+pub const SENSOR_IDS_KEY: &[u8] = b"sensors-oracle::sensor-ids";
+
+/// Persistent offchain local storage key of the number of sensors fetched at the same time when
+/// fetching them one by one, as a SCALE encoded `u32`. Defaults to
+/// `DEFAULT_MAX_CONCURRENT_REQUESTS`.
+pub const MAX_CONCURRENT_REQUESTS_KEY: &[u8] = b"sensors-oracle::max-concurrent-requests";
+
+/// Number of sensors fetched at the same time when `MAX_CONCURRENT_REQUESTS_KEY` is unset.
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: u32 = 4;
+
+/// Persistent offchain local storage key whose presence makes the worker gzip the body of its POST
+/// requests, sent with `Content-Encoding: gzip`. Only set it for an API known to accept compressed
+/// requests: there is no way to tell from a response beforehand.
+pub const COMPRESS_REQUESTS_KEY: &[u8] = b"sensors-oracle::compress-requests";
+
+/// Persistent offchain local storage key of the URL of an HTTP gateway the worker sends its
+/// requests through, as raw UTF-8, e.g. `http://gateway.corp:8080/`. Each request goes to the
+/// gateway URL followed by the URL it's meant for, e.g.
+/// `http://gateway.corp:8080/https://sensors-api.vercel.app/api/v1/sensors`, with the same method,
+/// headers and body. When unset, or not an `http` or `https` URL, requests go to the API directly.
+///
+/// The offchain HTTP API of the node only sends requests to the URL it's given: it has no notion
+/// of a proxy, can't open a `CONNECT` tunnel and ignores the proxy settings of the environment. So
+/// this only works with a gateway that forwards requests addressed this way, and TLS to the API
+/// ends at the gateway. A `Proxy-Authorization` header can be added with `REQUEST_CONFIG_KEY`.
+pub const HTTP_PROXY_KEY: &[u8] = b"sensors-oracle::http-proxy";
+
+/// Persistent offchain local storage key of the `ResponseFormat` asked from the sensors API, SCALE
+/// encoded: `0x00` for JSON, `0x01` for CSV. When unset JSON is asked for.
+pub const RESPONSE_FORMAT_KEY: &[u8] = b"sensors-oracle::response-format";
+
+/// Persistent offchain local storage key of the oracle key the worker signs its transactions with,
+/// as a SCALE encoded `SigningTypes::Public`.
+///
+/// When unset the worker signs with any oracle key in the keystore. When set to a key that isn't in
+/// the keystore nothing is submitted. Only used with `SignerStrategy::Preferred`, the strategy when
+/// `SIGNER_STRATEGY_KEY` is unset and this key is set.
+pub const PREFERRED_SIGNER_KEY: &[u8] = b"sensors-oracle::preferred-signer";
+
+/// Persistent offchain local storage key of the `SignerStrategy` of the worker, SCALE encoded:
+/// `0x00` for any key, `0x01` for round-robin, `0x02` for the preferred key.
+pub const SIGNER_STRATEGY_KEY: &[u8] = b"sensors-oracle::signer-strategy";
+
+/// Persistent offchain local storage key of the position, as a SCALE encoded `u32`, of the next
+/// key to sign with `SignerStrategy::RoundRobin` among the authority keys sorted.
+pub const SIGNER_INDEX_KEY: &[u8] = b"sensors-oracle::signer-index";
+
+/// Persistent offchain local storage key whose presence makes the worker read the sensors data from
+/// `LOCAL_FIXTURE_KEY` instead of the API, so the whole worker path runs without any network.
+pub const USE_LOCAL_FIXTURE_KEY: &[u8] = b"sensors-oracle::use_local_fixture";
+
+/// Persistent offchain local storage key of the raw JSON body read when `USE_LOCAL_FIXTURE_KEY` is
+/// set, in the same format as the API response. Set it with the `offchain_localStorageSet` RPC.
+pub const LOCAL_FIXTURE_KEY: &[u8] = b"sensors-oracle::local_fixture";
+
+/// Persistent offchain local storage key of the sampling interval K of the worker, as a SCALE
+/// encoded `u32`. Of the readings of each sensor id and type, the worker only submits the first of
+/// every K it sees. When unset, 0 or 1 every reading is submitted.
+///
+/// A reading only counts if it's newer than the last one counted for its sensor. The API keeps
+/// serving the latest reading of a device until it reports again, so however many runs of the
+/// worker it's served in, e.g. with a `GracePeriod` shorter than the reporting interval of the
+/// device, it counts once. Readings skipped are never submitted in a later run.
+pub const SAMPLE_EVERY_KEY: &[u8] = b"sensors-oracle::sample-every";
+
+/// Prefix of the persistent offchain local storage key of the `SampleCounter` of a sensor, followed
+/// by its SCALE encoded id and type.
+pub const SAMPLE_COUNTER_PREFIX: &[u8] = b"sensors-oracle::sample-counter::";
+
+/// Persistent offchain local storage key of the last batch of readings the worker submitted, as a
+/// SCALE encoded `Vec<SensorData>`. The next batch is diffed against it in the debug log.
+pub const LAST_SUBMISSION_KEY: &[u8] = b"sensors-oracle::last-submission";
+
+/// Persistent offchain local storage key of the worker's `BreakerState`.
+pub const BREAKER_KEY: &[u8] = b"sensors-oracle::breaker";
+
+/// Bound of the URL of a sensors API endpoint in `TypeEndpoints`.
+pub const MAX_ENDPOINT_LEN: u32 = 256;
+
+/// Bound of a raw `SensorHistory` key: the 32 byte prefix, then the hashed sensor id and type.
+const MAX_PRUNE_CURSOR_LEN: u32 = 128;
+
+/// Handler notified by the pallet of every reading it stores, so other pallets can react to new
+/// readings without depending on this one.
+pub trait SensorUpdateHandler<Reading> {
+    /// Called after `reading` has been written to storage.
+    fn on_sensor_update(reading: &Reading);
+}
+
+impl<Reading> SensorUpdateHandler<Reading> for () {
+    fn on_sensor_update(_reading: &Reading) {}
+}
+
 pub use pallet::*;
 
 #[import_section(events::events)]
@@ -74,11 +206,6 @@ pub use pallet::*;
 pub mod pallet {
     use super::*;
 
-    use core::fmt;
-    use frame_support::pallet_prelude::*;
-    use frame_system::pallet_prelude::*;
-    use serde_json::error;
-
     /// A double storage map with the sensors data.
     #[pallet::storage]
     #[pallet::getter(fn sensors)]
@@ -92,22 +219,281 @@ pub mod pallet {
         OptionQuery,
     >;
 
+    /// Metadata of the registered sensors.
+    #[pallet::storage]
+    #[pallet::getter(fn sensor_metadata)]
+    pub(super) type SensorMetadata<T: Config> =
+        StorageMap<_, Blake2_128Concat, SensorIdOf, SensorInfo, OptionQuery>;
+
+    /// The most recent readings of every sensor, oldest first.
+    ///
+    /// Once `MaxHistory` readings are stored the oldest one is dropped for every new one.
+    #[pallet::storage]
+    #[pallet::getter(fn sensor_history)]
+    pub(super) type SensorHistory<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        SensorIdOf,
+        Blake2_128Concat,
+        SensorType,
+        BoundedVec<SensorData, T::MaxHistory>,
+        ValueQuery,
+    >;
+
     /// Authorities allowed to submit the price.
     #[pallet::storage]
     #[pallet::getter(fn authorities)]
     pub(super) type Authorities<T: Config> =
         StorageValue<_, BoundedVec<T::AccountId, T::MaxAuthorities>, ValueQuery>;
 
+    #[pallet::type_value]
+    pub(super) fn DefaultConfirmationThreshold() -> u32 {
+        1
+    }
+
+    /// Number of authorities that must confirm a reading before it is accepted.
+    ///
+    /// Always between 1 and the current number of authorities when set through
+    /// `set_confirmation_threshold`, and lowered to the number of authorities left when removing
+    /// authorities leaves fewer than it.
+    #[pallet::storage]
+    #[pallet::getter(fn confirmation_threshold)]
+    pub(super) type ConfirmationThreshold<T: Config> =
+        StorageValue<_, u32, ValueQuery, DefaultConfirmationThreshold>;
+
+    #[pallet::type_value]
+    pub(super) fn DefaultTrustScore() -> u32 {
+        MAX_TRUST_SCORE
+    }
+
+    /// Rolling trust score of every authority, see `Pallet::trust_score`.
+    #[pallet::storage]
+    pub(super) type AuthorityTrust<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery, DefaultTrustScore>;
+
+    /// Whether readings are only accepted from sensors registered in `SensorMetadata`.
+    #[pallet::storage]
+    #[pallet::getter(fn require_registration)]
+    pub(super) type RequireRegistration<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// Whether `update_sensors_data` stores a batch all-or-nothing: with a single rejected
+    /// reading none of the batch is stored. Otherwise the valid readings of a batch are stored
+    /// and only the invalid ones are dropped.
+    #[pallet::storage]
+    #[pallet::getter(fn atomic_batches)]
+    pub(super) type AtomicBatches<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// Priority of the readings of a sensor type in `update_sensors_data`: higher priorities are
+    /// stored first, so they still make it into a block once `MaxWritesPerBlock` is hit. Types
+    /// without an entry have priority 0.
+    #[pallet::storage]
+    #[pallet::getter(fn type_priority)]
+    pub(super) type TypePriority<T: Config> =
+        StorageMap<_, Blake2_128Concat, SensorType, u8, ValueQuery>;
+
+    /// URL of the endpoint of the sensors API serving the readings of a sensor type, for
+    /// backends with a service per metric. The offchain worker fetches the readings of these types
+    /// from their endpoint, and those of the other types from `DefaultApiEndpoint`.
+    #[pallet::storage]
+    #[pallet::getter(fn type_endpoint)]
+    pub(super) type TypeEndpoints<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        SensorType,
+        BoundedVec<u8, ConstU32<MAX_ENDPOINT_LEN>>,
+        OptionQuery,
+    >;
+
+    /// The latest `MultiReading` of every sensor that submitted one, as submitted, keeping its
+    /// channels grouped. Each channel is also stored under its type in `Sensors`.
+    #[pallet::storage]
+    #[pallet::getter(fn multi_reading)]
+    pub(super) type MultiReadings<T: Config> =
+        StorageMap<_, Blake2_128Concat, SensorIdOf, MultiReading, OptionQuery>;
+
+    /// Block the readings counted against `MaxWritesPerBlock` were written in, with their number.
+    #[pallet::storage]
+    pub(super) type BlockWrites<T: Config> =
+        StorageValue<_, (BlockNumberFor<T>, u32), ValueQuery>;
+
+    /// Which events are emitted for incoming readings.
+    #[pallet::storage]
+    #[pallet::getter(fn event_verbosity)]
+    pub(super) type Verbosity<T: Config> = StorageValue<_, EventVerbosity, ValueQuery>;
+
+    /// Bounds outside of which a numeric reading of a sensor raises a `ThresholdBreached` event.
+    ///
+    /// Alerts are emitted whatever the event verbosity.
+    #[pallet::storage]
+    #[pallet::getter(fn alert_threshold)]
+    pub(super) type AlertThresholds<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        SensorIdOf,
+        Blake2_128Concat,
+        SensorType,
+        (Option<u32>, Option<u32>),
+        OptionQuery,
+    >;
+
+    /// Interval, in the units of reading timestamps, at which a sensor is expected to report,
+    /// for `is_stale`. Sensors without an entry are expected every `DefaultExpectedInterval`.
+    #[pallet::storage]
+    #[pallet::getter(fn expected_interval)]
+    pub(super) type ExpectedInterval<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        SensorIdOf,
+        Blake2_128Concat,
+        SensorType,
+        u64,
+        OptionQuery,
+    >;
+
+    /// Number of consecutive submissions a new state of a digital sensor has to be reported in
+    /// before it's stored, for devices that chatter. Sensors without an entry store every state
+    /// change right away.
+    #[pallet::storage]
+    #[pallet::getter(fn debounce_count)]
+    pub(super) type DebounceCounts<T: Config> =
+        StorageMap<_, Blake2_128Concat, SensorIdOf, u32, OptionQuery>;
+
+    /// State a debounced digital sensor is changing to, with the number of consecutive
+    /// submissions that reported it so far.
+    #[pallet::storage]
+    pub(super) type DigitalDebounce<T: Config> =
+        StorageMap<_, Blake2_128Concat, SensorIdOf, (bool, u32), OptionQuery>;
+
+    /// Newest reading of each sensor type across all sensors. A reading replaces the cached one
+    /// only if its timestamp is strictly greater.
+    #[pallet::storage]
+    #[pallet::getter(fn latest_by_type)]
+    pub(super) type LatestByType<T: Config> =
+        StorageMap<_, Blake2_128Concat, SensorType, SensorData, OptionQuery>;
+
+    /// Sequence number of the last `SensorDataAdded` or `SensorsDataUpdated` event. Data events
+    /// are numbered from 1 without gaps, so an indexer can tell whether it missed any.
+    #[pallet::storage]
+    #[pallet::getter(fn current_event_seq)]
+    pub(super) type EventSeq<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+    /// Number of readings stored by `add_sensor_data`.
+    #[pallet::storage]
+    #[pallet::getter(fn readings_stored)]
+    pub(super) type ReadingsStored<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+    /// Timestamp of the newest reading stored, which `HistoryRetention` is measured back from.
+    #[pallet::storage]
+    #[pallet::getter(fn latest_timestamp)]
+    pub(super) type LatestTimestamp<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+    /// Raw key of the last `SensorHistory` entry `on_idle` pruned, to resume from in the next
+    /// block. `None` starts over from the beginning of the map.
+    #[pallet::storage]
+    pub(super) type PruneCursor<T: Config> =
+        StorageValue<_, BoundedVec<u8, ConstU32<MAX_PRUNE_CURSOR_LEN>>, OptionQuery>;
+
+    /// Raw key of the last `Sensors` entry `on_idle` checked for expiry, to resume from in the next
+    /// block. `None` starts over from the beginning of the map.
+    #[pallet::storage]
+    pub(super) type ExpireCursor<T: Config> =
+        StorageValue<_, BoundedVec<u8, ConstU32<MAX_PRUNE_CURSOR_LEN>>, OptionQuery>;
+
+    /// Block each authority was added at or last submitted a batch with `update_sensors_data` at.
+    #[pallet::storage]
+    #[pallet::getter(fn last_submission)]
+    pub(super) type LastSubmission<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumberFor<T>, OptionQuery>;
+
+    /// Outcome of the last batch each authority submitted with `update_sensors_data`.
+    #[pallet::storage]
+    #[pallet::getter(fn last_batch_result)]
+    pub(super) type LastBatchResult<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BatchResult, OptionQuery>;
+
+    /// Authorities proposed for removal by `propose_remove_authority`, with the authority that
+    /// proposed it, until root ratifies the removal with `ratify_remove_authority`.
+    #[pallet::storage]
+    #[pallet::getter(fn removal_proposal)]
+    pub(super) type RemovalProposals<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    /// Authority each authority proposed for removal with `propose_remove_authority`, while the
+    /// proposal is pending: an authority can't propose another removal until it's ratified.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_proposal)]
+    pub(super) type PendingProposals<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    /// Number of readings rejected for each reason since the last `reset_rejection_counts`, so at
+    /// most one entry per `RejectReason`.
+    #[pallet::storage]
+    pub(super) type RejectionCounts<T: Config> =
+        StorageMap<_, Blake2_128Concat, RejectReason, u64, ValueQuery>;
+
+    #[pallet::genesis_config]
+    #[derive(frame_support::DefaultNoBound)]
+    pub struct GenesisConfig<T: Config> {
+        /// Authorities allowed to submit sensors data from the first block.
+        pub authorities: Vec<T::AccountId>,
+        /// Sensors registered from the first block, as `(id, name, unit, geolocation)`.
+        pub sensors: Vec<(SensorIdOf, Vec<u8>, Option<Unit>, Geolocation)>,
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+        fn build(&self) {
+            let mut authorities = BoundedVec::default();
+            for authority in &self.authorities {
+                assert!(
+                    !authorities.contains(authority),
+                    "duplicate genesis authority"
+                );
+                Pallet::<T>::push_authority(&mut authorities, authority)
+                    .expect("genesis authorities must fit in `MaxAuthorities`");
+            }
+            Authorities::<T>::put(authorities);
+
+            for (id, name, unit, geolocation) in &self.sensors {
+                assert!(
+                    !SensorMetadata::<T>::contains_key(id),
+                    "duplicate genesis sensor"
+                );
+                assert!(
+                    geolocation.is_valid(),
+                    "genesis sensor geolocations must be within range"
+                );
+                let info = SensorInfo {
+                    unit: *unit,
+                    name: name
+                        .clone()
+                        .try_into()
+                        .expect("genesis sensor names must fit in `SensorName`"),
+                    geolocation: Some(*geolocation),
+                    private: false,
+                };
+                SensorMetadata::<T>::insert(id, info);
+            }
+        }
+    }
+
     #[pallet::pallet]
     pub struct Pallet<T>(_);
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        #[cfg(feature = "ocw")]
         fn offchain_worker(block_number: BlockNumberFor<T>) {
-            match Self::get_sensors_data() {
-                Ok(_) => log::info!("Sensors data updated..."),
-                Err(_) => log::error!("Failed to update sensors data..."),
+            Self::log_ocw_config();
+            Self::run_offchain_worker(block_number);
+        }
+
+        fn on_idle(_block_number: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            let used = Self::prune_history(remaining_weight);
+            if !T::AutoExpire::get() {
+                return used;
             }
+            used.saturating_add(Self::expire_sensors(remaining_weight.saturating_sub(used)))
         }
     }
 
@@ -122,6 +508,16 @@ pub mod pallet {
         #[frame_support::register_default_impl(TestDefaultConfig)]
         impl DefaultConfig for TestDefaultConfig {
             type MaxAuthorities = frame_support::traits::ConstU32<64>;
+            type MinAuthorities = frame_support::traits::ConstU32<0>;
+            type MaxHistory = frame_support::traits::ConstU32<16>;
+            type MaxHistoryPage = frame_support::traits::ConstU32<8>;
+            type HistoryRetention = frame_support::traits::ConstU64<100>;
+            type AutoExpire = frame_support::traits::ConstBool<false>;
+            type MaxConsecutiveFailures = frame_support::traits::ConstU32<3>;
+            type DefaultExpectedInterval = frame_support::traits::ConstU64<10>;
+            type StalenessTolerance = frame_support::traits::ConstU32<3>;
+            type MaxWritesPerBlock = frame_support::traits::ConstU32<1000>;
+            type OnSensorUpdate = ();
         }
     }
 }
@@ -131,70 +527,970 @@ impl<T: Config> Pallet<T> {
         <Authorities<T>>::get().contains(who)
     }
 
-    /// Fetch current price and return the result in cents.
-    pub fn get_sensors_data() -> Result<Vec<SensorData>, http::Error> {
-        // We want to keep the offchain worker execution time reasonable, so we set a hard-coded
-        // deadline to 2s to complete the external call. You can also wait indefinitely for the
-        // response, however you may still get a timeout coming from the host machine.
-        let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(2_000));
-        // Initiate an external HTTP GET request. This is using high-level wrappers from
-        // `sp_runtime`, for the low-level calls that you can find in `sp_io`. The API is trying to
-        // be similar to `request`, but since we are running in a custom WASM execution environment
-        // we can't simply import the library here.
-        let request = http::Request::get("https://sensors-api.vercel.app/api/v1/sensors");
-        // We set the deadline for sending of the request, note that awaiting response can have a
-        // separate deadline. Next we send the request, before that it's also possible to alter
-        // request headers or stream body content in case of non-GET requests.
-        let pending = request
-            .deadline(deadline)
-            .send()
-            .map_err(|_| http::Error::IoError)?;
-
-        // The request is already being processed by the host, we are free to do anything else in
-        // the worker (we can send multiple concurrent requests too). At some point however we
-        // probably want to check the response though, so we can block current thread and wait for
-        // it to finish. Note that since the request is being driven by the host, we don't have to
-        // wait for the request to have it complete, we will just not read the response.
-        let response = pending
-            .try_wait(deadline)
-            .map_err(|_| http::Error::DeadlineReached)??;
-
-        // Let's check the status code before we proceed to reading the response.
-        if response.code != 200 {
-            log::warn!("Unexpected status code: {}", response.code);
-            return Err(http::Error::Unknown);
-        }
-
-        // Next we want to fully read the response body and collect it to a vector of bytes. Note
-        // that the return object allows you to read the body in chunks as well with a way to
-        // control the deadline.
-        let body = response.body().collect::<Vec<u8>>();
-
-        let sensors_data: Vec<SensorData> = serde_json::from_slice(&body).map_err(|_| {
+    /// Make `who` an authority if it isn't one already, for use by other pallets (e.g. in their
+    /// migrations). A no-op if `who` is already an authority.
+    pub fn ensure_authority(who: &T::AccountId) -> DispatchResult {
+        let mut authorities = <Authorities<T>>::get();
+        if Self::push_authority(&mut authorities, who)? {
+            Authorities::<T>::set(authorities);
+        }
+
+        Ok(())
+    }
+
+    /// Append `who` to `authorities` and emit `AuthorityAdded`, or fail with `TooManyAuthorities`
+    /// if `MaxAuthorities` is reached. Every way of adding authorities goes through here.
+    ///
+    /// Returns `false` without emitting anything if `who` is already in `authorities`, so that
+    /// `AuthorityAdded` is only ever emitted for actual membership changes.
+    fn push_authority(
+        authorities: &mut BoundedVec<T::AccountId, T::MaxAuthorities>,
+        who: &T::AccountId,
+    ) -> Result<bool, Error<T>> {
+        if authorities.contains(who) {
+            return Ok(false);
+        }
+
+        authorities
+            .try_push(who.clone())
+            .map_err(|_| Error::<T>::TooManyAuthorities)?;
+        <LastSubmission<T>>::insert(who, <frame_system::Pallet<T>>::block_number());
+
+        Self::deposit_event(Event::AuthorityAdded {
+            authority: who.clone(),
+        });
+
+        Ok(true)
+    }
+
+    /// Whether `who` hasn't submitted a batch for more than `SilenceThreshold` blocks at `now`.
+    /// Authorities are counted from the block they were added at until they first submit one.
+    pub fn is_silent(who: &T::AccountId, now: BlockNumberFor<T>) -> bool {
+        let last = <LastSubmission<T>>::get(who).unwrap_or_default();
+        now.saturating_sub(last) > T::SilenceThreshold::get()
+    }
+
+    /// Lower `ConfirmationThreshold` to the number of authorities, but not below 1, if removals
+    /// left fewer authorities than it, so that `submit_with_signatures` can still be satisfied.
+    fn clamp_confirmation_threshold() {
+        let authorities = (<Authorities<T>>::decode_len().unwrap_or(0) as u32).max(1);
+        if Self::confirmation_threshold() > authorities {
+            <ConfirmationThreshold<T>>::put(authorities);
+            Self::deposit_event(Event::ThresholdChanged {
+                threshold: authorities,
+            });
+        }
+    }
+
+    /// Forget the submissions of an authority that was removed, any proposal to remove it and
+    /// any proposal it made. A proposal it made stays open for root to ratify.
+    fn forget_authority(who: &T::AccountId) {
+        <LastSubmission<T>>::remove(who);
+        <LastBatchResult<T>>::remove(who);
+        if let Some(proposer) = <RemovalProposals<T>>::take(who) {
+            <PendingProposals<T>>::remove(proposer);
+        }
+        <PendingProposals<T>>::remove(who);
+    }
+
+    /// Blake2-256 hash of the SCALE encoding of a batch of readings.
+    pub fn readings_checksum(readings: &[SensorData]) -> [u8; 32] {
+        sp_io::hashing::blake2_256(&readings.encode())
+    }
+
+    /// Drop the readings of `readings` identical to an earlier one, comparing the Blake2-256 hash
+    /// of their SCALE encoding, and keep the order of the others. Returns how many were dropped.
+    pub fn dedup_readings(readings: &mut Vec<SensorData>) -> u32 {
+        let mut seen = sp_std::collections::btree_set::BTreeSet::new();
+        let before = readings.len();
+        readings.retain(|reading| seen.insert(sp_io::hashing::blake2_256(&reading.encode())));
+        (before - readings.len()) as u32
+    }
+
+    /// The sensors added, removed and whose value changed from `old` to `new`, in the order they
+    /// appear in `new`, then `old` for the removed ones. The last reading of a sensor that appears
+    /// more than once in a snapshot is taken.
+    pub fn diff_readings(old: &[SensorData], new: &[SensorData]) -> ReadingDiff {
+        let find = |readings: &[SensorData], sensor: &SensorData| {
+            readings
+                .iter()
+                .rev()
+                .find(|reading| reading.id == sensor.id && reading.type_ == sensor.type_)
+                .cloned()
+        };
+        let mut diff = ReadingDiff::default();
+        for (i, sensor) in new.iter().enumerate() {
+            if find(&new[i + 1..], sensor).is_some() {
+                continue;
+            }
+            match find(old, sensor) {
+                None => diff.added.push((sensor.id, sensor.type_.clone())),
+                Some(previous) if previous.value != sensor.value => diff.changed.push(ValueChange {
+                    id: sensor.id,
+                    type_: sensor.type_.clone(),
+                    old: previous.value,
+                    new: sensor.value,
+                }),
+                Some(_) => (),
+            }
+        }
+        for (i, sensor) in old.iter().enumerate() {
+            if find(&old[i + 1..], sensor).is_none() && find(new, sensor).is_none() {
+                diff.removed.push((sensor.id, sensor.type_.clone()));
+            }
+        }
+        diff
+    }
+
+    /// Decode a response of the API in the given `format` with `process_sensors_json`,
+    /// `process_sensors_csv` or `process_sensors_compact`.
+    pub fn process_sensors(
+        body: &[u8],
+        format: ResponseFormat,
+    ) -> Result<Vec<SensorData>, Error<T>> {
+        match format {
+            ResponseFormat::Json => Self::process_sensors_json(body),
+            ResponseFormat::Csv => Self::process_sensors_csv(body),
+            ResponseFormat::CompactJson => Self::process_sensors_compact(body),
+        }
+    }
+
+    /// Decode a JSON response of the API and add the readings derived from it, giving the batch
+    /// to submit on-chain.
+    pub fn process_sensors_json(body: &[u8]) -> Result<Vec<SensorData>, Error<T>> {
+        let mut sensors_data: Vec<SensorData> = serde_json::from_slice(body).map_err(|_| {
             log::warn!("No sensors data found");
-            http::Error::Unknown
+            Error::<T>::DeserializeError
         })?;
+        let derived = Self::derive_heat_indices(&sensors_data);
+        sensors_data.extend(derived);
 
-        let signer = Signer::<T, T::AuthorityId>::any_account();
+        Ok(sensors_data)
+    }
 
-        signer
-            .send_signed_transaction(|account| {
-                log::info!("Account, {:?}, {:?}", account.id, account.public);
-                Call::<T>::update_sensors_data {
-                    updated_data: sensors_data.clone(),
+    /// The sampling interval stored under `SAMPLE_EVERY_KEY`, at least 1.
+    pub fn sample_every() -> u32 {
+        match StorageValueRef::persistent(SAMPLE_EVERY_KEY).get::<u32>() {
+            Ok(every) => every.unwrap_or(1).max(1),
+            Err(_) => {
+                log::warn!("Ignoring undecodable sampling interval in offchain local storage");
+                1
+            }
+        }
+    }
+
+    /// The JSON body stored under `LOCAL_FIXTURE_KEY` if `USE_LOCAL_FIXTURE_KEY` is set, `None` to
+    /// fetch from the API.
+    pub fn local_fixture() -> Option<Vec<u8>> {
+        let get = |key| sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, key);
+        get(USE_LOCAL_FIXTURE_KEY)?;
+        Some(get(LOCAL_FIXTURE_KEY).unwrap_or_else(|| {
+            log::warn!("`use_local_fixture` is set but no local fixture is stored");
+            Vec::new()
+        }))
+    }
+
+    /// The sensor ids stored under `SENSOR_IDS_KEY`, empty if unset.
+    pub fn sensor_ids() -> Vec<SensorIdOf> {
+        match StorageValueRef::persistent(SENSOR_IDS_KEY).get::<Vec<SensorIdOf>>() {
+            Ok(ids) => ids.unwrap_or_default(),
+            Err(_) => {
+                log::warn!("Ignoring undecodable sensor ids in offchain local storage");
+                Vec::new()
+            }
+        }
+    }
+
+    /// The limit stored under `MAX_CONCURRENT_REQUESTS_KEY`, `DEFAULT_MAX_CONCURRENT_REQUESTS` if
+    /// unset. At least one request is always sent at a time.
+    pub fn max_concurrent_requests() -> u32 {
+        match StorageValueRef::persistent(MAX_CONCURRENT_REQUESTS_KEY).get::<u32>() {
+            Ok(limit) => limit.unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS).max(1),
+            Err(_) => {
+                log::warn!("Ignoring undecodable request limit in offchain local storage");
+                DEFAULT_MAX_CONCURRENT_REQUESTS
+            }
+        }
+    }
+
+    /// The public key stored under `PREFERRED_SIGNER_KEY`, if any.
+    pub fn preferred_signer() -> Option<T::Public> {
+        match StorageValueRef::persistent(PREFERRED_SIGNER_KEY).get::<T::Public>() {
+            Ok(public) => public,
+            Err(_) => {
+                log::warn!("Ignoring undecodable preferred signer in offchain local storage");
+                None
+            }
+        }
+    }
+
+    /// The `SignerStrategy` stored under `SIGNER_STRATEGY_KEY`. When unset, `Preferred` if a
+    /// preferred signer is stored, otherwise `Any`.
+    pub fn signer_strategy() -> SignerStrategy {
+        match StorageValueRef::persistent(SIGNER_STRATEGY_KEY).get::<SignerStrategy>() {
+            Ok(Some(strategy)) => strategy,
+            Ok(None) if Self::preferred_signer().is_some() => SignerStrategy::Preferred,
+            Ok(None) => SignerStrategy::Any,
+            Err(_) => {
+                log::warn!("Ignoring undecodable signer strategy in offchain local storage");
+                SignerStrategy::Any
+            }
+        }
+    }
+
+    /// Decode a CSV response of the API and add the readings derived from it, like
+    /// `process_sensors_json`.
+    pub fn process_sensors_csv(body: &[u8]) -> Result<Vec<SensorData>, Error<T>> {
+        let malformed = || {
+            log::warn!("Malformed CSV sensors data");
+            Error::<T>::DeserializeError
+        };
+        let body = core::str::from_utf8(body).map_err(|_| malformed())?;
+        let mut records = body.lines().filter(|line| !line.trim().is_empty()).peekable();
+        if records.peek().is_some_and(|header| header.starts_with("id,")) {
+            records.next();
+        }
+        let mut sensors_data = records
+            .map(SensorData::from_csv_record)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(malformed)?;
+        let derived = Self::derive_heat_indices(&sensors_data);
+        sensors_data.extend(derived);
+
+        Ok(sensors_data)
+    }
+
+    /// Decode a compact JSON response of the API, an array of `CompactSensorData`, and add the
+    /// readings derived from it, like `process_sensors_json`.
+    pub fn process_sensors_compact(body: &[u8]) -> Result<Vec<SensorData>, Error<T>> {
+        let compact: Vec<CompactSensorData> = serde_json::from_slice(body).map_err(|_| {
+            log::warn!("Malformed compact sensors data");
+            Error::<T>::DeserializeError
+        })?;
+        let mut sensors_data = compact
+            .into_iter()
+            .map(SensorData::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                log::warn!("Malformed compact sensors data: {}", e);
+                Error::<T>::DeserializeError
+            })?;
+        let derived = Self::derive_heat_indices(&sensors_data);
+        sensors_data.extend(derived);
+
+        Ok(sensors_data)
+    }
+
+    /// The `ResponseFormat` stored under `RESPONSE_FORMAT_KEY`, or JSON.
+    pub fn response_format() -> ResponseFormat {
+        match StorageValueRef::persistent(RESPONSE_FORMAT_KEY).get::<ResponseFormat>() {
+            Ok(format) => format.unwrap_or_default(),
+            Err(_) => {
+                log::warn!("Ignoring undecodable response format in offchain local storage");
+                ResponseFormat::default()
+            }
+        }
+    }
+
+    /// Whether `COMPRESS_REQUESTS_KEY` is set.
+    pub fn compress_requests() -> bool {
+        sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, COMPRESS_REQUESTS_KEY).is_some()
+    }
+
+    /// The gateway URL stored under `HTTP_PROXY_KEY`, if it's an `http` or `https` URL.
+    pub fn http_proxy() -> Option<Vec<u8>> {
+        let proxy = sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, HTTP_PROXY_KEY)?;
+        match core::str::from_utf8(&proxy).is_ok_and(is_http_url) {
+            true => Some(proxy),
+            false => {
+                log::warn!("Ignoring invalid HTTP proxy in offchain local storage");
+                None
+            }
+        }
+    }
+
+    /// The `RequestConfig` stored under `REQUEST_CONFIG_KEY`, or the default plain GET.
+    pub fn request_config() -> RequestConfig {
+        match StorageValueRef::persistent(REQUEST_CONFIG_KEY).get::<RequestConfig>() {
+            Ok(config) => config.unwrap_or_default(),
+            Err(_) => {
+                log::warn!("Ignoring undecodable request config in offchain local storage");
+                RequestConfig::default()
+            }
+        }
+    }
+
+    /// Serialize every stored reading to JSON, in the shape the API produces, so a snapshot can be
+    /// ingested again.
+    ///
+    /// Iterates the whole `Sensors` map, so it's meant for RPC/off-chain use only. Empty if a
+    /// custom sensor type name isn't valid UTF-8, which the API can't produce.
+    pub fn export_snapshot() -> Vec<u8> {
+        let readings: Vec<SensorData> = <Sensors<T>>::iter_values().collect();
+        serde_json::to_vec(&readings).unwrap_or_default()
+    }
+
+    /// Whether the sensor is marked private in its metadata.
+    pub fn is_private(id: SensorIdOf) -> bool {
+        <SensorMetadata<T>>::get(id).is_some_and(|info| info.private)
+    }
+
+    /// Latest reading of a sensor, or `None` if there is none or it's private and
+    /// `include_private` isn't set.
+    pub fn latest_reading(
+        id: SensorIdOf,
+        type_: SensorType,
+        include_private: bool,
+    ) -> Option<SensorData> {
+        if !include_private && Self::is_private(id) {
+            return None;
+        }
+        <Sensors<T>>::get(id, type_)
+    }
+
+    /// Latest reading of a sensor for public consumers, leaving out private sensors. Meant for
+    /// RPC/off-chain use.
+    pub fn public_latest_reading(id: SensorIdOf, type_: SensorType) -> Option<SensorData> {
+        Self::latest_reading(id, type_, false)
+    }
+
+    /// Page through the history of a sensor, oldest reading first.
+    ///
+    /// `start` is an index into the history and `limit` is clamped to `MaxHistoryPage`. Since the
+    /// oldest readings are dropped as new ones arrive, a cursor is only stable while no readings
+    /// are added. Meant for RPC/off-chain use.
+    pub fn sensor_history_paged(
+        id: SensorIdOf,
+        type_: SensorType,
+        start: u32,
+        limit: u32,
+    ) -> HistoryPage {
+        let history = <SensorHistory<T>>::get(id, type_);
+        let limit = limit.min(T::MaxHistoryPage::get());
+        let start = (start as usize).min(history.len());
+        let end = start.saturating_add(limit as usize).min(history.len());
+
+        HistoryPage {
+            readings: history[start..end].to_vec(),
+            next: if end < history.len() { Some(end as u32) } else { None },
+        }
+    }
+
+    /// Count the readings in the history of a sensor falling into each bucket delimited by
+    /// `edges`: bucket `i` holds the values from `edges[i]` included to `edges[i + 1]` excluded.
+    ///
+    /// Values outside of the buckets and readings that aren't numbers are left out. Returns `None`
+    /// unless there are at least two edges in strictly increasing order. Meant for RPC/off-chain
+    /// use.
+    pub fn value_histogram(id: SensorIdOf, type_: SensorType, edges: &[u32]) -> Option<Vec<u32>> {
+        if edges.len() < 2 || edges.windows(2).any(|w| w[0] >= w[1]) {
+            return None;
+        }
+
+        let mut counts = sp_std::vec![0u32; edges.len() - 1];
+        for value in <SensorHistory<T>>::get(id, type_).iter().filter_map(|r| r.value.as_number()) {
+            // Number of edges at or below the value: none is below the first bucket and all of
+            // them past the last one.
+            match edges.partition_point(|edge| *edge <= value) {
+                0 => (),
+                i if i == edges.len() => (),
+                i => counts[i - 1] += 1,
+            }
+        }
+
+        Some(counts)
+    }
+
+    /// Number of bytes the values stored for a sensor take up: its latest readings, its history
+    /// and its metadata. Storage keys and trie overhead aren't counted.
+    ///
+    /// Iterates every type the sensor reported, so it's meant for RPC/off-chain use only.
+    pub fn storage_footprint(id: SensorIdOf) -> u64 {
+        let latest: usize = <Sensors<T>>::iter_prefix_values(id).map(|r| r.encoded_size()).sum();
+        let history: usize =
+            <SensorHistory<T>>::iter_prefix_values(id).map(|h| h.encoded_size()).sum();
+        let metadata = <SensorMetadata<T>>::get(id).map_or(0, |info| info.encoded_size());
+
+        (latest + history + metadata) as u64
+    }
+
+    /// Compute a `HeatIndex` reading for every sensor id that reported both a `Temperature` and a
+    /// `Humidity` reading in `readings`. Sensors missing either input are skipped.
+    pub fn derive_heat_indices(readings: &[SensorData]) -> Vec<SensorData> {
+        readings
+            .iter()
+            .filter(|reading| reading.type_ == SensorType::Temperature)
+            .filter_map(|temperature| {
+                let humidity = readings.iter().find(|reading| {
+                    reading.id == temperature.id && reading.type_ == SensorType::Humidity
+                })?;
+                match (temperature.value.as_number(), humidity.value.as_number()) {
+                    (Some(t), Some(rh)) => Some(SensorData {
+                        id: temperature.id,
+                        type_: SensorType::HeatIndex,
+                        geolocation: temperature.geolocation,
+                        value: SensorValue::Number(heat_index(t, rh)),
+                        timestamp: temperature.timestamp.max(humidity.timestamp),
+                        derived: true,
+                        quality: None,
+                    }),
+                    _ => None,
                 }
             })
-            .ok_or(http::Error::DeadlineReached)?
-            .1
-            .map_err(|_| http::Error::Unknown)?;
+            .collect()
+    }
 
-        Ok(sensors_data)
+    /// Return every stored reading of the given `type_`, keyed by sensor id.
+    ///
+    /// This iterates the whole `Sensors` map and is therefore unbounded. It is meant for
+    /// off-chain/RPC use only and must never be called from a dispatchable.
+    pub fn all_readings_of_type(type_: SensorType) -> Vec<(SensorIdOf, SensorData)> {
+        <Sensors<T>>::iter()
+            .filter(|(_, t, _)| *t == type_)
+            .map(|(id, _, sensor)| (id, sensor))
+            .collect()
+    }
+
+    /// Exponentially-weighted moving average of the sensor history.
+    ///
+    /// `alpha` is the smoothing factor applied per unit of `timestamp`: a reading that arrives
+    /// `dt` units after the previous one is weighted with `1 - (1 - alpha)^dt`, so readings after
+    /// a long gap pull the average harder than bursts of readings. Readings sharing a timestamp
+    /// count as one unit apart.
+    ///
+    /// Returns `None` when there is no history or it holds non-numeric values.
+    pub fn ewma(id: SensorIdOf, type_: SensorType, alpha: Permill) -> Option<FixedU128> {
+        let history = <SensorHistory<T>>::get(id, type_);
+        let alpha = FixedU128::saturating_from_rational(alpha.deconstruct(), Permill::ACCURACY);
+        let decay = FixedU128::one().saturating_sub(alpha);
+
+        let mut readings = history
+            .iter()
+            .map(|sensor| Some((sensor.timestamp, sensor.value.as_number()?)));
+
+        let (mut last_timestamp, first) = readings.next()??;
+        let mut average = FixedU128::from_u32(first);
+        for reading in readings {
+            let (timestamp, value) = reading?;
+            let dt = timestamp.saturating_sub(last_timestamp).max(1);
+            let keep = decay.saturating_pow(dt.try_into().unwrap_or(usize::MAX));
+            let weight = FixedU128::one().saturating_sub(keep);
+            average = average
+                .saturating_mul(keep)
+                .saturating_add(FixedU128::from_u32(value).saturating_mul(weight));
+            last_timestamp = timestamp;
+        }
+
+        Some(average)
+    }
+
+    /// Value of a numeric sensor at `at`, linearly interpolated between the readings of its
+    /// history closest before and after it, rounded towards the earlier one. A reading taken at
+    /// `at` is returned as is.
+    ///
+    /// Returns `None` when `at` is outside of the history or either reading isn't numeric. Reads
+    /// the whole history of the sensor, so it's meant for off-chain/RPC use only.
+    pub fn interpolate(id: SensorIdOf, type_: SensorType, at: u64) -> Option<u32> {
+        let history = <SensorHistory<T>>::get(id, type_);
+        let before = history.iter().filter(|s| s.timestamp <= at).max_by_key(|s| s.timestamp)?;
+        let after = history.iter().filter(|s| s.timestamp >= at).min_by_key(|s| s.timestamp)?;
+        let (from, to) = (before.value.as_number()?, after.value.as_number()?);
+        if before.timestamp == after.timestamp {
+            return Some(from);
+        }
+
+        // Widened and signed for readings going down.
+        let elapsed = (at - before.timestamp) as i128;
+        let span = (after.timestamp - before.timestamp) as i128;
+        let value = from as i128 + (to as i128 - from as i128) * elapsed / span;
+        Some(value as u32)
+    }
+
+    /// Check a submitted reading before it is added.
+    pub fn validate_reading(sensor: &SensorData) -> Result<(), RejectReason> {
+        if sensor.id == SELF_TEST_SENSOR_ID {
+            return Err(RejectReason::OutOfScope);
+        }
+        if !sensor.type_.accepts(&sensor.value) {
+            return Err(RejectReason::ValueTypeMismatch);
+        }
+        // A `Percent` can't exceed 100, but a humidity submitted as a `Number` can.
+        if sensor.type_ == SensorType::Humidity && sensor.value.as_number().unwrap_or(0) > 100 {
+            return Err(RejectReason::OutOfRange);
+        }
+        if !sensor.geolocation.is_valid() {
+            return Err(RejectReason::OutOfRange);
+        }
+        if Self::require_registration() && !<SensorMetadata<T>>::contains_key(sensor.id) {
+            return Err(RejectReason::Unregistered);
+        }
+        if let Some(stored) = <Sensors<T>>::get(sensor.id, &sensor.type_) {
+            if sensor.timestamp < stored.timestamp {
+                return Err(RejectReason::StaleTimestamp);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bump `EventSeq` for a data event about to be emitted, returning its sequence number.
+    fn next_event_seq() -> u64 {
+        <EventSeq<T>>::mutate(|seq| {
+            *seq = seq.wrapping_add(1);
+            *seq
+        })
+    }
+
+    /// Count a reading rejected for `reason` and report it if `verbosity` asks for it.
+    fn reject_reading(sensor: &SensorData, reason: RejectReason, verbosity: EventVerbosity) {
+        <RejectionCounts<T>>::mutate(reason, |count| *count = count.saturating_add(1));
+        if verbosity.per_reading() {
+            Self::deposit_event(Event::ReadingRejected {
+                id: sensor.id,
+                type_: sensor.type_.clone(),
+                reason,
+            });
+        }
     }
 
-    pub fn add_sensor_data(sensor: SensorData) {
+    /// Bytes authorities sign to confirm `reading` for `submit_with_signatures`: the reading
+    /// SCALE encoded after `SIGNED_READING_CONTEXT`, so the signatures can't be taken for
+    /// anything else.
+    pub fn signed_reading_payload(reading: &SensorData) -> Vec<u8> {
+        (SIGNED_READING_CONTEXT, reading).encode()
+    }
+
+    /// Whether `sensor` is the reading already stored for its sensor. The API keeps serving the
+    /// latest reading of a device until it reports again, so most batches repeat some of them.
+    pub fn is_duplicate(sensor: &SensorData) -> bool {
+        <Sensors<T>>::get(sensor.id, &sensor.type_)
+            .is_some_and(|stored| stored.timestamp == sensor.timestamp)
+    }
+
+    /// Trust score of an authority, between 0 and `MAX_TRUST_SCORE`. Authorities start fully
+    /// trusted.
+    ///
+    /// Every batch with at least one stored or rejected reading scores
+    /// `acceptance * freshness / MAX_TRUST_SCORE`, where `acceptance` is the share of those
+    /// readings that were stored and `freshness` the average freshness of the stored ones, both
+    /// scaled to `MAX_TRUST_SCORE`. A reading is fully fresh when it's no older than the newest
+    /// reading stored before the batch, and loses freshness linearly with its age, down to zero at
+    /// `HistoryRetention`. The trust score then moves an eighth of the way towards the batch score:
+    /// `score = (7 * score + batch_score) / 8`. Batches of duplicates leave it unchanged.
+    pub fn trust_score(who: &T::AccountId) -> u32 {
+        <AuthorityTrust<T>>::get(who)
+    }
+
+    /// Freshness of a reading taken at `timestamp` when the newest reading stored is from
+    /// `latest`, between 0 and `MAX_TRUST_SCORE`.
+    pub fn reading_freshness(latest: u64, timestamp: u64) -> u32 {
+        let retention = T::HistoryRetention::get();
+        if retention == 0 {
+            return MAX_TRUST_SCORE;
+        }
+        let age = latest.saturating_sub(timestamp).min(retention);
+        // Widened so that a long retention can't overflow.
+        ((retention - age) as u128 * MAX_TRUST_SCORE as u128 / retention as u128) as u32
+    }
+
+    /// Fold a batch of `who` into its trust score, given the number of `written` and `rejected`
+    /// readings and the summed `reading_freshness` of the written ones.
+    fn update_trust_score(who: &T::AccountId, written: u32, rejected: u32, freshness: u64) {
+        let submitted = written.saturating_add(rejected) as u64;
+        if submitted == 0 {
+            return;
+        }
+        let max = MAX_TRUST_SCORE as u64;
+        let acceptance = written as u64 * max / submitted;
+        let freshness = match written {
+            0 => 0,
+            written => freshness / written as u64,
+        };
+        let batch_score = acceptance * freshness / max;
+
+        <AuthorityTrust<T>>::mutate(who, |score| {
+            *score = ((7 * *score as u64 + batch_score) / 8) as u32;
+        });
+    }
+
+    /// Weight of `update_sensors_data` for a batch of `readings` readings, of which `written`
+    /// were stored, `rejected` rejected and `held` held back by the debounce. The rest were
+    /// duplicates.
+    pub fn update_sensors_data_weight(
+        readings: u32,
+        written: u32,
+        rejected: u32,
+        held: u32,
+    ) -> Weight {
+        let db = T::DbWeight::get();
+        // The authorities, the verbosity, the latest timestamp, the trust score, whether
+        // registration is required, whether batches are atomic, the event sequence number and the
+        // writes of the block, then the priority of the type and the metadata and stored reading
+        // of every sensor in the batch for ordering, validation and deduplication. The last
+        // submission and batch result of the authority and the writes of the block are written
+        // too.
+        db.reads_writes(8 + 4 * readings as u64, 5)
+            // Unit, debounce count and pending state, alert bounds, history, reading count, latest
+            // timestamp, latest reading of the type and event sequence number for a written
+            // reading, then the pending state, reading, history, reading count, latest timestamp,
+            // latest reading of the type and event sequence number.
+            .saturating_add(db.reads_writes(9, 7).saturating_mul(written as u64))
+            .saturating_add(db.reads_writes(1, 1).saturating_mul(rejected as u64))
+            // Unit, debounce count and pending state, then the pending state.
+            .saturating_add(db.reads_writes(3, 1).saturating_mul(held as u64))
+    }
+
+    /// Weight of `submit_multi_reading` for a reading of `channels` channels, of which `written`
+    /// were stored and `rejected` rejected, like `update_sensors_data_weight`.
+    pub fn submit_multi_reading_weight(channels: u32, written: u32, rejected: u32) -> Weight {
+        let weight = Self::update_sensors_data_weight(channels, written, rejected, 0);
+        // The multi-channel reading itself, stored along with its channels.
+        match written {
+            0 => weight,
+            _ => weight.saturating_add(T::DbWeight::get().writes(1)),
+        }
+    }
+
+    /// Weight of removing `authorities` authorities with `remove_authority`,
+    /// `remove_authorities` or `ratify_remove_authority`.
+    pub fn remove_authority_weight(authorities: u32) -> Weight {
+        let db = T::DbWeight::get();
+        // The authorities and the confirmation threshold, clamped to them, then the proposal to
+        // remove every removed authority, its last submission and batch result and both its
+        // proposals.
+        db.reads_writes(2, 2)
+            .saturating_add(db.reads_writes(1, 5).saturating_mul(authorities as u64))
+    }
+
+    /// Weight of `submit_with_signatures` for a reading confirmed by `signatures` signatures.
+    pub fn submit_with_signatures_weight(signatures: u32) -> Weight {
+        let db = T::DbWeight::get();
+        // Verifying each signature and reading the authorities to check its signer, then the
+        // confirmation threshold, and the reading stored like a batch of one.
+        SIGNATURE_VERIFICATION_WEIGHT
+            .saturating_add(db.reads(1))
+            .saturating_mul(signatures as u64)
+            .saturating_add(db.reads(1))
+            .saturating_add(Self::update_sensors_data_weight(1, 1, 0, 0))
+    }
+
+    /// Drop the readings older than `HistoryRetention` from `SensorHistory`, removing entries left
+    /// empty, for as many entries as `remaining_weight` allows.
+    ///
+    /// Walks the map from `PruneCursor` and saves where it stopped, so successive calls cover the
+    /// whole map a bit at a time. Returns the weight used.
+    pub fn prune_history(remaining_weight: Weight) -> Weight {
+        let db = T::DbWeight::get();
+        // The latest timestamp and the cursor, then writing the cursor back.
+        let mut used = db.reads_writes(2, 1);
+        let per_entry = db.reads_writes(1, 1);
+        if remaining_weight.any_lt(used.saturating_add(per_entry)) {
+            return Weight::zero();
+        }
+
+        let cutoff = <LatestTimestamp<T>>::get().saturating_sub(T::HistoryRetention::get());
+        let mut entries = match <PruneCursor<T>>::get() {
+            Some(cursor) => <SensorHistory<T>>::iter_from(cursor.into_inner()),
+            None => <SensorHistory<T>>::iter(),
+        };
+
+        loop {
+            if remaining_weight.any_lt(used.saturating_add(per_entry)) {
+                match BoundedVec::try_from(entries.last_raw_key().to_vec()) {
+                    Ok(cursor) => <PruneCursor<T>>::put(cursor),
+                    Err(_) => <PruneCursor<T>>::kill(),
+                }
+                break;
+            }
+            let (id, type_, mut history) = match entries.next() {
+                Some(entry) => entry,
+                None => {
+                    <PruneCursor<T>>::kill();
+                    break;
+                }
+            };
+            used = used.saturating_add(per_entry);
+
+            let len = history.len();
+            history.retain(|reading| reading.timestamp >= cutoff);
+            if history.is_empty() {
+                <SensorHistory<T>>::remove(id, &type_);
+            } else if history.len() < len {
+                <SensorHistory<T>>::insert(id, &type_, history);
+            }
+        }
+
+        used
+    }
+
+    /// Number of readings `update_sensors_data` can still write in the current block before
+    /// `MaxWritesPerBlock` is hit.
+    pub fn remaining_writes() -> u32 {
+        let (block, written) = <BlockWrites<T>>::get();
+        match block == <frame_system::Pallet<T>>::block_number() {
+            true => T::MaxWritesPerBlock::get().saturating_sub(written),
+            false => T::MaxWritesPerBlock::get(),
+        }
+    }
+
+    /// Whether the sensor `id` of `type_` has gone more than `StalenessTolerance` of its expected
+    /// intervals without reporting at `now`, in the units of reading timestamps. A sensor without
+    /// a stored reading is stale.
+    pub fn is_stale(id: SensorIdOf, type_: SensorType, now: u64) -> bool {
+        let latest = match <Sensors<T>>::get(id, &type_) {
+            Some(reading) => reading.timestamp,
+            None => return true,
+        };
+        let interval = <ExpectedInterval<T>>::get(id, &type_)
+            .unwrap_or_else(T::DefaultExpectedInterval::get);
+        let max_age = interval.saturating_mul(T::StalenessTolerance::get().into());
+        now.saturating_sub(latest) > max_age
+    }
+
+    /// Every sensor whose latest reading is more than `max_age` milliseconds older than the
+    /// current time of `UnixTime`, with its age, in no particular order.
+    ///
+    /// Measured against the block time rather than the newest reading stored, so that every
+    /// sensor goes stale when the whole feed stops. This iterates the whole `Sensors` map and is
+    /// therefore unbounded. It is meant for off-chain/RPC use only and must never be called from a
+    /// dispatchable.
+    pub fn stale_sensors(max_age: u64) -> Vec<(SensorIdOf, SensorType, u64)> {
+        let now = T::UnixTime::now().as_millis() as u64;
+        <Sensors<T>>::iter()
+            .map(|(id, type_, reading)| (id, type_, now.saturating_sub(reading.timestamp)))
+            .filter(|(_, _, age)| *age > max_age)
+            .collect()
+    }
+
+    /// Remove the latest readings older than `HistoryRetention` from `Sensors`, for as many
+    /// entries as `remaining_weight` allows, emitting `SensorExpired` for each.
+    ///
+    /// Walks the map from `ExpireCursor` like `prune_history` does. Returns the weight used.
+    pub fn expire_sensors(remaining_weight: Weight) -> Weight {
+        let db = T::DbWeight::get();
+        // The latest timestamp and the cursor, then writing the cursor back.
+        let mut used = db.reads_writes(2, 1);
+        let per_entry = db.reads_writes(1, 1);
+        if remaining_weight.any_lt(used.saturating_add(per_entry)) {
+            return Weight::zero();
+        }
+
+        let cutoff = <LatestTimestamp<T>>::get().saturating_sub(T::HistoryRetention::get());
+        let mut entries = match <ExpireCursor<T>>::get() {
+            Some(cursor) => <Sensors<T>>::iter_from(cursor.into_inner()),
+            None => <Sensors<T>>::iter(),
+        };
+
+        loop {
+            if remaining_weight.any_lt(used.saturating_add(per_entry)) {
+                match BoundedVec::try_from(entries.last_raw_key().to_vec()) {
+                    Ok(cursor) => <ExpireCursor<T>>::put(cursor),
+                    Err(_) => <ExpireCursor<T>>::kill(),
+                }
+                break;
+            }
+            let (id, type_, reading) = match entries.next() {
+                Some(entry) => entry,
+                None => {
+                    <ExpireCursor<T>>::kill();
+                    break;
+                }
+            };
+            used = used.saturating_add(per_entry);
+
+            if reading.timestamp < cutoff {
+                <Sensors<T>>::remove(id, &type_);
+                Self::deposit_event(Event::SensorExpired { id, type_ });
+            }
+        }
+
+        used
+    }
+
+    /// Number of readings rejected for each reason since the counts were last reset.
+    pub fn rejection_counts() -> Vec<(RejectReason, u64)> {
+        <RejectionCounts<T>>::iter().collect()
+    }
+
+    /// Health summary of the oracle.
+    ///
+    /// Iterates the whole `Sensors`, `RejectionCounts` and `LastSubmission` maps, so it's meant
+    /// for RPC/off-chain use only.
+    pub fn oracle_status() -> OracleStatus<BlockNumberFor<T>> {
+        OracleStatus {
+            authorities: <Authorities<T>>::decode_len().unwrap_or(0) as u32,
+            sensors: <Sensors<T>>::iter_keys()
+                .map(|(id, _)| id)
+                .collect::<sp_std::collections::btree_set::BTreeSet<_>>()
+                .len() as u32,
+            readings_stored: Self::readings_stored(),
+            readings_rejected: <RejectionCounts<T>>::iter_values()
+                .fold(0, |total: u64, count| total.saturating_add(count)),
+            last_submission: <LastSubmission<T>>::iter_values().max(),
+            ocw_enabled: cfg!(feature = "ocw"),
+        }
+    }
+
+    /// Write a synthetic reading through `add_sensor_data`, read it back and remove it again.
+    /// Nothing outside the pallet sees it: `OnSensorUpdate` isn't called and no
+    /// `SensorDataAdded` event is emitted for it.
+    ///
+    /// Returns whether the stored reading matched the one written.
+    pub fn self_test() -> bool {
+        let reading = SensorData {
+            id: SELF_TEST_SENSOR_ID,
+            type_: SensorType::Digital,
+            geolocation: Geolocation { lat: 0, lon: 0 },
+            value: SensorValue::Bool(true),
+            timestamp: 0,
+            derived: false,
+            quality: None,
+        };
+
+        Self::add_sensor_data(reading.clone());
+        let stored = <Sensors<T>>::take(SELF_TEST_SENSOR_ID, &reading.type_);
+        if stored.is_some() {
+            <ReadingsStored<T>>::mutate(|count| *count = count.saturating_sub(1));
+        }
+        <SensorHistory<T>>::remove(SELF_TEST_SENSOR_ID, &reading.type_);
+        <LatestByType<T>>::mutate_exists(&reading.type_, |latest| {
+            if latest.as_ref().is_some_and(|latest| latest.id == SELF_TEST_SENSOR_ID) {
+                *latest = None;
+            }
+        });
+
+        stored == Some(reading)
+    }
+
+    /// Convert a numeric or fixed-point reading from the unit registered for its sensor to the
+    /// canonical unit of its type, recording the raw value in a `SensorDataConverted` event.
+    ///
+    /// The event is emitted whatever the event verbosity, as it's the only record of the reading
+    /// as reported.
+    fn convert_to_canonical_unit(sensor: &mut SensorData) {
+        let unit = match <SensorMetadata<T>>::get(sensor.id).and_then(|info| info.unit) {
+            Some(unit) if unit.measures(&sensor.type_) => unit,
+            _ => return,
+        };
+
+        let raw_value = sensor.value;
+        sensor.value = match raw_value {
+            SensorValue::Number(x) => unit.to_canonical(x),
+            SensorValue::Fixed(x) => SensorValue::Fixed(unit.to_canonical_fixed(x)),
+            _ => return,
+        };
+        if sensor.value != raw_value {
+            Self::deposit_event(Event::SensorDataConverted {
+                id: sensor.id,
+                type_: sensor.type_.clone(),
+                raw_unit: unit,
+                raw_value,
+            });
+        }
+    }
+
+    /// Emit `ThresholdBreached` if a numeric or fixed-point reading falls outside the alert
+    /// bounds of its sensor.
+    fn check_alert_threshold(sensor: &SensorData) {
+        // Compared in thousandths, so that the whole-number bounds apply to `Fixed` readings too.
+        let value = match sensor.value.as_fixed() {
+            Some(value) => value,
+            None => return,
+        };
+        let (low, high) = match <AlertThresholds<T>>::get(sensor.id, &sensor.type_) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let thousandths = |bound: u32| i64::from(bound) * 10i64.pow(FIXED_DECIMALS);
+
+        let breached = match (low, high) {
+            (Some(low), _) if value < thousandths(low) => Some(low),
+            (_, Some(high)) if value > thousandths(high) => Some(high),
+            _ => None,
+        };
+        if let Some(bound) = breached {
+            Self::deposit_event(Event::ThresholdBreached {
+                id: sensor.id,
+                type_: sensor.type_.clone(),
+                value: sensor.value,
+                bound,
+            });
+        }
+    }
+
+    /// Whether a reading is stored, holding back a state change of a digital sensor with a
+    /// `DebounceCounts` entry until it's been reported in that many consecutive submissions.
+    ///
+    /// A reading of the stored state, or of a sensor without a stored reading, is always stored
+    /// and cancels a pending change, so a device flipping back and forth never changes state.
+    fn debounce(sensor: &SensorData) -> bool {
+        let state = match (&sensor.type_, &sensor.value) {
+            (SensorType::Digital, SensorValue::Bool(state)) => *state,
+            _ => return true,
+        };
+        let count = match <DebounceCounts<T>>::get(sensor.id) {
+            Some(count) => count,
+            None => return true,
+        };
+        let changed = match <Sensors<T>>::get(sensor.id, SensorType::Digital) {
+            Some(stored) => stored.value != SensorValue::Bool(state),
+            None => false,
+        };
+        if !changed {
+            <DigitalDebounce<T>>::remove(sensor.id);
+            return true;
+        }
+
+        let streak = match <DigitalDebounce<T>>::get(sensor.id) {
+            Some((pending, streak)) if pending == state => streak.saturating_add(1),
+            _ => 1,
+        };
+        if streak >= count {
+            <DigitalDebounce<T>>::remove(sensor.id);
+            true
+        } else {
+            <DigitalDebounce<T>>::insert(sensor.id, (state, streak));
+            false
+        }
+    }
+
+    /// Store a validated reading, unless the debounce holds it back. Returns whether it was
+    /// stored.
+    pub fn add_sensor_data(mut sensor: SensorData) -> bool {
+        Self::convert_to_canonical_unit(&mut sensor);
+        if !Self::debounce(&sensor) {
+            return false;
+        }
+
         let id = sensor.id;
-        let type_ = sensor.type_;
-        <Sensors<T>>::insert(id, type_, sensor);
-        Self::deposit_event(Event::SensorDataAdded { id, type_ })
+        let type_ = sensor.type_.clone();
+        <Sensors<T>>::insert(id, &type_, &sensor);
+        <ReadingsStored<T>>::mutate(|count| *count = count.saturating_add(1));
+        <LatestTimestamp<T>>::mutate(|latest| *latest = (*latest).max(sensor.timestamp));
+        <LatestByType<T>>::mutate(&type_, |latest| {
+            if latest.as_ref().is_none_or(|latest| sensor.timestamp > latest.timestamp) {
+                *latest = Some(sensor.clone());
+            }
+        });
+        // The reading of `run_self_test` is synthetic: other pallets aren't told about it, it
+        // raises no alert and takes no `EventSeq` number.
+        let synthetic = id == SELF_TEST_SENSOR_ID;
+        if !synthetic {
+            T::OnSensorUpdate::on_sensor_update(&sensor);
+            Self::check_alert_threshold(&sensor);
+        }
+        <SensorHistory<T>>::mutate(id, &type_, |history| {
+            // Drop the oldest reading to make room once the history is full.
+            if let Err(sensor) = history.try_push(sensor) {
+                if !history.is_empty() {
+                    history.remove(0);
+                    let _ = history.try_push(sensor);
+                }
+            }
+        });
+        if !synthetic && Self::event_verbosity().per_reading() {
+            Self::deposit_event(Event::SensorDataAdded { id, type_, seq: Self::next_event_seq() })
+        }
+        true
     }
 }
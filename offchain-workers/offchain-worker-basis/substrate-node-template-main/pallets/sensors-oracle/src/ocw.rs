@@ -0,0 +1,563 @@
+//! The offchain worker fetching the readings of the sensors API over HTTP and submitting them
+//! on-chain, compiled with the `ocw` feature.
+
+use crate::{pallet::*, types::*, *};
+
+use core::fmt::Write;
+
+use frame_system::offchain::{SendSignedTransaction, Signer};
+use sp_runtime::{
+    offchain::{http, Duration, Timestamp},
+    RuntimeAppPublic,
+};
+
+/// Backoff of an open circuit breaker is capped at 2^10 blocks.
+const MAX_BACKOFF_EXPONENT: u32 = 10;
+
+/// Time the offchain worker gives the sensors API to answer all of its requests.
+pub(crate) const FETCH_TIMEOUT_MS: u64 = 2_000;
+
+impl<T: Config> Pallet<T> {
+    /// Fetch and submit the sensors data, unless the circuit breaker is open.
+    ///
+    /// After `MaxConsecutiveFailures` failed fetches in a row the breaker opens: the worker skips
+    /// 2 blocks, then 4, 8, ... after every further failure, until a successful run closes it.
+    pub fn run_offchain_worker(block_number: BlockNumberFor<T>) {
+        let mut breaker = StorageValueRef::persistent(BREAKER_KEY);
+        let mut state = breaker
+            .get::<BreakerState<BlockNumberFor<T>>>()
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        if block_number < state.retry_at {
+            log::debug!("Circuit breaker open, skipping until block {:?}", state.retry_at);
+            return;
+        }
+
+        let max = T::MaxConsecutiveFailures::get();
+        match Self::get_sensors_data() {
+            Ok(_) => {
+                log::info!("Sensors data updated...");
+                if state.failures >= max {
+                    log::info!("Sensors data fetched again, closing the circuit breaker");
+                }
+                breaker.clear();
+                if let Err(e) = Self::propose_silent_authorities(block_number) {
+                    log::error!("Failed to propose the removal of silent authorities: {:?}", e);
+                }
+            }
+            Err(
+                e @ (Error::<T>::HttpFetchFailed
+                | Error::<T>::DecompressFailed
+                | Error::<T>::DeserializeError),
+            ) => {
+                log::error!("Failed to update sensors data: {:?}", e);
+                state.failures = state.failures.saturating_add(1);
+                if let Some(excess) = state.failures.checked_sub(max) {
+                    if excess == 0 {
+                        log::warn!("{} failures in a row, opening the circuit breaker", max);
+                    }
+                    let delay = 1u32 << excess.saturating_add(1).min(MAX_BACKOFF_EXPONENT);
+                    state.retry_at = block_number.saturating_add(delay.into());
+                    log::warn!("Skipping the offchain worker until block {:?}", state.retry_at);
+                }
+                breaker.set(&state);
+            }
+            // Not the API's fault, so they don't count towards opening the breaker.
+            Err(e) => log::error!("Failed to update sensors data: {:?}", e),
+        }
+    }
+
+    /// Fetch the sensors data and submit it on-chain through a signed transaction.
+    pub fn get_sensors_data() -> Result<Vec<SensorData>, Error<T>> {
+        let keys = Self::authority_keys()?;
+        Self::log_last_batch_results(&keys);
+        let signing_keys = Self::signing_keys(keys);
+        Self::pass_signer_turn();
+        let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(signing_keys);
+
+        let mut sensors_data = match Self::local_fixture() {
+            Some(body) => Self::process_sensors(&body, Self::response_format())?,
+            None => Self::fetch_sensors_data()?,
+        };
+        let every = Self::sample_every();
+        if every > 1 {
+            sensors_data.retain(|sensor| Self::sample(sensor, every));
+        }
+        let checksum = Self::readings_checksum(&sensors_data);
+        let last_submission = StorageValueRef::persistent(LAST_SUBMISSION_KEY);
+        if let Ok(Some(last)) = last_submission.get::<Vec<SensorData>>() {
+            log::debug!(
+                "Readings changed since the last submission: {:?}",
+                Self::diff_readings(&last, &sensors_data)
+            );
+        }
+
+        signer
+            .send_signed_transaction(|account| {
+                log::info!("Account, {:?}, {:?}", account.id, account.public);
+                Call::<T>::update_sensors_data {
+                    updated_data: sensors_data.clone(),
+                    checksum,
+                }
+            })
+            .ok_or(Error::<T>::NoLocalKey)?
+            .1
+            .map_err(|()| Self::submit_failed(SubmitFailure::PoolRejected))?;
+        last_submission.set(&sensors_data);
+
+        Ok(sensors_data)
+    }
+
+    /// Propose the removal of another authority silent at `block_number` that isn't proposed
+    /// already, signing with one of our authority keys without a proposal pending. Each of them
+    /// can only have one, see `PendingProposals`, so at most one is proposed per run.
+    pub fn propose_silent_authorities(block_number: BlockNumberFor<T>) -> Result<(), Error<T>> {
+        let keys = Self::authority_keys()?;
+        let ours: Vec<T::AccountId> = keys.iter().map(|key| key.clone().into_account()).collect();
+        let proposer = match keys
+            .into_iter()
+            .find(|key| !<PendingProposals<T>>::contains_key(key.clone().into_account()))
+        {
+            Some(proposer) => proposer,
+            None => return Ok(()),
+        };
+        let silent = <Authorities<T>>::get()
+            .into_iter()
+            .filter(|authority| !ours.contains(authority))
+            .filter(|authority| !<RemovalProposals<T>>::contains_key(authority))
+            .find(|authority| Self::is_silent(authority, block_number));
+        let candidate = match silent {
+            Some(candidate) => candidate,
+            None => return Ok(()),
+        };
+
+        log::info!("Proposing the removal of silent authority {:?}", candidate);
+        Signer::<T, T::AuthorityId>::any_account()
+            .with_filter(sp_std::vec![proposer])
+            .send_signed_transaction(|_| Call::<T>::propose_remove_authority {
+                candidate: candidate.clone(),
+            })
+            .ok_or(Error::<T>::NoLocalKey)?
+            .1
+            .map_err(|()| Self::submit_failed(SubmitFailure::PoolRejected))?;
+
+        Ok(())
+    }
+
+    /// Log the outcome of the last batch submitted with each of `keys`, so operators see how much
+    /// of it was stored.
+    fn log_last_batch_results(keys: &[T::Public]) {
+        for key in keys {
+            let account = key.clone().into_account();
+            if let Some(result) = Self::last_batch_result(&account) {
+                log::info!(
+                    "Last batch of {:?}: {} readings stored, {} rejected",
+                    account,
+                    result.accepted,
+                    result.rejected,
+                );
+            }
+        }
+    }
+
+    /// Public keys of the oracle keys in the keystore the worker can sign with: the preferred
+    /// signer if one is set, and only those of authorities.
+    fn authority_keys() -> Result<Vec<T::Public>, Error<T>> {
+        let mut keys = Self::allowed_keys();
+        let preferred = Self::signer_strategy() == SignerStrategy::Preferred;
+        // Without a key of our `KEY_TYPE` in the keystore there is nobody to sign the
+        // transaction, so don't bother hitting the API.
+        if keys.is_empty() {
+            log::error!(
+                "No oracle key in keystore; insert one via `author_insertKey` (key type `sens`)."
+            );
+            if preferred {
+                log::error!(
+                    "The preferred signer in offchain local storage is unset or not in the keystore"
+                );
+            }
+            return Err(Error::<T>::NoLocalKey);
+        }
+        // The transaction of an account that isn't an authority would be included and fail.
+        let authorities = <Authorities<T>>::get();
+        if authorities.is_empty() {
+            log::error!("No authorities configured; add one via `add_authority` or the genesis");
+            return Err(Error::<T>::NoAuthoritiesConfigured);
+        }
+        keys.retain(|key| authorities.contains(&key.clone().into_account()));
+        if keys.is_empty() {
+            return Err(Self::submit_failed(SubmitFailure::NotAuthority));
+        }
+
+        Ok(keys)
+    }
+
+    /// The oracle keys in the keystore the signer strategy allows signing with: only the
+    /// preferred signer with `SignerStrategy::Preferred`, all of them otherwise.
+    fn allowed_keys() -> Vec<T::Public> {
+        let mut keys = Self::oracle_keys();
+        if Self::signer_strategy() == SignerStrategy::Preferred {
+            let signer = Self::preferred_signer();
+            keys.retain(|key| Some(key) == signer.as_ref());
+        }
+        keys
+    }
+
+    /// The authority keys among `keys` the batch of this run can be signed with: the next one in
+    /// turn with `SignerStrategy::RoundRobin`, all of them otherwise.
+    fn signing_keys(mut keys: Vec<T::Public>) -> Vec<T::Public> {
+        if keys.is_empty() || Self::signer_strategy() != SignerStrategy::RoundRobin {
+            return keys;
+        }
+        // The keystore doesn't list its keys in any particular order.
+        keys.sort();
+        let index = StorageValueRef::persistent(SIGNER_INDEX_KEY)
+            .get::<u32>()
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        sp_std::vec![keys.swap_remove(index as usize % keys.len())]
+    }
+
+    /// Hand the turn to sign to the next key with `SignerStrategy::RoundRobin`.
+    fn pass_signer_turn() {
+        let next = StorageValueRef::persistent(SIGNER_INDEX_KEY);
+        let index = next.get::<u32>().ok().flatten().unwrap_or(0);
+        next.set(&index.wrapping_add(1));
+    }
+
+    /// The key the batch of this run is signed with, if any: the first of its `signing_keys`, as
+    /// `Signer::any_account` signs with the first key of its filter that is in the keystore.
+    pub fn next_signer() -> Option<T::Public> {
+        let authorities = <Authorities<T>>::get();
+        let mut keys = Self::allowed_keys();
+        keys.retain(|key| authorities.contains(&key.clone().into_account()));
+        Self::signing_keys(keys).into_iter().next()
+    }
+
+    /// Public keys of the oracle keys of `KEY_TYPE` in the keystore.
+    fn oracle_keys() -> Vec<T::Public> {
+        <T::AuthorityId as AppCrypto<T::Public, T::Signature>>::RuntimeAppPublic::all()
+            .into_iter()
+            .map(|key| {
+                let generic: <T::AuthorityId as AppCrypto<T::Public, T::Signature>>::GenericPublic =
+                    key.into();
+                generic.into()
+            })
+            .collect()
+    }
+
+    fn submit_failed(reason: SubmitFailure) -> Error<T> {
+        log::error!("Failed to submit sensors data: {:?}", reason);
+        Error::<T>::SubmitFailed(reason)
+    }
+
+    /// Fetch the sensors data from the API and process it with `process_sensors`.
+    ///
+    /// Fetches every sensor listed under `SENSOR_IDS_KEY` from `<DefaultApiEndpoint>/<id>`, up to
+    /// `max_concurrent_requests` at a time, and merges the readings of those that succeeded, or
+    /// the whole list from `DefaultApiEndpoint` if there is none.
+    ///
+    /// The readings of the types in `TypeEndpoints` are fetched from their endpoint instead, and
+    /// merged with the others. Each endpoint is fetched on its own: the readings of one that fails
+    /// are left out of the batch, and it only fails if all of them do.
+    pub fn fetch_sensors_data() -> Result<Vec<SensorData>, Error<T>> {
+        // We want to keep the offchain worker execution time reasonable, so we set a hard-coded
+        // deadline of `FETCH_TIMEOUT_MS` to complete the external calls. You can also wait
+        // indefinitely for the response, however you may still get a timeout coming from the host
+        // machine.
+        let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(FETCH_TIMEOUT_MS));
+        let config = Self::request_config();
+        let format = Self::response_format();
+
+        let default = Self::fetch_default_endpoint(deadline, &config, format);
+        let endpoints: Vec<_> = <TypeEndpoints<T>>::iter().collect();
+        if endpoints.is_empty() {
+            return default;
+        }
+        let (mut sensors_data, default_error) = match default {
+            Ok(readings) => (readings, None),
+            Err(e) => {
+                log::warn!("Skipping the readings of the default endpoint");
+                (Vec::new(), Some(e))
+            }
+        };
+        let mut fetched = default_error.is_none();
+
+        // Heat indices are derived again once the readings of every endpoint are merged, as their
+        // temperature and humidity may come from different ones.
+        let routed = |type_: &SensorType| endpoints.iter().any(|(routed, _)| routed == type_);
+        sensors_data.retain(|sensor| !sensor.derived && !routed(&sensor.type_));
+        for chunk in endpoints.chunks(Self::max_concurrent_requests() as usize) {
+            let mut pending = Vec::new();
+            for (type_, endpoint) in chunk {
+                let request = core::str::from_utf8(endpoint)
+                    .map_err(|_| Error::<T>::InvalidEndpoint)
+                    .and_then(|uri| Self::send_request(uri, deadline, &config, format));
+                match request {
+                    Ok(request) => pending.push((type_, request)),
+                    Err(_) => log::warn!("Failed to send the request for {:?} readings", type_),
+                }
+            }
+
+            let (types, requests): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+            let responses = http::PendingRequest::try_wait_all(requests, deadline);
+            for (type_, response) in types.into_iter().zip(responses) {
+                let readings = match response {
+                    Ok(Ok(response)) => Self::read_response(response)
+                        .and_then(|body| Self::process_sensors(&body, format)),
+                    Ok(Err(e)) => Err(Self::fetch_failed(e)),
+                    Err(_) => Err(Self::fetch_failed(http::Error::DeadlineReached)),
+                };
+                match readings {
+                    Ok(readings) => {
+                        fetched = true;
+                        sensors_data.extend(
+                            readings.into_iter().filter(|sensor| sensor.type_ == *type_),
+                        );
+                    }
+                    Err(_) => log::warn!("Skipping the {:?} readings", type_),
+                }
+            }
+        }
+        match default_error {
+            Some(e) if !fetched => return Err(e),
+            _ => (),
+        }
+        let derived = Self::derive_heat_indices(&sensors_data);
+        sensors_data.extend(derived);
+
+        Ok(sensors_data)
+    }
+
+    /// Fetch the readings from `DefaultApiEndpoint`, as described in `fetch_sensors_data`.
+    fn fetch_default_endpoint(
+        deadline: Timestamp,
+        config: &RequestConfig,
+        format: ResponseFormat,
+    ) -> Result<Vec<SensorData>, Error<T>> {
+        let endpoint = T::DefaultApiEndpoint::get();
+
+        let ids = Self::sensor_ids();
+        if ids.is_empty() {
+            let pending = Self::send_request(endpoint, deadline, config, format)?;
+            // The request is already being processed by the host, we are free to do anything else
+            // in the worker (we can send multiple concurrent requests too). At some point however
+            // we probably want to check the response though, so we can block current thread and
+            // wait for it to finish. Note that since the request is being driven by the host, we
+            // don't have to wait for the request to have it complete, we will just not read the
+            // response.
+            let response = pending
+                .try_wait(deadline)
+                .map_err(|_| Self::fetch_failed(http::Error::DeadlineReached))?
+                .map_err(Self::fetch_failed)?;
+            let body = Self::read_response(response)?;
+            return Self::process_sensors(&body, format);
+        }
+
+        // Send up to `max_concurrent_requests` requests at once and wait for all of them before
+        // sending the next ones, all within the same deadline.
+        let mut sensors_data = Vec::new();
+        let mut fetched = false;
+        for chunk in ids.chunks(Self::max_concurrent_requests() as usize) {
+            let mut pending = Vec::new();
+            for id in chunk {
+                let mut uri = sp_std::Writer::default();
+                // Writing to memory can't fail.
+                let _ = write!(uri, "{}/{}", endpoint, id);
+                match core::str::from_utf8(uri.inner()) {
+                    Ok(uri) => match Self::send_request(uri, deadline, config, format) {
+                        Ok(request) => pending.push((*id, request)),
+                        Err(_) => log::warn!("Failed to send the request for sensor {}", id),
+                    },
+                    Err(_) => log::warn!("Invalid URI for sensor {}", id),
+                }
+            }
+
+            let (ids, requests): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+            let responses = http::PendingRequest::try_wait_all(requests, deadline);
+            for (id, response) in ids.into_iter().zip(responses) {
+                let response = match response {
+                    Ok(Ok(response)) => response,
+                    Ok(Err(e)) => {
+                        log::warn!("Failed to fetch sensor {}: {:?}", id, e);
+                        continue;
+                    }
+                    Err(_) => {
+                        log::warn!("Deadline reached fetching sensor {}", id);
+                        continue;
+                    }
+                };
+                // Heat indices are derived from readings of the same sensor, so deriving them per
+                // response gives the same readings as deriving them from the merged list.
+                let readings =
+                    Self::read_response(response).and_then(|b| Self::process_sensors(&b, format));
+                match readings {
+                    Ok(readings) => {
+                        sensors_data.extend(readings);
+                        fetched = true;
+                    }
+                    Err(_) => log::warn!("Skipping the response for sensor {}", id),
+                }
+            }
+        }
+
+        // Submit what was fetched, unless every sensor failed.
+        match fetched {
+            true => Ok(sensors_data),
+            false => Err(Error::<T>::HttpFetchFailed),
+        }
+    }
+
+    /// Send `config` to `uri` asking for `format` and a gzip-compressed response.
+    fn send_request(
+        uri: &str,
+        deadline: Timestamp,
+        config: &RequestConfig,
+        format: ResponseFormat,
+    ) -> Result<http::PendingRequest, Error<T>> {
+        // Initiate an external HTTP request. This is using high-level wrappers from
+        // `sp_runtime`, for the low-level calls that you can find in `sp_io`. The API is trying to
+        // be similar to `request`, but since we are running in a custom WASM execution environment
+        // we can't simply import the library here.
+        let method = match config.method {
+            HttpMethod::Get => http::Method::Get,
+            HttpMethod::Post => http::Method::Post,
+        };
+        let compress = config.method == HttpMethod::Post && Self::compress_requests();
+        // An empty chunk marks the end of the body for the host, so don't send one up front.
+        let body: Vec<Vec<u8>> = config
+            .body
+            .iter()
+            .filter(|b| !b.is_empty())
+            .map(|b| if compress { gzip::compress(b) } else { b.clone() })
+            .collect();
+        let gzipped = compress && !body.is_empty();
+        // Both are UTF-8, so their concatenation is too.
+        let proxied = Self::http_proxy().map(|proxy| [proxy, uri.as_bytes().to_vec()].concat());
+        let uri = match &proxied {
+            Some(proxied) => core::str::from_utf8(proxied).unwrap_or(uri),
+            None => uri,
+        };
+        let mut request = http::Request::new(uri)
+            .method(method)
+            .body(body)
+            .add_header("Accept", format.media_type())
+            .add_header("Accept-Encoding", "gzip");
+        if gzipped {
+            request = request.add_header("Content-Encoding", "gzip");
+        }
+        for (name, value) in &config.headers {
+            match (core::str::from_utf8(name), core::str::from_utf8(value)) {
+                (Ok(name), Ok(value)) => request = request.add_header(name, value),
+                _ => log::warn!("Skipping configured request header that isn't UTF-8"),
+            }
+        }
+        // We set the deadline for sending of the request, note that awaiting response can have a
+        // separate deadline. Next we send the request, before that it's also possible to alter
+        // request headers or stream body content in case of non-GET requests.
+        request.deadline(deadline).send().map_err(|_| Self::fetch_failed(http::Error::IoError))
+    }
+
+    /// Return the body of a response of the API, decompressing it if it's gzip-encoded.
+    fn read_response(mut response: http::Response) -> Result<Vec<u8>, Error<T>> {
+        // Let's check the status code before we proceed to reading the response.
+        if response.code != 200 {
+            log::warn!("Unexpected status code: {}", response.code);
+            return Err(Error::<T>::HttpFetchFailed);
+        }
+
+        // Next we want to fully read the response body and collect it to a vector of bytes. Note
+        // that the return object allows you to read the body in chunks as well with a way to
+        // control the deadline.
+        let mut gzipped = false;
+        let mut headers = response.headers().into_iter();
+        while headers.next() {
+            if let Some((name, value)) = headers.current() {
+                gzipped |= name.eq_ignore_ascii_case("Content-Encoding")
+                    && value.trim().eq_ignore_ascii_case("gzip");
+            }
+        }
+        let mut body = response.body().collect::<Vec<u8>>();
+        if gzipped {
+            body = gzip::decompress(&body).ok_or_else(|| {
+                log::warn!("Failed to decompress gzip-encoded sensors data");
+                Error::<T>::DecompressFailed
+            })?;
+        }
+
+        Ok(body)
+    }
+
+    /// Count `sensor` in the `SampleCounter` of its sensor if it's newer than the last reading
+    /// counted, returning whether it's the first of every `every` readings and so submitted.
+    fn sample(sensor: &SensorData, every: u32) -> bool {
+        let key = [SAMPLE_COUNTER_PREFIX, &(sensor.id, &sensor.type_).encode()].concat();
+        let counter = StorageValueRef::persistent(&key);
+        let last = match counter.get::<SampleCounter>() {
+            Ok(last) => last,
+            Err(_) => {
+                log::warn!("Resetting undecodable sample counter of sensor {}", sensor.id);
+                None
+            }
+        };
+        let count = match last {
+            Some(last) if sensor.timestamp <= last.timestamp => return false,
+            Some(last) => last.count.saturating_add(1) % every,
+            None => 0,
+        };
+        counter.set(&SampleCounter { timestamp: sensor.timestamp, count });
+        count == 0
+    }
+
+    /// The configuration the offchain worker runs with this cycle.
+    pub fn ocw_config() -> OcwConfig<BlockNumberFor<T>, T::Public> {
+        OcwConfig {
+            endpoint: T::DefaultApiEndpoint::get(),
+            sensor_ids: Self::sensor_ids(),
+            timeout_ms: FETCH_TIMEOUT_MS,
+            max_concurrent_requests: Self::max_concurrent_requests(),
+            method: Self::request_config().method,
+            format: Self::response_format(),
+            local_fixture: sp_io::offchain::local_storage_get(
+                StorageKind::PERSISTENT,
+                USE_LOCAL_FIXTURE_KEY,
+            )
+            .is_some(),
+            sample_every: Self::sample_every(),
+            max_failures: T::MaxConsecutiveFailures::get(),
+            grace_period: T::GracePeriod::get(),
+            signer_strategy: Self::signer_strategy(),
+            signer: Self::next_signer(),
+        }
+    }
+
+    /// Log `ocw_config`, so that a misconfigured node shows in its logs rather than only through
+    /// missing readings.
+    pub(crate) fn log_ocw_config() {
+        let config = Self::ocw_config();
+        log::info!(
+            "Offchain worker config: endpoint {}, {} sensor ids, timeout {}ms, {} concurrent \
+             requests, {:?} {:?}, local fixture {}, sampling 1 in {}, breaker after {} failures, \
+             grace period {:?}, signer strategy {:?}, signer {:?}",
+            config.endpoint,
+            config.sensor_ids.len(),
+            config.timeout_ms,
+            config.max_concurrent_requests,
+            config.method,
+            config.format,
+            config.local_fixture,
+            config.sample_every,
+            config.max_failures,
+            config.grace_period,
+            config.signer_strategy,
+            config.signer,
+        );
+    }
+
+    fn fetch_failed(e: http::Error) -> Error<T> {
+        log::warn!("Failed to fetch sensors data: {:?}", e);
+        Error::<T>::HttpFetchFailed
+    }
+}
@@ -18,19 +18,28 @@
 use crate as example_offchain_worker;
 use crate::*;
 use codec::Decode;
-use frame_support::{assert_noop, assert_ok, derive_impl, parameter_types, traits::ConstU64};
+use frame_support::{
+	assert_noop, assert_ok, derive_impl, parameter_types,
+	traits::{ConstU64, Hooks},
+};
 use pallet::config_preludes::*;
+use proptest::prelude::*;
+#[cfg(feature = "ocw")]
+use sp_core::offchain::{OffchainWorkerExt, TransactionPoolExt};
 use sp_core::{
-	offchain::{testing, OffchainWorkerExt, TransactionPoolExt},
+	offchain::{testing, OffchainDbExt},
 	sr25519::Signature,
 	H256,
 };
 
+#[cfg(feature = "ocw")]
 use sp_keystore::{testing::MemoryKeystore, Keystore, KeystoreExt};
+#[cfg(feature = "ocw")]
+use sp_runtime::RuntimeAppPublic;
 use sp_runtime::{
 	testing::TestXt,
 	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
-	RuntimeAppPublic,
+	BuildStorage,
 };
 
 type Block = frame_system::mocking::MockBlock<Test>;
@@ -40,7 +49,7 @@ frame_support::construct_runtime!(
 	pub enum Test
 	{
 		System: frame_system::{Pallet, Call, Config<T>, Storage, Event<T>},
-		PriceOracleOcwExample: example_offchain_worker::{Pallet, Call, Storage, Event<T>},
+		PriceOracleOcwExample: example_offchain_worker::{Pallet, Call, Config<T>, Storage, Event<T>},
 	}
 );
 
@@ -48,7 +57,7 @@ impl frame_system::Config for Test {
 	type BaseCallFilter = frame_support::traits::Everything;
 	type BlockWeights = ();
 	type BlockLength = ();
-	type DbWeight = ();
+	type DbWeight = frame_support::weights::constants::RocksDbWeight;
 	type RuntimeOrigin = RuntimeOrigin;
 	type Nonce = u64;
 	type Hash = H256;
@@ -102,6 +111,21 @@ where
 
 parameter_types! {
 	pub const UnsignedPriority: u64 = 1 << 20;
+	pub static MinAuthorities: u32 = 0;
+	pub static SensorsApiEndpoint: &'static str = SENSORS_API_URL;
+	pub static AutoExpire: bool = false;
+	pub static MaxWritesPerBlock: u32 = u32::MAX;
+	/// Current time of `MockTime`, in milliseconds.
+	pub static Now: u64 = 0;
+}
+
+/// Block time of the tests, set with `Now`.
+pub struct MockTime;
+
+impl frame_support::traits::UnixTime for MockTime {
+	fn now() -> core::time::Duration {
+		core::time::Duration::from_millis(Now::get())
+	}
 }
 
 #[derive_impl(TestDefaultConfig as pallet::DefaultConfig)]
@@ -109,12 +133,59 @@ impl Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type AuthorityId = crypto::TestAuthId;
 	type GracePeriod = ConstU64<5>;
+	type SilenceThreshold = ConstU64<20>;
+	type DefaultApiEndpoint = SensorsApiEndpoint;
+	type MinAuthorities = MinAuthorities;
+	type AutoExpire = AutoExpire;
+	type MaxWritesPerBlock = MaxWritesPerBlock;
+	type OnSensorUpdate = RecordUpdates;
+	type UnixTime = MockTime;
+}
+
+thread_local! {
+	static UPDATES: std::cell::RefCell<Vec<SensorData>> = Default::default();
+}
+
+/// Records every reading the pallet reports as stored.
+pub struct RecordUpdates;
+
+impl SensorUpdateHandler<SensorData> for RecordUpdates {
+	fn on_sensor_update(reading: &SensorData) {
+		UPDATES.with(|updates| updates.borrow_mut().push(reading.clone()));
+	}
 }
 
 fn test_pub() -> sp_core::sr25519::Public {
 	sp_core::sr25519::Public::from_raw([1u8; 32])
 }
 
+fn account(seed: u8) -> sp_core::sr25519::Public {
+	sp_core::sr25519::Public::from_raw([seed; 32])
+}
+
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = sp_io::TestExternalities::default();
+	t.execute_with(|| System::set_block_number(1));
+	t
+}
+
+/// Asserts that no events were deposited since the last `System::reset_events`.
+fn assert_no_events() {
+	assert_eq!(System::events().into_iter().map(|record| record.event).collect::<Vec<_>>(), vec![]);
+}
+
+fn sensor(id: u32, type_: SensorType, value: SensorValue) -> SensorData {
+	SensorData {
+		id,
+		type_,
+		geolocation: Geolocation { lat: 10, lon: 20 },
+		value,
+		timestamp: 1,
+		derived: false,
+		quality: None,
+	}
+}
+
 #[test]
 fn it_aggregates_the_price() {
 	sp_io::TestExternalities::default().execute_with(|| {
@@ -259,3 +330,4740 @@ fn parse_price_works() {
 		assert_eq!(expected, PriceOracleOcwExample::parse_price(json));
 	}
 }
+
+#[test]
+fn all_readings_of_type_only_returns_requested_type() {
+	new_test_ext().execute_with(|| {
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Temperature,
+			SensorValue::Number(21),
+		));
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Humidity,
+			SensorValue::Number(40),
+		));
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			2,
+			SensorType::Temperature,
+			SensorValue::Number(18),
+		));
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			3,
+			SensorType::Digital,
+			SensorValue::Bool(true),
+		));
+
+		let mut temperatures = PriceOracleOcwExample::all_readings_of_type(SensorType::Temperature);
+		temperatures.sort_by_key(|(id, _)| *id);
+
+		assert_eq!(
+			temperatures,
+			vec![
+				(1, sensor(1, SensorType::Temperature, SensorValue::Number(21))),
+				(2, sensor(2, SensorType::Temperature, SensorValue::Number(18))),
+			]
+		);
+		assert!(PriceOracleOcwExample::all_readings_of_type(SensorType::Pressure).is_empty());
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_fails_without_a_local_key() {
+	let (offchain, offchain_state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(KeystoreExt::new(MemoryKeystore::new()));
+
+	t.execute_with(|| {
+		assert!(matches!(
+			PriceOracleOcwExample::get_sensors_data(),
+			Err(Error::<Test>::NoLocalKey)
+		));
+		// The missing key is detected before any HTTP request is made.
+		assert!(offchain_state.read().requests.is_empty());
+	});
+}
+
+#[test]
+fn ewma_weights_readings_by_recency() {
+	new_test_ext().execute_with(|| {
+		let alpha = sp_runtime::Permill::from_percent(50);
+		assert_eq!(PriceOracleOcwExample::ewma(1, SensorType::Temperature, alpha), None);
+
+		let mut reading = sensor(1, SensorType::Temperature, SensorValue::Number(10));
+		PriceOracleOcwExample::add_sensor_data(reading.clone());
+		assert_eq!(
+			PriceOracleOcwExample::ewma(1, SensorType::Temperature, alpha),
+			Some(sp_runtime::FixedU128::from_u32(10))
+		);
+
+		reading.value = SensorValue::Number(20);
+		reading.timestamp = 2;
+		PriceOracleOcwExample::add_sensor_data(reading.clone());
+		reading.value = SensorValue::Number(30);
+		reading.timestamp = 4;
+		PriceOracleOcwExample::add_sensor_data(reading);
+
+		// 10 -> 10 * 0.5 + 20 * 0.5 = 15 -> (two units later) 15 * 0.25 + 30 * 0.75 = 26.25
+		let ewma = PriceOracleOcwExample::ewma(1, SensorType::Temperature, alpha).unwrap();
+		assert!((ewma.to_float() - 26.25).abs() < 1e-9);
+	});
+}
+
+#[test]
+fn interpolate_is_linear_between_the_bracketing_readings() {
+	new_test_ext().execute_with(|| {
+		let add = |timestamp, value| {
+			PriceOracleOcwExample::add_sensor_data(SensorData {
+				timestamp,
+				..sensor(1, SensorType::Temperature, SensorValue::Number(value))
+			})
+		};
+		let at =
+			|timestamp| PriceOracleOcwExample::interpolate(1, SensorType::Temperature, timestamp);
+		assert_eq!(at(10), None);
+
+		// A single reading only gives its own timestamp.
+		add(10, 100);
+		assert_eq!(at(10), Some(100));
+		assert_eq!(at(11), None);
+
+		add(20, 200);
+		assert_eq!(at(15), Some(150));
+		assert_eq!(at(13), Some(130));
+		assert_eq!(at(10), Some(100));
+		assert_eq!(at(20), Some(200));
+		assert_eq!(at(9), None);
+		assert_eq!(at(21), None);
+
+		// Going down, rounded towards the earlier reading.
+		add(23, 100);
+		assert_eq!(at(21), Some(167));
+		assert_eq!(at(22), Some(134));
+	});
+}
+
+#[test]
+fn interpolate_is_none_for_non_numeric_readings() {
+	new_test_ext().execute_with(|| {
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Digital,
+			SensorValue::Bool(true),
+		));
+
+		assert_eq!(PriceOracleOcwExample::interpolate(1, SensorType::Digital, 1), None);
+	});
+}
+
+#[test]
+fn ewma_is_none_for_non_numeric_history() {
+	new_test_ext().execute_with(|| {
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Digital,
+			SensorValue::Bool(true),
+		));
+
+		assert_eq!(
+			PriceOracleOcwExample::ewma(
+				1,
+				SensorType::Digital,
+				sp_runtime::Permill::from_percent(50)
+			),
+			None
+		);
+	});
+}
+
+#[test]
+fn sensor_history_drops_the_oldest_reading_when_full() {
+	new_test_ext().execute_with(|| {
+		let max = <<Test as Config>::MaxHistory as Get<u32>>::get();
+		let mut reading = sensor(1, SensorType::Pressure, SensorValue::Number(0));
+		for i in 0..=max {
+			reading.value = SensorValue::Number(i);
+			PriceOracleOcwExample::add_sensor_data(reading.clone());
+		}
+
+		let history = PriceOracleOcwExample::sensor_history(1, SensorType::Pressure);
+		assert_eq!(history.len() as u32, max);
+		assert_eq!(history[0].value, SensorValue::Number(1));
+		assert_eq!(history[history.len() - 1].value, SensorValue::Number(max));
+	});
+}
+
+#[test]
+fn set_confirmation_threshold_works() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::confirmation_threshold(), 1);
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(1)));
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(2)));
+
+		assert_ok!(PriceOracleOcwExample::set_confirmation_threshold(RuntimeOrigin::root(), 2));
+
+		assert_eq!(PriceOracleOcwExample::confirmation_threshold(), 2);
+		System::assert_last_event(Event::ThresholdChanged { threshold: 2 }.into());
+	});
+}
+
+#[test]
+fn removing_authorities_lowers_the_confirmation_threshold() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			(1..=4).map(account).collect()
+		));
+		assert_ok!(PriceOracleOcwExample::set_confirmation_threshold(RuntimeOrigin::root(), 3));
+
+		// Still as many authorities as the threshold.
+		assert_ok!(PriceOracleOcwExample::remove_authority(RuntimeOrigin::root(), account(4)));
+		assert_eq!(PriceOracleOcwExample::confirmation_threshold(), 3);
+
+		assert_ok!(PriceOracleOcwExample::remove_authority(RuntimeOrigin::root(), account(3)));
+		assert_eq!(PriceOracleOcwExample::confirmation_threshold(), 2);
+		System::assert_last_event(Event::ThresholdChanged { threshold: 2 }.into());
+
+		// Never below 1, even without authorities.
+		assert_ok!(PriceOracleOcwExample::remove_authorities(
+			RuntimeOrigin::root(),
+			vec![account(1), account(2)]
+		));
+		assert_eq!(PriceOracleOcwExample::confirmation_threshold(), 1);
+		System::assert_last_event(Event::ThresholdChanged { threshold: 1 }.into());
+	});
+}
+
+#[test]
+fn set_confirmation_threshold_rejects_zero() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(1)));
+
+		assert_noop!(
+			PriceOracleOcwExample::set_confirmation_threshold(RuntimeOrigin::root(), 0),
+			Error::<Test>::InvalidConfirmationThreshold
+		);
+	});
+}
+
+#[test]
+fn set_confirmation_threshold_rejects_more_than_the_authority_count() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(1)));
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(2)));
+
+		assert_noop!(
+			PriceOracleOcwExample::set_confirmation_threshold(RuntimeOrigin::root(), 3),
+			Error::<Test>::InvalidConfirmationThreshold
+		);
+		assert_noop!(
+			PriceOracleOcwExample::set_confirmation_threshold(RuntimeOrigin::signed(account(1)), 1),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn heat_index_is_derived_from_temperature_and_humidity() {
+	let readings = vec![
+		sensor(1, SensorType::Temperature, SensorValue::Number(32)),
+		sensor(1, SensorType::Humidity, SensorValue::Number(70)),
+		sensor(2, SensorType::Temperature, SensorValue::Number(20)),
+		sensor(2, SensorType::Humidity, SensorValue::Number(50)),
+	];
+
+	let derived = PriceOracleOcwExample::derive_heat_indices(&readings);
+
+	assert_eq!(
+		derived,
+		vec![
+			SensorData {
+				derived: true,
+				..sensor(1, SensorType::HeatIndex, SensorValue::Number(40))
+			},
+			SensorData {
+				derived: true,
+				..sensor(2, SensorType::HeatIndex, SensorValue::Number(19))
+			},
+		]
+	);
+}
+
+#[test]
+fn heat_index_skips_sensors_missing_an_input() {
+	let readings = vec![
+		sensor(1, SensorType::Temperature, SensorValue::Number(32)),
+		sensor(2, SensorType::Humidity, SensorValue::Number(70)),
+		sensor(3, SensorType::Pressure, SensorValue::Number(1013)),
+	];
+
+	assert!(PriceOracleOcwExample::derive_heat_indices(&readings).is_empty());
+}
+
+fn custom_type(name: &str) -> SensorType {
+	SensorType::Custom(name.as_bytes().to_vec().try_into().unwrap())
+}
+
+#[test]
+fn custom_sensor_type_round_trips() {
+	let json =
+		br#"{"id":7,"type_":"CO2","geolocation":{"lat":1,"lon":2},"value":"415","timestamp":3}"#;
+
+	let reading: SensorData = serde_json::from_slice(json).unwrap();
+
+	assert_eq!(reading.type_, custom_type("CO2"));
+	assert_eq!(SensorData::decode(&mut &reading.encode()[..]).unwrap(), reading);
+	// Known names keep their dedicated variant.
+	let known =
+		br#"{"id":7,"type_":"Pressure","geolocation":{"lat":1,"lon":2},"value":"1","timestamp":3}"#;
+	assert_eq!(serde_json::from_slice::<SensorData>(known).unwrap().type_, SensorType::Pressure);
+}
+
+#[test]
+fn custom_sensor_type_name_is_bounded() {
+	let json = br#"{"id":7,"type_":"AVeryLongSensorTypeName","geolocation":{"lat":1,"lon":2},"value":"1","timestamp":3}"#;
+
+	assert!(serde_json::from_slice::<SensorData>(json).is_err());
+}
+
+#[test]
+fn custom_sensor_type_is_a_storage_key() {
+	new_test_ext().execute_with(|| {
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			custom_type("CO2"),
+			SensorValue::Number(415),
+		));
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			custom_type("PM25"),
+			SensorValue::Number(12),
+		));
+
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, custom_type("CO2")).map(|s| s.value),
+			Some(SensorValue::Number(415))
+		);
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, custom_type("PM25")).map(|s| s.value),
+			Some(SensorValue::Number(12))
+		);
+		System::assert_last_event(
+			Event::SensorDataAdded { id: 1, type_: custom_type("PM25"), seq: 2 }.into(),
+		);
+	});
+}
+
+#[test]
+fn sensors_iter_prefix_is_scoped_to_one_sensor_id() {
+	new_test_ext().execute_with(|| {
+		for (id, offset) in [(1, 0), (2, 100)] {
+			PriceOracleOcwExample::add_sensor_data(sensor(
+				id,
+				SensorType::Temperature,
+				SensorValue::Number(20 + offset),
+			));
+			PriceOracleOcwExample::add_sensor_data(sensor(
+				id,
+				SensorType::Humidity,
+				SensorValue::Number(40 + offset),
+			));
+			PriceOracleOcwExample::add_sensor_data(sensor(
+				id,
+				SensorType::Digital,
+				SensorValue::Bool(id == 1),
+			));
+		}
+
+		let mut readings: Vec<_> = Sensors::<Test>::iter_prefix(1)
+			.map(|(type_, sensor)| (type_, sensor.id, sensor.value))
+			.collect();
+		readings.sort_by_key(|(type_, ..)| type_.encode());
+
+		assert_eq!(
+			readings,
+			vec![
+				(SensorType::Humidity, 1, SensorValue::Number(40)),
+				(SensorType::Temperature, 1, SensorValue::Number(20)),
+				(SensorType::Digital, 1, SensorValue::Bool(true)),
+			]
+		);
+	});
+}
+
+fn set_unit(id: u32, unit: Unit) {
+	assert_ok!(PriceOracleOcwExample::set_sensor_metadata(
+		RuntimeOrigin::root(),
+		id,
+		SensorInfo { unit: Some(unit), ..Default::default() }
+	));
+}
+
+#[test]
+fn fahrenheit_readings_are_stored_in_celsius() {
+	new_test_ext().execute_with(|| {
+		set_unit(1, Unit::Fahrenheit);
+
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Temperature,
+			SensorValue::Number(212),
+		));
+
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Temperature).map(|s| s.value),
+			Some(SensorValue::Number(100))
+		);
+		System::assert_has_event(
+			Event::SensorDataConverted {
+				id: 1,
+				type_: SensorType::Temperature,
+				raw_unit: Unit::Fahrenheit,
+				raw_value: SensorValue::Number(212),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn fahrenheit_readings_below_freezing_are_stored_as_negative_celsius() {
+	new_test_ext().execute_with(|| {
+		set_unit(1, Unit::Fahrenheit);
+		assert_ok!(PriceOracleOcwExample::set_event_verbosity(
+			RuntimeOrigin::root(),
+			EventVerbosity::Silent
+		));
+
+		for (fahrenheit, celsius) in [
+			(14, SensorValue::Fixed(-10_000)),
+			(0, SensorValue::Fixed(-17_778)),
+			(31, SensorValue::Fixed(-556)),
+			(32, SensorValue::Number(0)),
+		] {
+			PriceOracleOcwExample::add_sensor_data(sensor(
+				1,
+				SensorType::Temperature,
+				SensorValue::Number(fahrenheit),
+			));
+			assert_eq!(
+				PriceOracleOcwExample::sensors(1, SensorType::Temperature).map(|s| s.value),
+				Some(celsius)
+			);
+		}
+		// Kept whatever the verbosity, as the only record of the reading as reported.
+		System::assert_has_event(
+			Event::SensorDataConverted {
+				id: 1,
+				type_: SensorType::Temperature,
+				raw_unit: Unit::Fahrenheit,
+				raw_value: SensorValue::Number(14),
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn hectopascal_readings_are_stored_in_pascal() {
+	new_test_ext().execute_with(|| {
+		set_unit(1, Unit::Hectopascal);
+
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Pressure,
+			SensorValue::Number(1013),
+		));
+
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Pressure).map(|s| s.value),
+			Some(SensorValue::Number(101_300))
+		);
+	});
+}
+
+#[test]
+fn fixed_point_readings_are_converted_to_the_canonical_unit() {
+	new_test_ext().execute_with(|| {
+		set_unit(1, Unit::Fahrenheit);
+		set_unit(2, Unit::Hectopascal);
+
+		for (raw, celsius) in [(212_000, 100_000), (70_500, 21_389), (-40_000, -40_000)] {
+			PriceOracleOcwExample::add_sensor_data(sensor(
+				1,
+				SensorType::Temperature,
+				SensorValue::Fixed(raw),
+			));
+			assert_eq!(
+				PriceOracleOcwExample::sensors(1, SensorType::Temperature).map(|s| s.value),
+				Some(SensorValue::Fixed(celsius))
+			);
+		}
+		System::assert_has_event(
+			Event::SensorDataConverted {
+				id: 1,
+				type_: SensorType::Temperature,
+				raw_unit: Unit::Fahrenheit,
+				raw_value: SensorValue::Fixed(70_500),
+			}
+			.into(),
+		);
+
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			2,
+			SensorType::Pressure,
+			SensorValue::Fixed(1_013_250),
+		));
+		assert_eq!(
+			PriceOracleOcwExample::sensors(2, SensorType::Pressure).map(|s| s.value),
+			Some(SensorValue::Fixed(101_325_000))
+		);
+	});
+}
+
+#[test]
+fn unit_conversion_skips_other_types_and_canonical_units() {
+	new_test_ext().execute_with(|| {
+		set_unit(1, Unit::Fahrenheit);
+		set_unit(2, Unit::Celsius);
+
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Humidity,
+			SensorValue::Number(50),
+		));
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Digital,
+			SensorValue::Bool(true),
+		));
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			2,
+			SensorType::Temperature,
+			SensorValue::Number(21),
+		));
+
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Humidity).map(|s| s.value),
+			Some(SensorValue::Number(50))
+		);
+		assert_eq!(
+			PriceOracleOcwExample::sensors(2, SensorType::Temperature).map(|s| s.value),
+			Some(SensorValue::Number(21))
+		);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PriceOracleOcwExample(Event::SensorDataConverted { .. })
+		)));
+	});
+}
+
+#[test]
+fn update_sensors_data_accepts_a_matching_checksum() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let batch = vec![
+			sensor(1, SensorType::Temperature, SensorValue::Number(21)),
+			sensor(2, SensorType::Digital, SensorValue::Bool(false)),
+		];
+		let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+
+		assert_ok!(PriceOracleOcwExample::update_sensors_data(
+			RuntimeOrigin::signed(test_pub()),
+			batch,
+			checksum
+		));
+
+		assert!(PriceOracleOcwExample::sensors(1, SensorType::Temperature).is_some());
+		assert!(PriceOracleOcwExample::sensors(2, SensorType::Digital).is_some());
+	});
+}
+
+#[test]
+fn update_sensors_data_rejects_an_altered_batch() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let mut batch = vec![sensor(1, SensorType::Temperature, SensorValue::Number(21))];
+		let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+		batch[0].value = SensorValue::Number(99);
+
+		assert_noop!(
+			PriceOracleOcwExample::update_sensors_data(
+				RuntimeOrigin::signed(test_pub()),
+				batch,
+				checksum
+			),
+			Error::<Test>::ChecksumMismatch
+		);
+	});
+}
+
+fn add_pressure_history(count: u32) {
+	for i in 0..count {
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Pressure,
+			SensorValue::Number(i),
+		));
+	}
+}
+
+#[test]
+fn sensor_history_paged_walks_the_whole_history() {
+	new_test_ext().execute_with(|| {
+		add_pressure_history(5);
+
+		let mut values = Vec::new();
+		let mut cursors = Vec::new();
+		let mut start = 0;
+		loop {
+			let page =
+				PriceOracleOcwExample::sensor_history_paged(1, SensorType::Pressure, start, 2);
+			values.extend(page.readings.iter().map(|reading| reading.value));
+			cursors.push(page.next);
+			match page.next {
+				Some(next) => start = next,
+				None => break,
+			}
+		}
+
+		assert_eq!(values, (0..5).map(SensorValue::Number).collect::<Vec<_>>());
+		assert_eq!(cursors, vec![Some(2), Some(4), None]);
+	});
+}
+
+#[test]
+fn sensor_history_paged_clamps_the_limit() {
+	new_test_ext().execute_with(|| {
+		add_pressure_history(10);
+
+		let page = PriceOracleOcwExample::sensor_history_paged(1, SensorType::Pressure, 0, 100);
+
+		assert_eq!(
+			page.readings.len() as u32,
+			<<Test as Config>::MaxHistoryPage as Get<u32>>::get()
+		);
+		assert_eq!(page.next, Some(8));
+		// A cursor past the end yields an empty last page.
+		let page = PriceOracleOcwExample::sensor_history_paged(1, SensorType::Pressure, 50, 2);
+		assert!(page.readings.is_empty());
+		assert_eq!(page.next, None);
+	});
+}
+
+#[test]
+fn run_self_test_passes_and_cleans_up() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::run_self_test(RuntimeOrigin::root()));
+
+		// Only the outcome is reported: the synthetic reading isn't.
+		assert_eq!(
+			System::events().into_iter().map(|record| record.event).collect::<Vec<_>>(),
+			vec![RuntimeEvent::PriceOracleOcwExample(Event::SelfTestPassed)]
+		);
+		assert!(UPDATES.with(|updates| updates.borrow().is_empty()));
+		assert_eq!(EventSeq::<Test>::get(), 0);
+		assert_eq!(PriceOracleOcwExample::sensors(SELF_TEST_SENSOR_ID, SensorType::Digital), None);
+		assert!(PriceOracleOcwExample::sensor_history(SELF_TEST_SENSOR_ID, SensorType::Digital)
+			.is_empty());
+		assert_eq!(PriceOracleOcwExample::latest_by_type(SensorType::Digital), None);
+		assert_noop!(
+			PriceOracleOcwExample::run_self_test(RuntimeOrigin::signed(test_pub())),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[cfg(feature = "ocw")]
+/// `[{"id":7,"type_":"Pressure","geolocation":{"lat":1,"lon":2},"value":"1013","timestamp":3}]`
+/// compressed with gzip.
+const GZIPPED_SENSORS: [u8; 103] = [
+	0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x15, 0xcb, 0x41, 0x0a, 0x80, 0x20,
+	0x10, 0x46, 0xe1, 0xbb, 0xfc, 0x6b, 0x17, 0x99, 0x8b, 0x60, 0x4e, 0xd1, 0x3e, 0x22, 0xa4, 0x86,
+	0x10, 0x34, 0x45, 0xc7, 0x20, 0xa4, 0xbb, 0x67, 0xcb, 0x07, 0xdf, 0x5b, 0x1a, 0xdc, 0x01, 0x9a,
+	0x14, 0xe4, 0x49, 0xbc, 0x81, 0x30, 0x67, 0x2e, 0xa5, 0x66, 0x86, 0xc2, 0xc9, 0xd1, 0xc7, 0xdd,
+	0x8a, 0x8b, 0x17, 0xa8, 0xc1, 0x5b, 0x01, 0x69, 0x05, 0xff, 0xe7, 0xf8, 0x2a, 0xdc, 0xd6, 0x57,
+	0xee, 0x8b, 0x1e, 0xb4, 0xe9, 0x5c, 0x5c, 0xe0, 0x22, 0x36, 0x24, 0x90, 0x79, 0xd7, 0x0f, 0x31,
+	0x91, 0x62, 0xef, 0x5a, 0x00, 0x00, 0x00,
+];
+
+#[cfg(feature = "ocw")]
+fn sensors_response(state: &mut testing::OffchainState, body: Vec<u8>) {
+	state.expect_request(testing::PendingRequest {
+		method: "GET".into(),
+		uri: "https://sensors-api.vercel.app/api/v1/sensors".into(),
+		headers: vec![
+			("Accept".into(), "application/json".into()),
+			("Accept-Encoding".into(), "gzip".into()),
+		],
+		response: Some(body),
+		response_headers: vec![("Content-Encoding".into(), "gzip".into())],
+		sent: true,
+		..Default::default()
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_decodes_gzip_response() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	sensors_response(&mut state.write(), GZIPPED_SENSORS.to_vec());
+
+	t.execute_with(|| {
+		let data = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+		assert_eq!(
+			data,
+			vec![SensorData {
+				id: 7,
+				type_: SensorType::Pressure,
+				geolocation: Geolocation { lat: 1, lon: 2 },
+				value: SensorValue::Number(1013),
+				timestamp: 3,
+				derived: false,
+				quality: None,
+			}]
+		);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_rejects_corrupt_gzip_response() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	let mut corrupt = GZIPPED_SENSORS.to_vec();
+	corrupt[20..40].fill(0xff);
+	sensors_response(&mut state.write(), corrupt);
+
+	t.execute_with(|| {
+		assert!(matches!(
+			PriceOracleOcwExample::fetch_sensors_data(),
+			Err(Error::<Test>::DecompressFailed)
+		));
+	});
+}
+
+#[test]
+fn ensure_authority_adds_a_new_account() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::ensure_authority(&account(1)));
+
+		assert!(PriceOracleOcwExample::is_authority(&account(1)));
+		System::assert_last_event(Event::AuthorityAdded { authority: account(1) }.into());
+	});
+}
+
+#[test]
+fn ensure_authority_is_a_no_op_for_an_existing_account() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::ensure_authority(&account(1)));
+		let events = System::events().len();
+
+		assert_ok!(PriceOracleOcwExample::ensure_authority(&account(1)));
+
+		assert_eq!(PriceOracleOcwExample::authorities().to_vec(), vec![account(1)]);
+		assert_eq!(System::events().len(), events);
+	});
+}
+
+#[test]
+fn authority_added_is_not_emitted_for_an_existing_authority() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(1)));
+		System::reset_events();
+
+		assert_ok!(PriceOracleOcwExample::ensure_authority(&account(1)));
+
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PriceOracleOcwExample(Event::AuthorityAdded { .. })
+		)));
+	});
+}
+
+#[test]
+fn remove_authority_emits_authority_removed() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(1)));
+
+		assert_ok!(PriceOracleOcwExample::remove_authority(RuntimeOrigin::root(), account(1)));
+
+		System::assert_last_event(Event::AuthorityRemoved { authority: account(1) }.into());
+	});
+}
+
+#[test]
+fn ensure_authority_respects_max_authorities() {
+	new_test_ext().execute_with(|| {
+		let max = <<Test as Config>::MaxAuthorities as Get<u32>>::get() as u8;
+		for seed in 0..max {
+			assert_ok!(PriceOracleOcwExample::ensure_authority(&account(seed)));
+		}
+
+		assert_noop!(
+			PriceOracleOcwExample::ensure_authority(&account(max)),
+			Error::<Test>::TooManyAuthorities
+		);
+		// Already present, so still fine at capacity.
+		assert_ok!(PriceOracleOcwExample::ensure_authority(&account(0)));
+	});
+}
+
+#[test]
+fn add_authorities_adds_every_account() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			vec![account(1), account(2)]
+		));
+
+		assert_eq!(PriceOracleOcwExample::authorities().to_vec(), vec![account(1), account(2)]);
+		System::assert_has_event(Event::AuthorityAdded { authority: account(1) }.into());
+		System::assert_last_event(Event::AuthorityAdded { authority: account(2) }.into());
+	});
+}
+
+#[test]
+fn add_authorities_respects_max_authorities() {
+	new_test_ext().execute_with(|| {
+		let max = <<Test as Config>::MaxAuthorities as Get<u32>>::get() as u8;
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			(1..max).map(account).collect()
+		));
+
+		// The whole batch is rejected, not just the account over capacity.
+		assert_noop!(
+			PriceOracleOcwExample::add_authorities(
+				RuntimeOrigin::root(),
+				vec![account(max), account(max + 1)]
+			),
+			Error::<Test>::TooManyAuthorities
+		);
+	});
+}
+
+#[test]
+fn add_authority_respects_max_authorities() {
+	new_test_ext().execute_with(|| {
+		let max = <<Test as Config>::MaxAuthorities as Get<u32>>::get() as u8;
+		for seed in 0..max {
+			assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(seed)));
+		}
+
+		assert_noop!(
+			PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(max)),
+			Error::<Test>::TooManyAuthorities
+		);
+	});
+}
+
+#[test]
+fn remove_authorities_removes_a_subset() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			(1..=4).map(account).collect()
+		));
+
+		assert_ok!(PriceOracleOcwExample::remove_authorities(
+			RuntimeOrigin::root(),
+			vec![account(1), account(3)]
+		));
+		let mut left = PriceOracleOcwExample::authorities().into_inner();
+		left.sort();
+		assert_eq!(left, vec![account(2), account(4)]);
+		System::assert_has_event(Event::AuthorityRemoved { authority: account(1) }.into());
+		System::assert_last_event(Event::AuthorityRemoved { authority: account(3) }.into());
+	});
+}
+
+#[test]
+fn remove_authorities_skips_absent_accounts() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			vec![account(1), account(2)]
+		));
+		System::reset_events();
+
+		assert_ok!(PriceOracleOcwExample::remove_authorities(
+			RuntimeOrigin::root(),
+			vec![account(3), account(4)]
+		));
+		assert_eq!(PriceOracleOcwExample::authorities().into_inner(), vec![account(1), account(2)]);
+		assert_no_events();
+	});
+}
+
+#[test]
+fn remove_authorities_keeps_min_authorities() {
+	new_test_ext().execute_with(|| {
+		MinAuthorities::set(1);
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			vec![account(1), account(2)]
+		));
+
+		// Nothing is removed, not even the accounts that could go.
+		assert_noop!(
+			PriceOracleOcwExample::remove_authorities(
+				RuntimeOrigin::root(),
+				vec![account(1), account(2)]
+			),
+			Error::<Test>::BelowMinAuthorities
+		);
+		assert_ok!(PriceOracleOcwExample::remove_authorities(
+			RuntimeOrigin::root(),
+			vec![account(1)]
+		));
+		assert_eq!(PriceOracleOcwExample::authorities().into_inner(), vec![account(2)]);
+	});
+}
+
+#[test]
+fn remove_authority_keeps_min_authorities() {
+	new_test_ext().execute_with(|| {
+		MinAuthorities::set(2);
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			(1..=3).map(account).collect()
+		));
+
+		// Down to the minimum is fine, one past it isn't.
+		assert_ok!(PriceOracleOcwExample::remove_authority(RuntimeOrigin::root(), account(1)));
+		assert_noop!(
+			PriceOracleOcwExample::remove_authority(RuntimeOrigin::root(), account(2)),
+			Error::<Test>::BelowMinAuthorities
+		);
+		assert_eq!(PriceOracleOcwExample::authorities().len(), 2);
+	});
+}
+
+#[test]
+fn every_authority_can_be_removed_by_default() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authorities(
+			RuntimeOrigin::root(),
+			vec![account(1), account(2)]
+		));
+
+		assert_ok!(PriceOracleOcwExample::remove_authority(RuntimeOrigin::root(), account(1)));
+		assert_ok!(PriceOracleOcwExample::remove_authorities(
+			RuntimeOrigin::root(),
+			vec![account(2)]
+		));
+		assert!(PriceOracleOcwExample::authorities().is_empty());
+	});
+}
+
+#[test]
+fn genesis_sets_the_authorities() {
+	let mut t: sp_io::TestExternalities = RuntimeGenesisConfig {
+		price_oracle_ocw_example: crate::GenesisConfig {
+			authorities: vec![account(1), account(2)],
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap()
+	.into();
+
+	t.execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::authorities().to_vec(), vec![account(1), account(2)]);
+	});
+}
+
+#[test]
+#[should_panic(expected = "TooManyAuthorities")]
+fn genesis_respects_max_authorities() {
+	let max = <<Test as Config>::MaxAuthorities as Get<u32>>::get() as u8;
+	let _ = RuntimeGenesisConfig {
+		price_oracle_ocw_example: crate::GenesisConfig {
+			authorities: (0..=max).map(account).collect(),
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage();
+}
+
+#[test]
+fn genesis_registers_the_sensors() {
+	let geolocation = Geolocation { lat: 4581, lon: 1523 };
+	let mut t: sp_io::TestExternalities = RuntimeGenesisConfig {
+		price_oracle_ocw_example: crate::GenesisConfig {
+			sensors: vec![
+				(1, b"greenhouse".to_vec(), Some(Unit::Fahrenheit), geolocation),
+				(2, Vec::new(), None, geolocation),
+			],
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap()
+	.into();
+
+	t.execute_with(|| {
+		assert_eq!(
+			PriceOracleOcwExample::sensor_metadata(1),
+			Some(SensorInfo {
+				unit: Some(Unit::Fahrenheit),
+				name: b"greenhouse".to_vec().try_into().unwrap(),
+				geolocation: Some(geolocation),
+				private: false,
+			})
+		);
+		assert_eq!(PriceOracleOcwExample::sensor_metadata(2).unwrap().unit, None);
+		assert_eq!(PriceOracleOcwExample::sensor_metadata(3), None);
+
+		// Readings of the registered sensors are converted from their unit.
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Temperature,
+			SensorValue::Number(212),
+		));
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Temperature).unwrap().value,
+			SensorValue::Number(100)
+		);
+	});
+}
+
+#[test]
+#[should_panic(expected = "genesis sensor geolocations must be within range")]
+fn genesis_rejects_geolocations_out_of_range() {
+	let _ = RuntimeGenesisConfig {
+		price_oracle_ocw_example: crate::GenesisConfig {
+			sensors: vec![(1, Vec::new(), None, Geolocation { lat: -9001, lon: 0 })],
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage();
+}
+
+#[test]
+#[should_panic(expected = "genesis sensor names must fit in `SensorName`")]
+fn genesis_rejects_sensor_names_over_the_bound() {
+	let _ = RuntimeGenesisConfig {
+		price_oracle_ocw_example: crate::GenesisConfig {
+			sensors: vec![(1, vec![b'x'; 33], None, Geolocation { lat: 0, lon: 0 })],
+			..Default::default()
+		},
+		..Default::default()
+	}
+	.build_storage();
+}
+
+/// Submit a batch of two readings under `verbosity` and return the events it emitted.
+fn batch_events(verbosity: EventVerbosity) -> Vec<RuntimeEvent> {
+	assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+	assert_ok!(PriceOracleOcwExample::set_event_verbosity(RuntimeOrigin::root(), verbosity));
+	System::assert_last_event(Event::EventVerbosityChanged { verbosity }.into());
+	System::reset_events();
+
+	let batch = vec![
+		sensor(1, SensorType::Temperature, SensorValue::Number(21)),
+		sensor(2, SensorType::Digital, SensorValue::Bool(false)),
+	];
+	let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+	assert_ok!(PriceOracleOcwExample::update_sensors_data(
+		RuntimeOrigin::signed(test_pub()),
+		batch,
+		checksum
+	));
+
+	System::events().into_iter().map(|record| record.event).collect()
+}
+
+#[test]
+fn event_verbosity_defaults_to_full() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::event_verbosity(), EventVerbosity::Full);
+	});
+}
+
+#[test]
+fn full_verbosity_emits_reading_and_batch_events() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			batch_events(EventVerbosity::Full),
+			vec![
+				Event::SensorDataAdded { id: 1, type_: SensorType::Temperature, seq: 1 }.into(),
+				Event::SensorDataAdded { id: 2, type_: SensorType::Digital, seq: 2 }.into(),
+				Event::SensorsDataUpdated { count: 2, seq: 3 }.into(),
+			]
+		);
+	});
+}
+
+#[test]
+fn batch_only_verbosity_emits_only_the_batch_event() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			batch_events(EventVerbosity::BatchOnly),
+			vec![Event::SensorsDataUpdated { count: 2, seq: 1 }.into()]
+		);
+	});
+}
+
+#[test]
+fn silent_verbosity_emits_no_events() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(batch_events(EventVerbosity::Silent), vec![]);
+		assert!(PriceOracleOcwExample::sensors(1, SensorType::Temperature).is_some());
+	});
+}
+
+#[test]
+fn event_seq_numbers_every_data_event_without_gaps() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::current_event_seq(), 0);
+		assert_eq!(batch_events(EventVerbosity::Full).len(), 3);
+		assert_eq!(PriceOracleOcwExample::current_event_seq(), 3);
+
+		let submit = |verbosity, timestamp| {
+			assert_ok!(PriceOracleOcwExample::set_event_verbosity(
+				RuntimeOrigin::root(),
+				verbosity
+			));
+			System::reset_events();
+			let batch = vec![SensorData {
+				timestamp,
+				..sensor(1, SensorType::Temperature, SensorValue::Number(21))
+			}];
+			let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+			assert_ok!(PriceOracleOcwExample::update_sensors_data(
+				RuntimeOrigin::signed(test_pub()),
+				batch,
+				checksum
+			));
+			System::events().into_iter().map(|record| record.event).collect::<Vec<_>>()
+		};
+		// Events that aren't emitted aren't numbered.
+		assert_eq!(submit(EventVerbosity::Silent, 2), vec![]);
+		assert_eq!(PriceOracleOcwExample::current_event_seq(), 3);
+		assert_eq!(
+			submit(EventVerbosity::BatchOnly, 3),
+			vec![Event::SensorsDataUpdated { count: 1, seq: 4 }.into()]
+		);
+		assert_eq!(
+			submit(EventVerbosity::Full, 4),
+			vec![
+				Event::SensorDataAdded { id: 1, type_: SensorType::Temperature, seq: 5 }.into(),
+				Event::SensorsDataUpdated { count: 1, seq: 6 }.into(),
+			]
+		);
+		assert_eq!(PriceOracleOcwExample::current_event_seq(), 6);
+	});
+}
+
+#[test]
+fn set_event_verbosity_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PriceOracleOcwExample::set_event_verbosity(
+				RuntimeOrigin::signed(test_pub()),
+				EventVerbosity::Silent
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn sensor_data_quality_defaults_to_none() {
+	let json =
+		br#"{"id":7,"type_":"Pressure","geolocation":{"lat":1,"lon":2},"value":"1","timestamp":3}"#;
+
+	let data: SensorData = serde_json::from_slice(json).unwrap();
+
+	assert_eq!(data.quality, None);
+}
+
+#[test]
+fn sensor_data_quality_is_deserialized_and_stored() {
+	let json = br#"{"id":7,"type_":"Pressure","geolocation":{"lat":1,"lon":2},"value":"1","timestamp":3,"quality":87}"#;
+
+	let data: SensorData = serde_json::from_slice(json).unwrap();
+	assert_eq!(data.quality, Some(87));
+	assert_eq!(SensorData::decode(&mut &data.encode()[..]).unwrap(), data);
+
+	new_test_ext().execute_with(|| {
+		PriceOracleOcwExample::add_sensor_data(data);
+
+		assert_eq!(
+			PriceOracleOcwExample::sensors(7, SensorType::Pressure).unwrap().quality,
+			Some(87)
+		);
+		assert_eq!(
+			PriceOracleOcwExample::sensor_history(7, SensorType::Pressure)[0].quality,
+			Some(87)
+		);
+	});
+}
+
+/// Run a golden API response from `fixtures/` through `process_sensors_json` and compare the
+/// serialized batch with its `.expected.json` file. Update the expected file deliberately when the
+/// format changes.
+fn assert_matches_golden(response: &[u8], expected: &[u8]) {
+	let batch = PriceOracleOcwExample::process_sensors_json(response).unwrap();
+
+	let expected: serde_json::Value = serde_json::from_slice(expected).unwrap();
+	assert_eq!(serde_json::to_value(batch).unwrap(), expected);
+}
+
+#[test]
+fn golden_sensors_response() {
+	assert_matches_golden(
+		include_bytes!("../fixtures/sensors.json"),
+		include_bytes!("../fixtures/sensors.expected.json"),
+	);
+}
+
+#[test]
+fn golden_sensors_edge_cases_response() {
+	assert_matches_golden(
+		include_bytes!("../fixtures/sensors_edge_cases.json"),
+		include_bytes!("../fixtures/sensors_edge_cases.expected.json"),
+	);
+}
+
+#[test]
+fn golden_negative_temperature_response() {
+	let response = include_bytes!("../fixtures/sensors_negative_temperature.json");
+
+	// Readings are unsigned without `fixed-point`, so the batch is refused rather than misread.
+	#[cfg(not(feature = "fixed-point"))]
+	assert!(matches!(
+		PriceOracleOcwExample::process_sensors_json(response),
+		Err(Error::<Test>::DeserializeError)
+	));
+	#[cfg(feature = "fixed-point")]
+	assert_matches_golden(
+		response,
+		include_bytes!("../fixtures/sensors_negative_temperature.expected.json"),
+	);
+}
+
+#[test]
+fn sensor_type_serializes_as_its_name() {
+	let number = SensorValue::Number(21);
+	for (type_, name, value) in [
+		(SensorType::Humidity, "Humidity", SensorValue::Percent(Percent::from_percent(60))),
+		(SensorType::Temperature, "Temperature", number),
+		(SensorType::Pressure, "Pressure", number),
+		(SensorType::Digital, "Digital", SensorValue::Bool(true)),
+		(SensorType::HeatIndex, "HeatIndex", number),
+		(custom_type("CO2"), "CO2", number),
+	] {
+		let json = serde_json::to_string(&type_).unwrap();
+		assert_eq!(json, format!("{name:?}"));
+		assert_eq!(parse_type(name).unwrap(), type_);
+		assert_eq!(serde_json::from_str::<SensorType>(&json).unwrap(), type_);
+
+		let reading = sensor(1, type_, value);
+		let json = serde_json::to_string(&reading).unwrap();
+		assert!(json.contains(&format!(r#""type_":{name:?}"#)));
+		assert_eq!(serde_json::from_str::<SensorData>(&json).unwrap(), reading);
+	}
+}
+
+#[test]
+fn export_snapshot_round_trips_through_ingestion() {
+	new_test_ext().execute_with(|| {
+		let readings = vec![
+			sensor(1, SensorType::Temperature, SensorValue::Number(21)),
+			sensor(1, SensorType::Humidity, SensorValue::Percent(Percent::from_percent(60))),
+			SensorData {
+				quality: Some(90),
+				..sensor(2, SensorType::Digital, SensorValue::Bool(true))
+			},
+			sensor(3, custom_type("CO2"), SensorValue::Number(415)),
+		];
+		for reading in readings.clone() {
+			PriceOracleOcwExample::add_sensor_data(reading);
+		}
+
+		let snapshot = PriceOracleOcwExample::export_snapshot();
+
+		let exported: Vec<SensorData> = serde_json::from_slice(&snapshot).unwrap();
+		assert_eq!(exported.len(), readings.len());
+		assert!(readings.iter().all(|reading| exported.contains(reading)));
+	});
+}
+
+#[test]
+fn update_sensors_data_stores_valid_readings_and_rejects_the_rest() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		PriceOracleOcwExample::add_sensor_data(SensorData {
+			timestamp: 10,
+			..sensor(1, SensorType::Temperature, SensorValue::Number(20))
+		});
+		System::reset_events();
+
+		let batch = vec![
+			SensorData {
+				timestamp: 5,
+				..sensor(1, SensorType::Temperature, SensorValue::Number(25))
+			},
+			sensor(2, SensorType::Humidity, SensorValue::Number(60)),
+			sensor(SELF_TEST_SENSOR_ID, SensorType::Digital, SensorValue::Bool(true)),
+			SensorData { timestamp: 10, ..sensor(1, SensorType::Pressure, SensorValue::Number(1)) },
+		];
+		let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+		assert_ok!(PriceOracleOcwExample::update_sensors_data(
+			RuntimeOrigin::signed(test_pub()),
+			batch,
+			checksum
+		));
+
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Temperature).unwrap().value,
+			SensorValue::Number(20)
+		);
+		assert!(PriceOracleOcwExample::sensors(2, SensorType::Humidity).is_some());
+		assert!(PriceOracleOcwExample::sensors(1, SensorType::Pressure).is_some());
+		assert_eq!(PriceOracleOcwExample::sensors(SELF_TEST_SENSOR_ID, SensorType::Digital), None);
+		assert_eq!(
+			System::events().into_iter().map(|record| record.event).collect::<Vec<_>>(),
+			vec![
+				Event::ReadingRejected {
+					id: 1,
+					type_: SensorType::Temperature,
+					reason: RejectReason::StaleTimestamp
+				}
+				.into(),
+				Event::SensorDataAdded { id: 2, type_: SensorType::Humidity, seq: 2 }.into(),
+				Event::ReadingRejected {
+					id: SELF_TEST_SENSOR_ID,
+					type_: SensorType::Digital,
+					reason: RejectReason::OutOfScope
+				}
+				.into(),
+				Event::SensorDataAdded { id: 1, type_: SensorType::Pressure, seq: 3 }.into(),
+				Event::SensorsDataUpdated { count: 2, seq: 4 }.into(),
+			]
+		);
+	});
+}
+
+#[test]
+fn add_sensor_data_notifies_the_update_handler_with_the_stored_reading() {
+	new_test_ext().execute_with(|| {
+		set_unit(1, Unit::Hectopascal);
+		UPDATES.with(|updates| updates.borrow_mut().clear());
+
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Pressure,
+			SensorValue::Number(1013),
+		));
+
+		let stored = PriceOracleOcwExample::sensors(1, SensorType::Pressure).unwrap();
+		assert_eq!(stored.value, SensorValue::Number(101_300));
+		assert_eq!(UPDATES.with(|updates| updates.borrow().clone()), vec![stored]);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_applies_the_configured_request() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	state.write().expect_request(testing::PendingRequest {
+		method: "POST".into(),
+		uri: "https://sensors-api.vercel.app/api/v1/sensors".into(),
+		headers: vec![
+			("Accept".into(), "application/json".into()),
+			("Accept-Encoding".into(), "gzip".into()),
+			("Authorization".into(), "Bearer secret".into()),
+		],
+		body: br#"{"region":"eu"}"#.to_vec(),
+		response: Some(b"[]".to_vec()),
+		sent: true,
+		..Default::default()
+	});
+
+	t.execute_with(|| {
+		sp_runtime::offchain::storage::StorageValueRef::persistent(REQUEST_CONFIG_KEY).set(
+			&RequestConfig {
+				method: HttpMethod::Post,
+				headers: vec![(b"Authorization".to_vec(), b"Bearer secret".to_vec())],
+				body: Some(br#"{"region":"eu"}"#.to_vec()),
+			},
+		);
+
+		assert_eq!(PriceOracleOcwExample::fetch_sensors_data().unwrap(), vec![]);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_gzips_the_post_body_when_enabled() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	let json = br#"{"region":"eu","sensors":[1,2,3,4,5,6,7,8,9,10]}"#;
+	let gzipped = crate::gzip::compress(json);
+	assert_eq!(crate::gzip::decompress(&gzipped).as_deref(), Some(&json[..]));
+	state.write().expect_request(testing::PendingRequest {
+		method: "POST".into(),
+		uri: "https://sensors-api.vercel.app/api/v1/sensors".into(),
+		headers: vec![
+			("Accept".into(), "application/json".into()),
+			("Accept-Encoding".into(), "gzip".into()),
+			("Content-Encoding".into(), "gzip".into()),
+		],
+		body: gzipped,
+		response: Some(b"[]".to_vec()),
+		sent: true,
+		..Default::default()
+	});
+
+	t.execute_with(|| {
+		sp_runtime::offchain::storage::StorageValueRef::persistent(REQUEST_CONFIG_KEY).set(
+			&RequestConfig { method: HttpMethod::Post, headers: vec![], body: Some(json.to_vec()) },
+		);
+		sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, COMPRESS_REQUESTS_KEY, &[]);
+
+		assert_eq!(PriceOracleOcwExample::fetch_sensors_data().unwrap(), vec![]);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn gzip_compress_writes_a_standard_trailer() {
+	let gzipped = crate::gzip::compress(b"123456789");
+
+	assert_eq!(gzipped[..3], [0x1f, 0x8b, 8]);
+	// The CRC-32 check value of "123456789", then the length.
+	assert_eq!(gzipped[gzipped.len() - 8..], [0x26, 0x39, 0xf4, 0xcb, 9, 0, 0, 0]);
+	assert_eq!(crate::gzip::decompress(&gzipped).as_deref(), Some(&b"123456789"[..]));
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn gzip_decompress_checks_the_trailer() {
+	let gzipped = crate::gzip::compress(b"123456789");
+	let len = gzipped.len();
+
+	// A flipped bit in the CRC-32, then in the length.
+	for at in [len - 8, len - 4] {
+		let mut corrupt = gzipped.clone();
+		corrupt[at] ^= 1;
+		assert_eq!(crate::gzip::decompress(&corrupt), None, "byte {at} unchecked");
+	}
+	// Missing trailer bytes.
+	assert_eq!(crate::gzip::decompress(&gzipped[..len - 1]), None);
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_goes_through_the_configured_http_proxy() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	for uri in [
+		"http://gateway.corp:8080/https://sensors-api.vercel.app/api/v1/sensors".to_string(),
+		SENSORS_API_URL.to_string(),
+	] {
+		state.write().expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri,
+			headers: vec![
+				("Accept".into(), "application/json".into()),
+				("Accept-Encoding".into(), "gzip".into()),
+			],
+			response: Some(b"[]".to_vec()),
+			sent: true,
+			..Default::default()
+		});
+	}
+
+	t.execute_with(|| {
+		let set = |proxy: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, HTTP_PROXY_KEY, proxy)
+		};
+		set(b"http://gateway.corp:8080/");
+		assert_eq!(PriceOracleOcwExample::fetch_sensors_data().unwrap(), vec![]);
+
+		// Not a URL, so bypassed.
+		set(b"gateway.corp:8080");
+		assert_eq!(PriceOracleOcwExample::http_proxy(), None);
+		assert_eq!(PriceOracleOcwExample::fetch_sensors_data().unwrap(), vec![]);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_uses_the_default_api_endpoint() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	SensorsApiEndpoint::set("http://localhost:3000/sensors");
+	for uri in ["http://localhost:3000/sensors", "http://localhost:3000/sensors/7"] {
+		state.write().expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri: uri.into(),
+			headers: vec![
+				("Accept".into(), "application/json".into()),
+				("Accept-Encoding".into(), "gzip".into()),
+			],
+			response: Some(b"[]".to_vec()),
+			sent: true,
+			..Default::default()
+		});
+	}
+
+	t.execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::fetch_sensors_data().unwrap(), vec![]);
+
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			SENSOR_IDS_KEY,
+			&vec![7u32].encode(),
+		);
+		assert_eq!(PriceOracleOcwExample::fetch_sensors_data().unwrap(), vec![]);
+	});
+}
+
+#[test]
+fn request_config_defaults_to_a_plain_get() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain));
+
+	t.execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::request_config(), RequestConfig::default());
+		assert_eq!(RequestConfig::default().method, HttpMethod::Get);
+	});
+}
+
+/// `SensorValue` as it would look with more variants appended after the original two.
+#[derive(Debug, PartialEq, Encode, Decode)]
+enum AppendedSensorValue {
+	#[codec(index = 0)]
+	Number(u32),
+	#[codec(index = 1)]
+	Bool(bool),
+	#[codec(index = 2)]
+	Signed(i32),
+	#[codec(index = 3)]
+	Fixed(u64),
+}
+
+#[test]
+fn sensor_value_encoding_survives_appended_variants() {
+	// Encodings produced by the original two-variant layout.
+	let number = [0u8, 0x2a, 0, 0, 0];
+	let boolean = [1u8, 1];
+
+	assert_eq!(SensorValue::Number(42).encode(), number);
+	assert_eq!(SensorValue::Bool(true).encode(), boolean);
+	assert_eq!(SensorValue::decode(&mut &number[..]).unwrap(), SensorValue::Number(42));
+	assert_eq!(SensorValue::decode(&mut &boolean[..]).unwrap(), SensorValue::Bool(true));
+	assert_eq!(
+		AppendedSensorValue::decode(&mut &number[..]).unwrap(),
+		AppendedSensorValue::Number(42)
+	);
+	assert_eq!(
+		AppendedSensorValue::decode(&mut &boolean[..]).unwrap(),
+		AppendedSensorValue::Bool(true)
+	);
+}
+
+#[test]
+fn unit_encoding_is_pinned() {
+	for (unit, code) in
+		[(Unit::Celsius, 0u8), (Unit::Fahrenheit, 1), (Unit::Pascal, 2), (Unit::Hectopascal, 3)]
+	{
+		assert_eq!(unit.encode(), [code]);
+		assert_eq!(Unit::decode(&mut &[code][..]).unwrap(), unit);
+	}
+}
+
+fn submit_batch(batch: Vec<SensorData>) {
+	let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+	assert_ok!(PriceOracleOcwExample::update_sensors_data(
+		RuntimeOrigin::signed(test_pub()),
+		batch,
+		checksum
+	));
+}
+
+#[test]
+fn rejection_counts_increment_per_reason_and_reset() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		PriceOracleOcwExample::add_sensor_data(SensorData {
+			timestamp: 10,
+			..sensor(1, SensorType::Temperature, SensorValue::Number(20))
+		});
+		assert_eq!(PriceOracleOcwExample::rejection_counts(), vec![]);
+
+		let stale = SensorData {
+			timestamp: 5,
+			..sensor(1, SensorType::Temperature, SensorValue::Number(25))
+		};
+		let reserved = sensor(SELF_TEST_SENSOR_ID, SensorType::Digital, SensorValue::Bool(true));
+		let also_stale = SensorData { value: SensorValue::Number(26), ..stale.clone() };
+		submit_batch(vec![stale.clone(), reserved, also_stale]);
+		submit_batch(vec![stale]);
+
+		let mut counts = PriceOracleOcwExample::rejection_counts();
+		counts.sort_by_key(|(_, count)| *count);
+		assert_eq!(counts, vec![(RejectReason::OutOfScope, 1), (RejectReason::StaleTimestamp, 3)]);
+
+		assert_ok!(PriceOracleOcwExample::reset_rejection_counts(RuntimeOrigin::root()));
+		System::assert_last_event(Event::RejectionCountsReset.into());
+		assert_eq!(PriceOracleOcwExample::rejection_counts(), vec![]);
+	});
+}
+
+#[test]
+fn reset_rejection_counts_is_weighed_per_reason() {
+	use frame_support::dispatch::GetDispatchInfo;
+
+	let db = <Test as frame_system::Config>::DbWeight::get();
+	let call = crate::Call::<Test>::reset_rejection_counts {};
+
+	assert_eq!(
+		RejectReason::ALL.iter().map(|reason| reason.code()).collect::<Vec<_>>(),
+		[0, 1, 2, 3, 4, 5]
+	);
+	assert_eq!(call.get_dispatch_info().weight, db.writes(6));
+}
+
+#[test]
+fn authority_calls_are_weighed_by_their_reads_and_writes() {
+	use frame_support::dispatch::GetDispatchInfo;
+
+	let db = <Test as frame_system::Config>::DbWeight::get();
+	let weight = |call: crate::Call<Test>| call.get_dispatch_info().weight;
+
+	assert_eq!(weight(crate::Call::add_authority { authority: account(1) }), db.reads_writes(1, 2));
+	assert_eq!(
+		weight(crate::Call::remove_authority { authority: account(1) }),
+		db.reads_writes(3, 7)
+	);
+	assert_eq!(
+		weight(crate::Call::set_confirmation_threshold { threshold: 1 }),
+		db.reads_writes(1, 1)
+	);
+	assert_eq!(
+		weight(crate::Call::add_authorities { authorities: vec![account(1), account(2)] }),
+		db.reads_writes(1, 3)
+	);
+	assert_eq!(
+		weight(crate::Call::remove_authorities { authorities: vec![account(1), account(2)] }),
+		db.reads_writes(4, 12)
+	);
+}
+
+#[test]
+fn reset_rejection_counts_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PriceOracleOcwExample::reset_rejection_counts(RuntimeOrigin::signed(test_pub())),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn public_latest_reading_omits_private_sensors() {
+	new_test_ext().execute_with(|| {
+		let motion = sensor(1, SensorType::Digital, SensorValue::Bool(true));
+		let door = sensor(2, SensorType::Digital, SensorValue::Bool(false));
+		PriceOracleOcwExample::add_sensor_data(motion.clone());
+		PriceOracleOcwExample::add_sensor_data(door.clone());
+		assert_ok!(PriceOracleOcwExample::set_sensor_metadata(
+			RuntimeOrigin::root(),
+			1,
+			SensorInfo { private: true, ..Default::default() }
+		));
+
+		assert_eq!(PriceOracleOcwExample::public_latest_reading(1, SensorType::Digital), None);
+		assert_eq!(
+			PriceOracleOcwExample::public_latest_reading(2, SensorType::Digital),
+			Some(door)
+		);
+		assert_eq!(
+			PriceOracleOcwExample::latest_reading(1, SensorType::Digital, true),
+			Some(motion.clone())
+		);
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Digital), Some(motion));
+	});
+}
+
+fn parse_value(s: &str) -> Result<SensorValue, serde_json::Error> {
+	let json = serde_json::to_string(s).unwrap();
+	de_string_to_sensor_value(&mut serde_json::Deserializer::from_str(&json))
+}
+
+#[test]
+fn sensor_values_are_read_from_strings_and_native_json() {
+	let read =
+		|json: &str| de_string_to_sensor_value(&mut serde_json::Deserializer::from_str(json));
+	for (json, value) in [
+		(r#""42""#, SensorValue::Number(42)),
+		("42", SensorValue::Number(42)),
+		(r#""true""#, SensorValue::Bool(true)),
+		("true", SensorValue::Bool(true)),
+		("false", SensorValue::Bool(false)),
+		("4294967295", SensorValue::Number(u32::MAX)),
+	] {
+		assert_eq!(read(json).unwrap(), value, "{json}");
+	}
+	for json in ["21.5", "null", "[42]"] {
+		assert!(read(json).is_err(), "{json} was accepted");
+	}
+	#[cfg(not(feature = "fixed-point"))]
+	for json in ["-5", "4294967296"] {
+		assert!(read(json).is_err(), "{json} was accepted");
+	}
+
+	let reading: SensorData = serde_json::from_str(
+		r#"{"id":1,"type_":"Digital","geolocation":{"lat":10,"lon":20},"value":true,"timestamp":1}"#,
+	)
+	.unwrap();
+	assert_eq!(reading, sensor(1, SensorType::Digital, SensorValue::Bool(true)));
+}
+
+fn parse_type(s: &str) -> Result<SensorType, serde_json::Error> {
+	let json = serde_json::to_string(s).unwrap();
+	de_string_to_sensor_type(&mut serde_json::Deserializer::from_str(&json))
+}
+
+proptest! {
+	#[test]
+	fn sensor_data_deserialize_never_panics_on_bytes(
+		bytes in prop::collection::vec(any::<u8>(), 0..256)
+	) {
+		let _ = serde_json::from_slice::<SensorData>(&bytes);
+	}
+
+	#[test]
+	fn sensor_data_deserialize_never_panics_on_json(
+		json in r#"\{("(id|type_|geolocation|value|timestamp|lat|lon)":("[ -~]{0,8}"|-?[0-9]{1,21}|\{\}|true|null),?){0,7}\}"#
+	) {
+		let _ = serde_json::from_str::<SensorData>(&json);
+	}
+
+	// With `fixed-point` decimals and negative numbers are accepted too.
+	#[cfg(not(feature = "fixed-point"))]
+	#[test]
+	fn sensor_value_accepts_only_booleans_and_u32(
+		s in prop_oneof!["[0-9]{1,11}", "[-+]?[0-9]{1,3}", "true|false", "[ -~]{0,12}"]
+	) {
+		let expected = match s.as_str() {
+			"true" => Some(SensorValue::Bool(true)),
+			"false" => Some(SensorValue::Bool(false)),
+			s => s.parse::<u32>().ok().map(SensorValue::Number),
+		};
+
+		prop_assert_eq!(parse_value(&s).ok(), expected);
+	}
+
+	#[test]
+	fn sensor_type_accepts_known_types_and_short_custom_names(
+		// Printable ASCII except `"` and `\`, which serde_json can't hand out as a borrowed `&str`.
+		s in r#"[ !#-\[\]-~]{0,20}"#
+	) {
+		let expected = match s.as_str() {
+			"Humidity" => Some(SensorType::Humidity),
+			"Temperature" => Some(SensorType::Temperature),
+			"Pressure" => Some(SensorType::Pressure),
+			"Digital" => Some(SensorType::Digital),
+			"HeatIndex" => Some(SensorType::HeatIndex),
+			"" => None,
+			s if s.len() <= 16 => Some(custom_type(s)),
+			_ => None,
+		};
+
+		prop_assert_eq!(parse_type(&s).ok(), expected);
+	}
+
+	#[test]
+	fn well_formed_sensor_data_deserializes(
+		id: u32,
+		lat in -Geolocation::MAX_LAT..=Geolocation::MAX_LAT,
+		lon in -Geolocation::MAX_LON..=Geolocation::MAX_LON,
+		value in prop_oneof![
+			any::<u32>().prop_map(SensorValue::Number),
+			any::<bool>().prop_map(SensorValue::Bool),
+		],
+		timestamp: u64,
+	) {
+		let raw_value = match value {
+			SensorValue::Number(x) => x.to_string(),
+			SensorValue::Bool(b) => b.to_string(),
+			SensorValue::Percent(p) => p.deconstruct().to_string(),
+			SensorValue::Fixed(_) => unreachable!("not generated"),
+		};
+		let json = format!(
+			r#"{{"id":{id},"type_":"Pressure","geolocation":{{"lat":{lat},"lon":{lon}}},"value":"{raw_value}","timestamp":{timestamp}}}"#
+		);
+
+		prop_assert_eq!(
+			serde_json::from_str::<SensorData>(&json).unwrap(),
+			SensorData {
+				id,
+				type_: SensorType::Pressure,
+				geolocation: Geolocation { lat, lon },
+				value,
+				timestamp,
+				derived: false,
+				quality: None,
+			}
+		);
+	}
+}
+
+#[cfg(not(feature = "fixed-point"))]
+#[test]
+fn sensor_value_rejects_malformed_numbers() {
+	// Found by `sensor_value_accepts_only_booleans_and_u32`: these used to be stored as 0.
+	for s in ["", "-5", "21.5", "4294967296", "NaN"] {
+		assert!(parse_value(s).is_err(), "{s:?} was accepted");
+	}
+}
+
+#[test]
+fn parse_fixed_reads_decimals_in_thousandths() {
+	for (s, expected) in [
+		("21.75", 21_750),
+		("21", 21_000),
+		("21.", 21_000),
+		(".5", 500),
+		("-0.5", -500),
+		("-.5", -500),
+		("+1.001", 1_001),
+		("0", 0),
+		("-0", 0),
+		("007.070", 7_070),
+		("9223372036854775.807", i64::MAX),
+		("-9223372036854775.808", i64::MIN),
+	] {
+		assert_eq!(parse_fixed(s), Some(expected), "{s:?}");
+	}
+}
+
+#[test]
+fn parse_fixed_rejects_malformed_and_overflowing_decimals() {
+	for s in [
+		"",
+		".",
+		"-",
+		"+.",
+		"--1",
+		"+-1",
+		"1.2.3",
+		"1,5",
+		" 1.5",
+		"1e3",
+		"NaN",
+		// More decimals than are kept.
+		"0.0001",
+		"9223372036854775.808",
+		"-9223372036854775.809",
+		"100000000000000000000",
+	] {
+		assert_eq!(parse_fixed(s), None, "{s:?}");
+	}
+}
+
+#[cfg(feature = "fixed-point")]
+#[test]
+fn decimal_readings_round_trip_as_fixed() {
+	for (raw, value) in [("21.75", 21_750), ("-0.5", -500), (".5", 500), ("-5", -5_000)] {
+		assert_eq!(parse_value(raw).unwrap(), SensorValue::Fixed(value));
+
+		let reading = sensor(1, SensorType::Temperature, SensorValue::Fixed(value));
+		let json = serde_json::to_string(&reading).unwrap();
+		assert_eq!(serde_json::from_str::<SensorData>(&json).unwrap(), reading);
+	}
+	// Whole numbers are still `Number`s.
+	assert_eq!(parse_value("21").unwrap(), SensorValue::Number(21));
+	assert!(parse_value("0.0001").is_err());
+}
+
+fn parse_humidity(value: &str) -> Result<SensorData, serde_json::Error> {
+	serde_json::from_str(&format!(
+		r#"{{"id":1,"type_":"Humidity","geolocation":{{"lat":0,"lon":0}},"value":"{value}","timestamp":1}}"#
+	))
+}
+
+#[test]
+fn humidity_deserializes_to_a_percent() {
+	assert_eq!(
+		parse_humidity("45").unwrap().value,
+		SensorValue::Percent(Percent::from_percent(45))
+	);
+	assert_eq!(
+		parse_humidity("100").unwrap().value,
+		SensorValue::Percent(Percent::from_percent(100))
+	);
+}
+
+#[test]
+fn humidity_above_100_is_rejected() {
+	assert!(parse_humidity("101").is_err());
+	assert!(parse_humidity("150").is_err());
+	assert!(parse_humidity("256").is_err());
+}
+
+fn set_temperature_alert(low: Option<u32>, high: Option<u32>) {
+	assert_ok!(PriceOracleOcwExample::set_alert_threshold(
+		RuntimeOrigin::root(),
+		1,
+		SensorType::Temperature,
+		low,
+		high
+	));
+	System::assert_last_event(
+		Event::AlertThresholdSet { id: 1, type_: SensorType::Temperature }.into(),
+	);
+}
+
+#[test]
+fn reading_above_the_high_bound_raises_an_alert() {
+	new_test_ext().execute_with(|| {
+		set_temperature_alert(Some(5), Some(40));
+
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Temperature,
+			SensorValue::Number(41),
+		));
+
+		System::assert_has_event(
+			Event::ThresholdBreached {
+				id: 1,
+				type_: SensorType::Temperature,
+				value: SensorValue::Number(41),
+				bound: 40,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn reading_below_the_low_bound_raises_an_alert() {
+	new_test_ext().execute_with(|| {
+		set_temperature_alert(Some(5), None);
+
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Temperature,
+			SensorValue::Number(4),
+		));
+
+		System::assert_has_event(
+			Event::ThresholdBreached {
+				id: 1,
+				type_: SensorType::Temperature,
+				value: SensorValue::Number(4),
+				bound: 5,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn fixed_point_readings_are_checked_against_the_bounds() {
+	new_test_ext().execute_with(|| {
+		set_temperature_alert(Some(5), Some(40));
+
+		for (timestamp, value) in (1..).zip([5_000, 40_000, 40_500, -1_000]) {
+			PriceOracleOcwExample::add_sensor_data(SensorData {
+				timestamp,
+				..sensor(1, SensorType::Temperature, SensorValue::Fixed(value))
+			});
+		}
+
+		let breaches: Vec<_> = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				RuntimeEvent::PriceOracleOcwExample(Event::ThresholdBreached {
+					value,
+					bound,
+					..
+				}) => Some((value, bound)),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(
+			breaches,
+			vec![(SensorValue::Fixed(40_500), 40), (SensorValue::Fixed(-1_000), 5)]
+		);
+	});
+}
+
+#[test]
+fn readings_within_the_bounds_raise_no_alert() {
+	new_test_ext().execute_with(|| {
+		set_temperature_alert(Some(5), Some(40));
+
+		for value in [5, 21, 40] {
+			PriceOracleOcwExample::add_sensor_data(sensor(
+				1,
+				SensorType::Temperature,
+				SensorValue::Number(value),
+			));
+		}
+		// Bounds of another sensor type don't apply.
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Humidity,
+			SensorValue::Number(90),
+		));
+
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PriceOracleOcwExample(Event::ThresholdBreached { .. })
+		)));
+	});
+}
+
+#[test]
+fn clearing_both_bounds_removes_the_alert_threshold() {
+	new_test_ext().execute_with(|| {
+		set_temperature_alert(Some(5), Some(40));
+		set_temperature_alert(None, None);
+
+		assert_eq!(PriceOracleOcwExample::alert_threshold(1, SensorType::Temperature), None);
+	});
+}
+
+#[test]
+fn duplicate_readings_are_skipped_and_weigh_less() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let batch = vec![
+			sensor(1, SensorType::Temperature, SensorValue::Number(21)),
+			sensor(2, SensorType::Digital, SensorValue::Bool(false)),
+		];
+		let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+		let submit = || {
+			PriceOracleOcwExample::update_sensors_data(
+				RuntimeOrigin::signed(test_pub()),
+				batch.clone(),
+				checksum,
+			)
+			.unwrap()
+		};
+
+		let new = submit();
+		System::reset_events();
+		let duplicate = submit();
+
+		assert_eq!(new.pays_fee, Pays::No);
+		assert!(
+			duplicate.actual_weight.unwrap().ref_time() < new.actual_weight.unwrap().ref_time()
+		);
+		assert_eq!(PriceOracleOcwExample::sensor_history(1, SensorType::Temperature).len(), 1);
+		assert_eq!(
+			System::events().into_iter().map(|record| record.event).collect::<Vec<_>>(),
+			vec![Event::SensorsDataUpdated { count: 0, seq: 4 }.into()]
+		);
+	});
+}
+
+#[test]
+fn identical_readings_in_a_batch_are_written_once() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let temperature = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+		let pressure = sensor(2, SensorType::Pressure, SensorValue::Number(1000));
+		// A boolean value for a temperature sensor is rejected.
+		let invalid = sensor(1, SensorType::Temperature, SensorValue::Bool(true));
+
+		submit_batch(vec![
+			temperature.clone(),
+			pressure.clone(),
+			invalid.clone(),
+			temperature.clone(),
+			invalid,
+			pressure.clone(),
+		]);
+
+		assert_eq!(UPDATES.with(|updates| updates.borrow().clone()), vec![temperature, pressure]);
+		assert_eq!(PriceOracleOcwExample::readings_stored(), 2);
+		assert_eq!(
+			PriceOracleOcwExample::rejection_counts(),
+			vec![(RejectReason::ValueTypeMismatch, 1)]
+		);
+		assert_eq!(
+			PriceOracleOcwExample::last_batch_result(test_pub()),
+			Some(BatchResult { accepted: 2, rejected: 1 })
+		);
+	});
+}
+
+#[test]
+fn dedup_readings_keeps_the_first_of_identical_readings() {
+	let first = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+	let second = sensor(2, SensorType::Temperature, SensorValue::Number(21));
+	// Same sensor, different content.
+	let later = SensorData { timestamp: 2, ..first.clone() };
+	let mut readings =
+		vec![first.clone(), second.clone(), first.clone(), later.clone(), second.clone()];
+
+	assert_eq!(PriceOracleOcwExample::dedup_readings(&mut readings), 2);
+	assert_eq!(readings, vec![first, second, later]);
+}
+
+#[test]
+fn sensor_type_all_lists_every_built_in_type() {
+	// The built-in types are the ones encoded as their index alone, `Custom` needs its name too.
+	let decodable: Vec<_> = (0..=u8::MAX)
+		.filter_map(|index| SensorType::decode(&mut &[index][..]).ok())
+		.collect();
+
+	assert_eq!(decodable, SensorType::ALL);
+	assert_eq!(SensorType::count(), SensorType::ALL.len());
+	for type_ in SensorType::ALL {
+		let name = serde_json::to_string(type_).unwrap();
+		assert_eq!(serde_json::from_str::<SensorType>(&name).unwrap(), *type_);
+	}
+}
+
+#[test]
+fn stale_sensors_lists_the_readings_older_than_max_age_with_their_age() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::stale_sensors(10), vec![]);
+		for (id, type_, value, timestamp) in [
+			(1, SensorType::Temperature, SensorValue::Number(21), 100),
+			(2, SensorType::Pressure, SensorValue::Number(1000), 40),
+			// Exactly `max_age` old, so not stale yet.
+			(3, SensorType::Temperature, SensorValue::Number(19), 90),
+			(1, SensorType::Digital, SensorValue::Bool(true), 10),
+		] {
+			PriceOracleOcwExample::add_sensor_data(SensorData {
+				timestamp,
+				..sensor(id, type_, value)
+			});
+		}
+
+		Now::set(100);
+
+		let mut stale = PriceOracleOcwExample::stale_sensors(10);
+		stale.sort_by_key(|(id, _, _)| *id);
+
+		assert_eq!(stale, vec![(1, SensorType::Digital, 90), (2, SensorType::Pressure, 60)]);
+		assert_eq!(PriceOracleOcwExample::stale_sensors(100), vec![]);
+	});
+}
+
+#[test]
+fn stale_sensors_age_with_the_block_time_when_no_readings_arrive() {
+	new_test_ext().execute_with(|| {
+		for id in [1, 2] {
+			PriceOracleOcwExample::add_sensor_data(SensorData {
+				timestamp: 1_000,
+				..sensor(id, SensorType::Temperature, SensorValue::Number(21))
+			});
+		}
+		Now::set(1_000);
+		assert_eq!(PriceOracleOcwExample::stale_sensors(60_000), vec![]);
+
+		// The whole feed stops while blocks keep coming.
+		Now::set(61_001);
+		let mut stale = PriceOracleOcwExample::stale_sensors(60_000);
+		stale.sort_by_key(|(id, _, _)| *id);
+
+		assert_eq!(
+			stale,
+			vec![(1, SensorType::Temperature, 60_001), (2, SensorType::Temperature, 60_001)]
+		);
+	});
+}
+
+#[test]
+fn duplicate_readings_emit_nothing_when_silent() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		assert_ok!(PriceOracleOcwExample::set_event_verbosity(
+			RuntimeOrigin::root(),
+			EventVerbosity::Silent
+		));
+		let batch = vec![sensor(1, SensorType::Temperature, SensorValue::Number(21))];
+		submit_batch(batch.clone());
+		let seq = PriceOracleOcwExample::current_event_seq();
+		System::reset_events();
+
+		submit_batch(batch);
+
+		assert_no_events();
+		assert_eq!(PriceOracleOcwExample::current_event_seq(), seq);
+		assert_eq!(PriceOracleOcwExample::sensor_history(1, SensorType::Temperature).len(), 1);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn circuit_breaker_skips_cycles_after_repeated_failures_until_a_success() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	let oracle = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	let run = |block: u64, response: Option<&[u8]>| {
+		if let Some(body) = response {
+			state.write().expect_request(testing::PendingRequest {
+				method: "GET".into(),
+				uri: "https://sensors-api.vercel.app/api/v1/sensors".into(),
+				headers: vec![
+					("Accept".into(), "application/json".into()),
+					("Accept-Encoding".into(), "gzip".into()),
+				],
+				response: Some(body.to_vec()),
+				sent: true,
+				..Default::default()
+			});
+		}
+		PriceOracleOcwExample::run_offchain_worker(block);
+	};
+	let breaker = || {
+		sp_runtime::offchain::storage::StorageValueRef::persistent(crate::BREAKER_KEY)
+			.get::<BreakerState<u64>>()
+			.unwrap()
+	};
+
+	t.execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), oracle));
+		// `MaxConsecutiveFailures` failures open the breaker for 2 blocks.
+		for block in 1..=3 {
+			run(block, Some(b"not json"));
+		}
+		assert_eq!(breaker(), Some(BreakerState { failures: 3, retry_at: 5 }));
+		// No request is expected while the breaker is open.
+		run(4, None);
+
+		// Failing again doubles the backoff.
+		run(5, Some(b"not json"));
+		for block in 6..9 {
+			run(block, None);
+		}
+		assert_eq!(breaker(), Some(BreakerState { failures: 4, retry_at: 9 }));
+
+		// A success closes the breaker, so the next block runs as usual.
+		run(9, Some(b"[]"));
+		assert_eq!(breaker(), None);
+		run(10, Some(b"[]"));
+		assert_eq!(pool_state.read().transactions.len(), 2);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_reads_the_local_fixture_without_http() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	let oracle = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	let fixture = include_bytes!("../fixtures/sensors.json");
+
+	t.execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), oracle));
+		let set = |key, value: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, key, value)
+		};
+		set(USE_LOCAL_FIXTURE_KEY, &[]);
+		set(LOCAL_FIXTURE_KEY, fixture);
+
+		// No HTTP expectation is registered, so any request would panic.
+		let submitted = PriceOracleOcwExample::get_sensors_data().unwrap();
+		assert_eq!(submitted, PriceOracleOcwExample::process_sensors_json(fixture).unwrap());
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(
+			tx.call,
+			RuntimeCall::PriceOracleOcwExample(crate::Call::update_sensors_data {
+				checksum: PriceOracleOcwExample::readings_checksum(&submitted),
+				updated_data: submitted.clone(),
+			})
+		);
+		assert_eq!(
+			sp_runtime::offchain::storage::StorageValueRef::persistent(LAST_SUBMISSION_KEY)
+				.get::<Vec<SensorData>>(),
+			Ok(Some(submitted))
+		);
+	});
+}
+
+#[test]
+fn diff_readings_reports_added_removed_and_changed_sensors() {
+	let temperature = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+	let humidity = sensor(1, SensorType::Humidity, SensorValue::Number(40));
+	let door = sensor(2, SensorType::Digital, SensorValue::Bool(false));
+	let old = [temperature.clone(), humidity.clone(), door.clone()];
+
+	assert!(PriceOracleOcwExample::diff_readings(&old, &old).is_empty());
+	// Only the value counts, not when it was read.
+	let reread = [SensorData { timestamp: 2, ..temperature.clone() }, humidity.clone(), door];
+	assert!(PriceOracleOcwExample::diff_readings(&old, &reread).is_empty());
+	assert!(PriceOracleOcwExample::diff_readings(&[], &[]).is_empty());
+
+	let pressure = sensor(3, SensorType::Pressure, SensorValue::Number(1013));
+	let new = [
+		pressure.clone(),
+		SensorData { value: SensorValue::Number(22), ..temperature },
+		humidity,
+		// The last reading of a sensor is taken.
+		sensor(2, SensorType::Digital, SensorValue::Bool(true)),
+		sensor(2, SensorType::Digital, SensorValue::Bool(false)),
+	];
+	let diff = PriceOracleOcwExample::diff_readings(&old, &new);
+	assert_eq!(
+		diff,
+		ReadingDiff {
+			added: vec![(3, SensorType::Pressure)],
+			removed: vec![],
+			changed: vec![ValueChange {
+				id: 1,
+				type_: SensorType::Temperature,
+				old: SensorValue::Number(21),
+				new: SensorValue::Number(22),
+			}],
+		}
+	);
+
+	let diff = PriceOracleOcwExample::diff_readings(&new, &[pressure]);
+	assert_eq!(
+		diff.removed,
+		vec![(1, SensorType::Temperature), (1, SensorType::Humidity), (2, SensorType::Digital)]
+	);
+	assert!(diff.added.is_empty() && diff.changed.is_empty());
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_submits_every_kth_reading_of_each_sensor() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	let oracle = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), oracle));
+		let set = |key, value: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, key, value)
+		};
+		set(USE_LOCAL_FIXTURE_KEY, &[]);
+		set(SAMPLE_EVERY_KEY, &3u32.encode());
+
+		// Sensor 1 reports on every run, sensor 2 on every other run, so the API serves each of
+		// its readings twice.
+		let mut submitted = vec![];
+		for run in 1..=14u64 {
+			let readings = [
+				sensor(1, SensorType::Pressure, SensorValue::Number(1000)),
+				sensor(2, SensorType::Digital, SensorValue::Bool(true)),
+			]
+			.map(|reading| SensorData {
+				timestamp: if reading.id == 1 { run } else { run.div_ceil(2) },
+				..reading
+			});
+			set(LOCAL_FIXTURE_KEY, &serde_json::to_vec(&readings).unwrap());
+			submitted.extend(
+				PriceOracleOcwExample::get_sensors_data()
+					.unwrap()
+					.into_iter()
+					.map(|reading| (reading.id, reading.timestamp)),
+			);
+		}
+
+		assert_eq!(
+			submitted,
+			vec![(1, 1), (2, 1), (1, 4), (1, 7), (2, 4), (1, 10), (1, 13), (2, 7)]
+		);
+		assert_eq!(pool_state.read().transactions.len(), 14);
+	});
+}
+
+#[test]
+fn on_idle_prunes_stale_history_across_blocks() {
+	new_test_ext().execute_with(|| {
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		let base = db.reads_writes(2, 1);
+		let per_entry = db.reads_writes(1, 1);
+		// Enough for two entries per block.
+		let budget = base + per_entry * 2;
+
+		for id in 1..=4 {
+			PriceOracleOcwExample::add_sensor_data(sensor(
+				id,
+				SensorType::Pressure,
+				SensorValue::Number(1),
+			));
+		}
+		let fresh = SensorData {
+			timestamp: 950,
+			..sensor(1, SensorType::Pressure, SensorValue::Number(2))
+		};
+		PriceOracleOcwExample::add_sensor_data(fresh.clone());
+		assert_eq!(PriceOracleOcwExample::latest_timestamp(), 950);
+		let entries = || SensorHistory::<Test>::iter_keys().count();
+
+		assert_eq!(PriceOracleOcwExample::on_idle(1, budget), budget);
+		assert!(entries() >= 2);
+		assert!(pallet::PruneCursor::<Test>::get().is_some());
+
+		assert_eq!(PriceOracleOcwExample::on_idle(2, budget), budget);
+		assert_eq!(entries(), 1);
+
+		// Nothing left to walk: the cursor wraps around to the start of the map.
+		assert_eq!(PriceOracleOcwExample::on_idle(3, budget), base);
+		assert!(pallet::PruneCursor::<Test>::get().is_none());
+
+		// Readings within `HistoryRetention` of the newest one are kept.
+		assert_eq!(
+			PriceOracleOcwExample::sensor_history(1, SensorType::Pressure).to_vec(),
+			vec![fresh]
+		);
+	});
+}
+
+#[test]
+fn on_idle_expires_stale_readings_when_enabled() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		AutoExpire::set(true);
+		let info = SensorInfo { unit: Some(Unit::Pascal), ..Default::default() };
+		assert_ok!(PriceOracleOcwExample::set_sensor_metadata(
+			RuntimeOrigin::root(),
+			1,
+			info.clone()
+		));
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Pressure,
+			SensorValue::Number(1),
+		));
+		let fresh = SensorData {
+			timestamp: 950,
+			..sensor(2, SensorType::Pressure, SensorValue::Number(2))
+		};
+		PriceOracleOcwExample::add_sensor_data(fresh.clone());
+
+		PriceOracleOcwExample::on_idle(1, Weight::MAX);
+
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Pressure), None);
+		System::assert_has_event(
+			Event::SensorExpired { id: 1, type_: SensorType::Pressure }.into(),
+		);
+		// Readings within `HistoryRetention` of the newest one are kept, and so is the metadata
+		// of expired sensors.
+		assert_eq!(PriceOracleOcwExample::sensors(2, SensorType::Pressure), Some(fresh));
+		assert_eq!(PriceOracleOcwExample::sensor_metadata(1), Some(info));
+	});
+}
+
+#[test]
+fn on_idle_keeps_stale_readings_by_default() {
+	new_test_ext().execute_with(|| {
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Pressure,
+			SensorValue::Number(1),
+		));
+		PriceOracleOcwExample::add_sensor_data(SensorData {
+			timestamp: 950,
+			..sensor(2, SensorType::Pressure, SensorValue::Number(2))
+		});
+
+		PriceOracleOcwExample::on_idle(1, Weight::MAX);
+
+		assert!(PriceOracleOcwExample::sensors(1, SensorType::Pressure).is_some());
+	});
+}
+
+#[test]
+fn on_idle_does_nothing_without_weight_for_an_entry() {
+	new_test_ext().execute_with(|| {
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Pressure,
+			SensorValue::Number(1),
+		));
+		pallet::LatestTimestamp::<Test>::put(1_000);
+
+		let budget = db.reads_writes(2, 1) + db.reads_writes(1, 1) - Weight::from_parts(1, 0);
+		assert_eq!(PriceOracleOcwExample::on_idle(1, budget), Weight::zero());
+
+		assert_eq!(SensorHistory::<Test>::iter_keys().count(), 1);
+		assert!(pallet::PruneCursor::<Test>::get().is_none());
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_signs_with_the_preferred_account() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	let first = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+	let second = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), first));
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), second));
+		// Tell the accounts apart by their nonce, the only signer data in a `TestXt`.
+		frame_system::Account::<Test>::mutate(first, |account| account.nonce = 1);
+		frame_system::Account::<Test>::mutate(second, |account| account.nonce = 2);
+
+		let set = |key, value: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, key, value)
+		};
+		set(USE_LOCAL_FIXTURE_KEY, &[]);
+		set(LOCAL_FIXTURE_KEY, b"[]");
+
+		for (preferred, nonce) in [(first, 1), (second, 2)] {
+			set(PREFERRED_SIGNER_KEY, &preferred.encode());
+			PriceOracleOcwExample::get_sensors_data().unwrap();
+
+			let tx = pool_state.write().transactions.pop().unwrap();
+			let tx = Extrinsic::decode(&mut &*tx).unwrap();
+			assert_eq!(tx.signature.unwrap().0, nonce);
+		}
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn round_robin_rotates_the_signing_key_every_run() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	let mut keys: Vec<_> = (0..3)
+		.map(|_| keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap())
+		.collect();
+	keys.sort();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		// Tell the accounts apart by their nonce, which goes up by one with each transaction.
+		for (i, key) in keys.iter().enumerate() {
+			assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), *key));
+			frame_system::Account::<Test>::mutate(key, |account| account.nonce = 10 * i as u64);
+		}
+		let set = |key, value: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, key, value)
+		};
+		set(USE_LOCAL_FIXTURE_KEY, &[]);
+		set(LOCAL_FIXTURE_KEY, b"[]");
+		set(SIGNER_STRATEGY_KEY, &SignerStrategy::RoundRobin.encode());
+
+		let signers: Vec<_> = (0..4)
+			.map(|_| {
+				PriceOracleOcwExample::get_sensors_data().unwrap();
+				let tx = pool_state.write().transactions.pop().unwrap();
+				Extrinsic::decode(&mut &*tx).unwrap().signature.unwrap().0 / 10
+			})
+			.collect();
+
+		assert_eq!(signers, vec![0, 1, 2, 0]);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_submits_nothing_if_the_preferred_account_is_missing() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			PREFERRED_SIGNER_KEY,
+			&account(1).encode(),
+		);
+
+		assert!(matches!(
+			PriceOracleOcwExample::get_sensors_data(),
+			Err(Error::<Test>::NoLocalKey)
+		));
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+#[test]
+fn trust_score_drops_with_rejected_submissions_and_recovers() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		assert_eq!(PriceOracleOcwExample::trust_score(&test_pub()), MAX_TRUST_SCORE);
+
+		let reading = |timestamp| SensorData {
+			timestamp,
+			..sensor(1, SensorType::Temperature, SensorValue::Number(20))
+		};
+		submit_batch(vec![reading(50)]);
+		assert_eq!(PriceOracleOcwExample::trust_score(&test_pub()), MAX_TRUST_SCORE);
+
+		// Older than the stored reading, so rejected: each batch scores 0.
+		submit_batch(vec![reading(40)]);
+		assert_eq!(PriceOracleOcwExample::trust_score(&test_pub()), 875);
+		submit_batch(vec![reading(40)]);
+		assert_eq!(PriceOracleOcwExample::trust_score(&test_pub()), 765);
+
+		// Half accepted, half rejected.
+		submit_batch(vec![
+			reading(40),
+			SensorData { timestamp: 60, ..sensor(2, SensorType::Pressure, SensorValue::Number(1)) },
+		]);
+		assert_eq!(PriceOracleOcwExample::trust_score(&test_pub()), (7 * 765 + 500) / 8);
+
+		let mut score = PriceOracleOcwExample::trust_score(&test_pub());
+		for timestamp in 70..75 {
+			submit_batch(vec![reading(timestamp)]);
+			let recovered = PriceOracleOcwExample::trust_score(&test_pub());
+			assert!(recovered > score);
+			score = recovered;
+		}
+	});
+}
+
+#[test]
+fn trust_score_weights_accepted_readings_by_age() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		PriceOracleOcwExample::add_sensor_data(SensorData {
+			timestamp: 1_000,
+			..sensor(1, SensorType::Pressure, SensorValue::Number(1))
+		});
+
+		// `HistoryRetention` is 100, so a reading 25 behind the newest one is 75% fresh.
+		assert_eq!(PriceOracleOcwExample::reading_freshness(1_000, 975), 750);
+		submit_batch(vec![SensorData {
+			timestamp: 975,
+			..sensor(2, SensorType::Pressure, SensorValue::Number(1))
+		}]);
+		assert_eq!(PriceOracleOcwExample::trust_score(&test_pub()), (7 * 1_000 + 750) / 8);
+
+		// Past the retention window readings are worth nothing.
+		assert_eq!(PriceOracleOcwExample::reading_freshness(1_000, 1), 0);
+		assert_eq!(PriceOracleOcwExample::reading_freshness(1_000, 2_000), MAX_TRUST_SCORE);
+	});
+}
+
+#[test]
+fn trust_score_ignores_batches_of_duplicates() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let stale =
+			SensorData { timestamp: 5, ..sensor(1, SensorType::Pressure, SensorValue::Number(1)) };
+		let stored = SensorData { timestamp: 10, ..stale.clone() };
+		submit_batch(vec![stored.clone()]);
+		submit_batch(vec![stale]);
+		let score = PriceOracleOcwExample::trust_score(&test_pub());
+
+		submit_batch(vec![stored]);
+		assert_eq!(PriceOracleOcwExample::trust_score(&test_pub()), score);
+	});
+}
+
+#[cfg(feature = "ocw")]
+/// Expect a plain request for the sensors asking for `accept`, answered with `body`.
+fn negotiated_sensors_response(state: &mut testing::OffchainState, accept: &str, body: &[u8]) {
+	state.expect_request(testing::PendingRequest {
+		method: "GET".into(),
+		uri: "https://sensors-api.vercel.app/api/v1/sensors".into(),
+		headers: vec![("Accept".into(), accept.into()), ("Accept-Encoding".into(), "gzip".into())],
+		response: Some(body.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn json_and_csv_responses_give_the_same_readings() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	t.execute_with(|| {
+		negotiated_sensors_response(
+			&mut state.write(),
+			"application/json",
+			include_bytes!("../fixtures/sensors.json"),
+		);
+		let json = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			RESPONSE_FORMAT_KEY,
+			&ResponseFormat::Csv.encode(),
+		);
+		negotiated_sensors_response(
+			&mut state.write(),
+			"text/csv",
+			include_bytes!("../fixtures/sensors.csv"),
+		);
+		let csv = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+
+		assert_eq!(json.len(), 5);
+		assert_eq!(csv, json);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn compact_and_object_responses_give_the_same_readings() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	t.execute_with(|| {
+		negotiated_sensors_response(
+			&mut state.write(),
+			"application/json",
+			include_bytes!("../fixtures/sensors.json"),
+		);
+		let objects = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			RESPONSE_FORMAT_KEY,
+			&ResponseFormat::CompactJson.encode(),
+		);
+		negotiated_sensors_response(
+			&mut state.write(),
+			"application/json",
+			include_bytes!("../fixtures/sensors.compact.json"),
+		);
+		let compact = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+
+		assert_eq!(objects.len(), 5);
+		assert_eq!(compact, objects);
+	});
+}
+
+#[test]
+fn geolocations_out_of_range_are_rejected() {
+	let parse = |lat: i32, lon: i32| {
+		serde_json::from_str::<SensorData>(&format!(
+			r#"{{"id":1,"type_":"Pressure","geolocation":{{"lat":{lat},"lon":{lon}}},"value":"1","timestamp":1}}"#
+		))
+		.map(|sensor| sensor.geolocation)
+	};
+
+	for (lat, lon) in [(4581, 1523), (0, 0), (9000, 18000), (-9000, -18000), (-3387, 15121)] {
+		assert_eq!(parse(lat, lon).unwrap(), Geolocation { lat, lon });
+	}
+	for (lat, lon) in [(9001, 0), (-9001, 0), (0, 18001), (0, -18001), (i32::MIN, i32::MAX)] {
+		let e = parse(lat, lon).unwrap_err();
+		assert!(e.to_string().contains("Geolocation is out of range"), "{e}");
+	}
+}
+
+#[test]
+fn compact_records_take_negative_coordinates() {
+	let object = serde_json::from_str::<SensorData>(
+		r#"{"id":1,"type_":"Temperature","geolocation":{"lat":-33,"lon":151},"value":"21","timestamp":1680000000}"#,
+	)
+	.unwrap();
+
+	assert_eq!(object.geolocation, Geolocation { lat: -33, lon: 151 });
+	assert_eq!(
+		PriceOracleOcwExample::process_sensors_compact(
+			br#"[[1,"Temperature",[-33,151],"21",1680000000]]"#
+		)
+		.unwrap(),
+		vec![object]
+	);
+}
+
+#[test]
+fn malformed_compact_records_are_rejected() {
+	for record in [
+		r#"[1,"Temperature",[0,0],"30"]"#,
+		r#"[1,"Temperature",[0,0],"30",1,2]"#,
+		r#"[1,"Temperature",{"lat":0,"lon":0},"30",1]"#,
+		r#"[1,"Temperature",[0],"30",1]"#,
+		r#"[1,"Humidity",[0,0],"150",1]"#,
+		r#"[1,"Temperature",[9001,0],"30",1]"#,
+		r#"[1,"Temperature",[0,18001],"30",1]"#,
+		r#"[1,"Temperature",[-9001,0],"30",1]"#,
+		r#"[1,"Temperature",[0,-18001],"30",1]"#,
+		r#"{"id":1,"type_":"Temperature","geolocation":{"lat":0,"lon":0},"value":"30","timestamp":1}"#,
+	] {
+		assert!(
+			matches!(
+				PriceOracleOcwExample::process_sensors_compact(format!("[{record}]").as_bytes()),
+				Err(Error::<Test>::DeserializeError)
+			),
+			"{record:?} was accepted"
+		);
+	}
+}
+
+#[test]
+fn malformed_csv_records_are_rejected() {
+	for record in [
+		"1,Temperature,0,0,30",
+		"1,Temperature,0,0,30,1,extra",
+		"x,Temperature,0,0,30,1",
+		"1,,0,0,30,1",
+		"1,Temperature,0,0,21.5.1,1",
+		"1,Humidity,0,0,150,1",
+		"1,Temperature,9001,0,30,1",
+		"1,Temperature,0,18001,30,1",
+	] {
+		assert_eq!(SensorData::from_csv_record(record), None, "{record:?} was accepted");
+	}
+	assert!(matches!(
+		PriceOracleOcwExample::process_sensors_csv(b"id,type,lat,lon,value,timestamp\n1,Pressure"),
+		Err(Error::<Test>::DeserializeError)
+	));
+}
+
+#[test]
+fn readings_of_unregistered_sensors_are_rejected_only_when_registration_is_required() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		assert_ok!(PriceOracleOcwExample::set_sensor_metadata(
+			RuntimeOrigin::root(),
+			1,
+			SensorInfo::default()
+		));
+		let registered = sensor(1, SensorType::Pressure, SensorValue::Number(1));
+		let unregistered = sensor(2, SensorType::Pressure, SensorValue::Number(1));
+
+		// Off by default.
+		assert!(!PriceOracleOcwExample::require_registration());
+		submit_batch(vec![unregistered.clone()]);
+		assert_eq!(PriceOracleOcwExample::sensors(2, SensorType::Pressure), Some(unregistered));
+
+		assert_ok!(PriceOracleOcwExample::set_require_registration(RuntimeOrigin::root(), true));
+		System::assert_last_event(Event::RequireRegistrationSet { required: true }.into());
+		let unregistered =
+			SensorData { timestamp: 2, ..sensor(3, SensorType::Pressure, SensorValue::Number(1)) };
+		submit_batch(vec![registered.clone(), unregistered]);
+
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Pressure), Some(registered));
+		assert_eq!(PriceOracleOcwExample::sensors(3, SensorType::Pressure), None);
+		System::assert_has_event(
+			Event::ReadingRejected {
+				id: 3,
+				type_: SensorType::Pressure,
+				reason: RejectReason::Unregistered,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn set_require_registration_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PriceOracleOcwExample::set_require_registration(
+				RuntimeOrigin::signed(test_pub()),
+				true
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[cfg(feature = "ocw")]
+/// A transaction pool refusing every transaction, as a full one does.
+struct FullPool;
+
+#[cfg(feature = "ocw")]
+impl sp_core::offchain::TransactionPool for FullPool {
+	fn submit_transaction(&mut self, _extrinsic: Vec<u8>) -> Result<(), ()> {
+		Err(())
+	}
+}
+
+/// Run `get_sensors_data` on the local fixture `[]` with one oracle key, made an authority if
+/// `authority` is set, and the given transaction pool.
+#[cfg(feature = "ocw")]
+fn submit_with(authority: bool, pool: TransactionPoolExt) -> Result<Vec<SensorData>, Error<Test>> {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let keystore = MemoryKeystore::new();
+	let oracle = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(pool);
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		// Some other account is an authority otherwise, so the authorities aren't empty.
+		let authority = if authority { oracle } else { account(1) };
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), authority));
+		sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, USE_LOCAL_FIXTURE_KEY, &[]);
+		sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, LOCAL_FIXTURE_KEY, b"[]");
+
+		PriceOracleOcwExample::get_sensors_data()
+	})
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_reports_a_key_that_is_not_an_authority() {
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+
+	assert!(matches!(
+		submit_with(false, TransactionPoolExt::new(pool)),
+		Err(Error::<Test>::SubmitFailed(SubmitFailure::NotAuthority))
+	));
+	assert!(pool_state.read().transactions.is_empty());
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_reports_a_transaction_refused_by_the_pool() {
+	assert!(matches!(
+		submit_with(true, TransactionPoolExt::new(FullPool)),
+		Err(Error::<Test>::SubmitFailed(SubmitFailure::PoolRejected))
+	));
+}
+
+#[test]
+fn readings_must_match_the_value_kind_of_their_type() {
+	let percent = SensorValue::Percent(Percent::from_percent(40));
+	for (type_, value) in [
+		(SensorType::Digital, SensorValue::Bool(true)),
+		(SensorType::Temperature, SensorValue::Number(21)),
+		(SensorType::Pressure, SensorValue::Number(101_325)),
+		(SensorType::HeatIndex, SensorValue::Number(25)),
+		(SensorType::Humidity, percent),
+		(SensorType::Humidity, SensorValue::Number(40)),
+		(custom_type("CO2"), SensorValue::Number(415)),
+		(custom_type("Door"), SensorValue::Bool(false)),
+	] {
+		assert!(type_.accepts(&value), "{type_:?} rejected {value:?}");
+	}
+	for (type_, value) in [
+		(SensorType::Digital, SensorValue::Number(1)),
+		(SensorType::Temperature, SensorValue::Bool(true)),
+		(SensorType::Pressure, percent),
+		(SensorType::Humidity, SensorValue::Bool(false)),
+	] {
+		assert!(!type_.accepts(&value), "{type_:?} accepted {value:?}");
+	}
+}
+
+#[test]
+fn update_sensors_data_rejects_a_digital_reading_with_a_number() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+
+		submit_batch(vec![
+			sensor(1, SensorType::Digital, SensorValue::Number(1)),
+			sensor(2, SensorType::Digital, SensorValue::Bool(true)),
+		]);
+
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Digital), None);
+		assert!(PriceOracleOcwExample::sensors(2, SensorType::Digital).is_some());
+		System::assert_has_event(
+			Event::ReadingRejected {
+				id: 1,
+				type_: SensorType::Digital,
+				reason: RejectReason::ValueTypeMismatch,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn update_sensors_data_rejects_a_geolocation_out_of_range() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let at = |lat, lon| SensorData {
+			geolocation: Geolocation { lat, lon },
+			..sensor(1, SensorType::Pressure, SensorValue::Number(1013))
+		};
+
+		submit_batch(vec![at(0, 18001), SensorData { id: 2, ..at(-9000, -18000) }]);
+
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Pressure), None);
+		assert!(PriceOracleOcwExample::sensors(2, SensorType::Pressure).is_some());
+		System::assert_has_event(
+			Event::ReadingRejected {
+				id: 1,
+				type_: SensorType::Pressure,
+				reason: RejectReason::OutOfRange,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn update_sensors_data_rejects_a_humidity_above_100() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+
+		submit_batch(vec![
+			sensor(1, SensorType::Humidity, SensorValue::Number(150)),
+			sensor(2, SensorType::Humidity, SensorValue::Number(100)),
+		]);
+
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Humidity), None);
+		assert!(PriceOracleOcwExample::sensors(2, SensorType::Humidity).is_some());
+		System::assert_has_event(
+			Event::ReadingRejected {
+				id: 1,
+				type_: SensorType::Humidity,
+				reason: RejectReason::OutOfRange,
+			}
+			.into(),
+		);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_merges_the_configured_sensors() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	let oracle = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	for (id, type_, value) in [(1, "Temperature", "30"), (2, "Pressure", "101325")] {
+		let body = format!(
+			r#"[{{"id":{},"type_":"{}","geolocation":{{"lat":1,"lon":2}},"value":"{}","timestamp":3}}]"#,
+			id, type_, value
+		);
+		state.write().expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri: format!("https://sensors-api.vercel.app/api/v1/sensors/{}", id),
+			headers: vec![
+				("Accept".into(), "application/json".into()),
+				("Accept-Encoding".into(), "gzip".into()),
+			],
+			response: Some(body.into_bytes()),
+			sent: true,
+			..Default::default()
+		});
+	}
+
+	t.execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), oracle));
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			SENSOR_IDS_KEY,
+			&vec![1u32, 2].encode(),
+		);
+
+		let submitted = PriceOracleOcwExample::get_sensors_data().unwrap();
+		let reading = |id, type_, value| SensorData {
+			id,
+			type_,
+			geolocation: Geolocation { lat: 1, lon: 2 },
+			value: SensorValue::Number(value),
+			timestamp: 3,
+			derived: false,
+			quality: None,
+		};
+		assert_eq!(
+			submitted,
+			vec![reading(1, SensorType::Temperature, 30), reading(2, SensorType::Pressure, 101325)]
+		);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(
+			tx.call,
+			RuntimeCall::PriceOracleOcwExample(crate::Call::update_sensors_data {
+				checksum: PriceOracleOcwExample::readings_checksum(&submitted),
+				updated_data: submitted,
+			})
+		);
+	});
+}
+
+#[cfg(feature = "ocw")]
+fn endpoint_response(
+	state: &mut testing::OffchainState,
+	uri: &str,
+	readings: &[(u32, &str, &str)],
+) {
+	let readings: Vec<_> = readings
+		.iter()
+		.map(|(id, type_, value)| {
+			format!(
+				r#"{{"id":{id},"type_":"{type_}","geolocation":{{"lat":1,"lon":2}},"value":"{value}","timestamp":3}}"#
+			)
+		})
+		.collect();
+	state.expect_request(testing::PendingRequest {
+		method: "GET".into(),
+		uri: uri.into(),
+		headers: vec![
+			("Accept".into(), "application/json".into()),
+			("Accept-Encoding".into(), "gzip".into()),
+		],
+		response: Some(format!("[{}]", readings.join(",")).into_bytes()),
+		sent: true,
+		..Default::default()
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_reads_each_routed_type_from_its_endpoint() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	t.execute_with(|| {
+		for (type_, endpoint) in [
+			(SensorType::Temperature, "https://temperature.example/readings"),
+			(SensorType::Humidity, "https://humidity.example/readings"),
+		] {
+			assert_ok!(PriceOracleOcwExample::set_type_endpoint(
+				RuntimeOrigin::root(),
+				type_,
+				Some(endpoint.as_bytes().to_vec())
+			));
+		}
+		// The bulk endpoint still serves a temperature, which is ignored.
+		endpoint_response(
+			&mut state.write(),
+			SENSORS_API_URL,
+			&[(1, "Temperature", "99"), (2, "Pressure", "101325")],
+		);
+		for (type_, _) in TypeEndpoints::<Test>::iter() {
+			match type_ {
+				SensorType::Temperature => endpoint_response(
+					&mut state.write(),
+					"https://temperature.example/readings",
+					&[(1, "Temperature", "30"), (9, "Pressure", "5")],
+				),
+				_ => endpoint_response(
+					&mut state.write(),
+					"https://humidity.example/readings",
+					&[(1, "Humidity", "60")],
+				),
+			}
+		}
+
+		let fetched = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+
+		let mut measured: Vec<_> = fetched
+			.iter()
+			.filter(|sensor| !sensor.derived)
+			.map(|sensor| (sensor.id, sensor.type_.clone(), sensor.value))
+			.collect();
+		measured.sort_by_key(|(id, type_, _)| (*id, type_.encode()));
+		let mut expected = vec![
+			(1, SensorType::Temperature, SensorValue::Number(30)),
+			(1, SensorType::Humidity, SensorValue::Percent(Percent::from_percent(60))),
+			(2, SensorType::Pressure, SensorValue::Number(101325)),
+		];
+		expected.sort_by_key(|(id, type_, _)| (*id, type_.encode()));
+		assert_eq!(measured, expected);
+		// Derived from the temperature and humidity of different endpoints.
+		let heat_index: Vec<_> =
+			fetched.iter().filter(|sensor| sensor.type_ == SensorType::HeatIndex).collect();
+		assert_eq!(heat_index.len(), 1);
+		assert_eq!(heat_index[0].id, 1);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_leaves_out_a_type_whose_endpoint_fails() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	t.execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::set_type_endpoint(
+			RuntimeOrigin::root(),
+			SensorType::Temperature,
+			Some(b"https://temperature.example/readings".to_vec())
+		));
+		endpoint_response(
+			&mut state.write(),
+			SENSORS_API_URL,
+			&[(1, "Temperature", "99"), (2, "Pressure", "101325")],
+		);
+		state.write().expect_request(testing::PendingRequest {
+			method: "GET".into(),
+			uri: "https://temperature.example/readings".into(),
+			headers: vec![
+				("Accept".into(), "application/json".into()),
+				("Accept-Encoding".into(), "gzip".into()),
+			],
+			response: Some(b"[{".to_vec()),
+			sent: true,
+			..Default::default()
+		});
+
+		let fetched = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+
+		assert_eq!(
+			fetched
+				.iter()
+				.map(|sensor| (sensor.id, sensor.type_.clone()))
+				.collect::<Vec<_>>(),
+			vec![(2, SensorType::Pressure)]
+		);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_keeps_the_type_endpoints_when_the_default_one_fails() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	t.execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::set_type_endpoint(
+			RuntimeOrigin::root(),
+			SensorType::Temperature,
+			Some(b"https://temperature.example/readings".to_vec())
+		));
+		let malformed = |state: &mut testing::OffchainState, uri: &str| {
+			state.expect_request(testing::PendingRequest {
+				method: "GET".into(),
+				uri: uri.into(),
+				headers: vec![
+					("Accept".into(), "application/json".into()),
+					("Accept-Encoding".into(), "gzip".into()),
+				],
+				response: Some(b"[{".to_vec()),
+				sent: true,
+				..Default::default()
+			})
+		};
+		malformed(&mut state.write(), SENSORS_API_URL);
+		endpoint_response(
+			&mut state.write(),
+			"https://temperature.example/readings",
+			&[(1, "Temperature", "30")],
+		);
+
+		let fetched = PriceOracleOcwExample::fetch_sensors_data().unwrap();
+		assert_eq!(
+			fetched
+				.iter()
+				.map(|sensor| (sensor.id, sensor.type_.clone()))
+				.collect::<Vec<_>>(),
+			vec![(1, SensorType::Temperature)]
+		);
+
+		// With every endpoint failing, so does the fetch.
+		malformed(&mut state.write(), SENSORS_API_URL);
+		malformed(&mut state.write(), "https://temperature.example/readings");
+		assert!(PriceOracleOcwExample::fetch_sensors_data().is_err());
+	});
+}
+
+#[test]
+fn set_type_endpoint_requires_root_and_an_http_url() {
+	new_test_ext().execute_with(|| {
+		let set = |url: &[u8]| {
+			PriceOracleOcwExample::set_type_endpoint(
+				RuntimeOrigin::root(),
+				SensorType::Temperature,
+				Some(url.to_vec()),
+			)
+		};
+		assert_noop!(
+			PriceOracleOcwExample::set_type_endpoint(
+				RuntimeOrigin::signed(test_pub()),
+				SensorType::Temperature,
+				Some(b"https://temperature.example".to_vec())
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(set(b"ftp://temperature.example/readings"), Error::<Test>::InvalidEndpoint);
+		assert_noop!(set(&[0xff, 0xfe]), Error::<Test>::InvalidEndpoint);
+		let oversized = format!("https://temperature.example/{}", "a".repeat(256));
+		assert_noop!(set(oversized.as_bytes()), Error::<Test>::InvalidEndpoint);
+
+		assert_ok!(set(b"https://temperature.example/readings"));
+		System::assert_last_event(
+			Event::TypeEndpointSet {
+				type_: SensorType::Temperature,
+				endpoint: Some(
+					b"https://temperature.example/readings".to_vec().try_into().unwrap(),
+				),
+			}
+			.into(),
+		);
+		assert_ok!(PriceOracleOcwExample::set_type_endpoint(
+			RuntimeOrigin::root(),
+			SensorType::Temperature,
+			None
+		));
+		assert_eq!(PriceOracleOcwExample::type_endpoint(SensorType::Temperature), None);
+	});
+}
+
+#[test]
+fn is_http_url_needs_an_http_scheme_and_a_host() {
+	for url in [
+		"https://sensors.example",
+		"http://sensors.example/api/v1/sensors?region=eu#latest",
+		"HTTPS://sensors.example:8443/api",
+		"http://192.168.1.10:8080/readings",
+		"http://[::1]:8080/readings",
+	] {
+		assert!(is_http_url(url), "{}", url);
+	}
+	for url in [
+		"ftp://sensors.example/readings",
+		"sensors.example/readings",
+		"https://",
+		"https:///readings",
+		"https://sensors example/readings",
+		"https://user@sensors.example",
+		"https://sensors.example:port",
+		"https://sensors.example:65536",
+		"https://sensors.example:+80",
+		"https://[]:8080",
+		"https://sensörs.example",
+	] {
+		assert!(!is_http_url(url), "{}", url);
+	}
+}
+
+#[test]
+fn storage_footprint_sums_latest_history_and_metadata() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::storage_footprint(1), 0);
+
+		let info = SensorInfo { unit: Some(Unit::Pascal), ..Default::default() };
+		assert_ok!(PriceOracleOcwExample::set_sensor_metadata(
+			RuntimeOrigin::root(),
+			1,
+			info.clone()
+		));
+		add_pressure_history(3);
+		// Another sensor doesn't count.
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			2,
+			SensorType::Pressure,
+			SensorValue::Number(1),
+		));
+
+		let reading = sensor(1, SensorType::Pressure, SensorValue::Number(0)).encode().len();
+		// The latest reading, then the 3 readings of the history after their compact length.
+		let expected = reading + 1 + 3 * reading + info.encode().len();
+		assert_eq!(PriceOracleOcwExample::storage_footprint(1), expected as u64);
+	});
+}
+
+fn digital_states() -> Vec<bool> {
+	PriceOracleOcwExample::sensor_history(1, SensorType::Digital)
+		.into_iter()
+		.map(|reading| reading.value == SensorValue::Bool(true))
+		.collect()
+}
+
+#[test]
+fn debounce_only_stores_stable_state_changes() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::set_debounce(RuntimeOrigin::root(), 1, 3));
+
+		let chatter = [true, false, true, false, false, true, false, false, false, true];
+		let stored: Vec<bool> = chatter
+			.into_iter()
+			.enumerate()
+			.map(|(timestamp, state)| {
+				let mut reading = sensor(1, SensorType::Digital, SensorValue::Bool(state));
+				reading.timestamp = timestamp as u64;
+				PriceOracleOcwExample::add_sensor_data(reading)
+			})
+			.collect();
+
+		// The first state is stored as is, the flips back and forth are suppressed and only the
+		// third `false` in a row changes the state. Readings of the stored state are kept.
+		assert_eq!(digital_states(), vec![true, true, true, false]);
+		assert_eq!(stored, [true, false, true, false, false, true, false, false, true, false]);
+		assert_eq!(
+			PriceOracleOcwExample::latest_reading(1, SensorType::Digital, true)
+				.unwrap()
+				.timestamp,
+			8
+		);
+		assert_eq!(DigitalDebounce::<Test>::get(1), Some((true, 1)));
+	});
+}
+
+#[test]
+fn readings_held_back_by_the_debounce_are_not_counted_as_accepted() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		assert_ok!(PriceOracleOcwExample::set_debounce(RuntimeOrigin::root(), 1, 2));
+		submit_batch(vec![sensor(1, SensorType::Digital, SensorValue::Bool(true))]);
+		let remaining = PriceOracleOcwExample::remaining_writes();
+
+		System::reset_events();
+		let mut held = sensor(1, SensorType::Digital, SensorValue::Bool(false));
+		held.timestamp = 2;
+		let batch = vec![held, sensor(2, SensorType::Temperature, SensorValue::Number(21))];
+		let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+		let result = PriceOracleOcwExample::update_sensors_data(
+			RuntimeOrigin::signed(test_pub()),
+			batch,
+			checksum,
+		)
+		.unwrap();
+
+		assert_eq!(
+			result.actual_weight,
+			Some(PriceOracleOcwExample::update_sensors_data_weight(2, 1, 0, 1))
+		);
+		assert_eq!(
+			PriceOracleOcwExample::last_batch_result(test_pub()),
+			Some(BatchResult { accepted: 1, rejected: 0 })
+		);
+		assert_eq!(PriceOracleOcwExample::remaining_writes(), remaining - 1);
+		assert!(System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::PriceOracleOcwExample(Event::SensorsDataUpdated { count: 1, .. })
+		)));
+	});
+}
+
+#[test]
+fn clearing_the_debounce_stores_every_state_change() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::set_debounce(RuntimeOrigin::root(), 1, 3));
+		assert_ok!(PriceOracleOcwExample::set_debounce(RuntimeOrigin::root(), 1, 0));
+		assert_eq!(PriceOracleOcwExample::debounce_count(1), None);
+
+		for (timestamp, state) in [true, false, true].into_iter().enumerate() {
+			let mut reading = sensor(1, SensorType::Digital, SensorValue::Bool(state));
+			reading.timestamp = timestamp as u64;
+			PriceOracleOcwExample::add_sensor_data(reading);
+		}
+		assert_eq!(digital_states(), vec![true, false, true]);
+	});
+}
+
+#[test]
+fn set_debounce_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PriceOracleOcwExample::set_debounce(RuntimeOrigin::signed(account(1)), 1, 3),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn get_sensors_data_stops_without_authorities() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let keystore = MemoryKeystore::new();
+	keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		// No HTTP expectation is registered, so any request would panic.
+		assert!(matches!(
+			PriceOracleOcwExample::get_sensors_data(),
+			Err(Error::<Test>::NoAuthoritiesConfigured)
+		));
+	});
+	assert!(state.read().requests.is_empty());
+}
+
+#[cfg(feature = "ocw")]
+/// Expect a request for sensor `id` alone, answered with a pressure reading of `id`, or with a
+/// malformed body if it's not `ok`.
+fn sensor_by_id_response(state: &mut testing::OffchainState, id: u32, ok: bool) {
+	let mut body = format!(
+		r#"[{{"id":{id},"type_":"Pressure","geolocation":{{"lat":1,"lon":2}},"value":"{id}","timestamp":3}}]"#
+	);
+	if !ok {
+		body.truncate(body.len() / 2);
+	}
+	state.expect_request(testing::PendingRequest {
+		method: "GET".into(),
+		uri: format!("https://sensors-api.vercel.app/api/v1/sensors/{id}"),
+		headers: vec![
+			("Accept".into(), "application/json".into()),
+			("Accept-Encoding".into(), "gzip".into()),
+		],
+		response: Some(body.into_bytes()),
+		sent: true,
+		..Default::default()
+	});
+}
+
+#[cfg(feature = "ocw")]
+fn fetch_sensors_by_id(ids: Vec<u32>, failing: &[u32]) -> Result<Vec<SensorData>, Error<Test>> {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let mut t = sp_io::TestExternalities::default();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+
+	for &id in &ids {
+		sensor_by_id_response(&mut state.write(), id, !failing.contains(&id));
+	}
+
+	let fetched = t.execute_with(|| {
+		let set = |key, value: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, key, value)
+		};
+		set(SENSOR_IDS_KEY, &ids.encode());
+		set(MAX_CONCURRENT_REQUESTS_KEY, &2u32.encode());
+
+		PriceOracleOcwExample::fetch_sensors_data()
+	});
+	// Every expected request was sent and its response read.
+	assert!(state.read().requests.is_empty());
+	fetched
+}
+
+#[cfg(feature = "ocw")]
+fn pressure_readings(ids: &[u32]) -> Vec<SensorData> {
+	ids.iter()
+		.map(|&id| SensorData {
+			geolocation: Geolocation { lat: 1, lon: 2 },
+			timestamp: 3,
+			..sensor(id, SensorType::Pressure, SensorValue::Number(id))
+		})
+		.collect()
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_awaits_the_sensors_in_concurrent_batches() {
+	// Three batches of at most two concurrent requests.
+	assert_eq!(
+		fetch_sensors_by_id(vec![1, 2, 3, 4, 5], &[]).unwrap(),
+		pressure_readings(&[1, 2, 3, 4, 5])
+	);
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_skips_the_sensors_that_failed() {
+	assert_eq!(fetch_sensors_by_id(vec![1, 2, 3], &[2]).unwrap(), pressure_readings(&[1, 3]));
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn fetch_sensors_data_fails_when_every_sensor_failed() {
+	assert!(matches!(
+		fetch_sensors_by_id(vec![1, 2], &[1, 2]),
+		Err(Error::<Test>::HttpFetchFailed)
+	));
+}
+
+#[test]
+fn value_histogram_counts_the_readings_per_bucket() {
+	new_test_ext().execute_with(|| {
+		for (timestamp, value) in [5, 10, 14, 19, 20, 25, 29, 30, 31].into_iter().enumerate() {
+			PriceOracleOcwExample::add_sensor_data(SensorData {
+				timestamp: timestamp as u64,
+				..sensor(1, SensorType::Temperature, SensorValue::Number(value))
+			});
+		}
+
+		// An edge value falls in the bucket it starts, and values below the first edge or at or
+		// above the last one in none.
+		assert_eq!(
+			PriceOracleOcwExample::value_histogram(1, SensorType::Temperature, &[10, 20, 30]),
+			Some(vec![3, 3])
+		);
+		assert_eq!(
+			PriceOracleOcwExample::value_histogram(1, SensorType::Temperature, &[0, 15, 40]),
+			Some(vec![3, 6])
+		);
+		assert_eq!(
+			PriceOracleOcwExample::value_histogram(2, SensorType::Temperature, &[0, 15]),
+			Some(vec![0])
+		);
+	});
+}
+
+#[test]
+fn value_histogram_skips_non_numeric_readings() {
+	new_test_ext().execute_with(|| {
+		PriceOracleOcwExample::add_sensor_data(sensor(
+			1,
+			SensorType::Digital,
+			SensorValue::Bool(true),
+		));
+
+		assert_eq!(
+			PriceOracleOcwExample::value_histogram(1, SensorType::Digital, &[0, 2]),
+			Some(vec![0])
+		);
+	});
+}
+
+#[test]
+fn value_histogram_rejects_unsorted_or_overlapping_edges() {
+	new_test_ext().execute_with(|| {
+		for edges in [&[][..], &[10], &[20, 10], &[10, 10, 20], &[0, 20, 10]] {
+			assert_eq!(
+				PriceOracleOcwExample::value_histogram(1, SensorType::Temperature, edges),
+				None,
+				"{edges:?}"
+			);
+		}
+	});
+}
+
+fn signed_by(
+	seeds: &[&str],
+	reading: &SensorData,
+) -> BoundedVec<(sp_core::sr25519::Public, Signature), <Test as Config>::MaxAuthorities> {
+	let payload = PriceOracleOcwExample::signed_reading_payload(reading);
+	seeds
+		.iter()
+		.map(|seed| {
+			let pair = <sp_core::sr25519::Pair as sp_core::Pair>::from_string(seed, None).unwrap();
+			(sp_core::Pair::public(&pair), sp_core::Pair::sign(&pair, &payload))
+		})
+		.collect::<Vec<_>>()
+		.try_into()
+		.unwrap()
+}
+
+fn public_of(seed: &str) -> sp_core::sr25519::Public {
+	let pair = <sp_core::sr25519::Pair as sp_core::Pair>::from_string(seed, None).unwrap();
+	sp_core::Pair::public(&pair)
+}
+
+/// `//Alice`, `//Bob` and `//Charlie` are authorities and two of them must confirm a reading.
+fn new_signing_test_ext() -> sp_io::TestExternalities {
+	let mut t = new_test_ext();
+	t.execute_with(|| {
+		for seed in ["//Alice", "//Bob", "//Charlie"] {
+			assert_ok!(PriceOracleOcwExample::add_authority(
+				RuntimeOrigin::root(),
+				public_of(seed)
+			));
+		}
+		assert_ok!(PriceOracleOcwExample::set_confirmation_threshold(RuntimeOrigin::root(), 2));
+	});
+	t
+}
+
+#[test]
+fn submit_with_signatures_stores_a_reading_confirmed_by_the_threshold() {
+	new_signing_test_ext().execute_with(|| {
+		let reading = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+
+		assert_ok!(PriceOracleOcwExample::submit_with_signatures(
+			RuntimeOrigin::signed(account(9)),
+			reading.clone(),
+			signed_by(&["//Alice", "//Charlie"], &reading)
+		));
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Temperature), Some(reading));
+	});
+}
+
+#[test]
+fn submit_with_signatures_is_weighed_per_signature() {
+	use frame_support::dispatch::GetDispatchInfo;
+
+	let weight = PriceOracleOcwExample::submit_with_signatures_weight;
+	let db = <Test as frame_system::Config>::DbWeight::get();
+	let per_signature = SIGNATURE_VERIFICATION_WEIGHT.saturating_add(db.reads(1));
+
+	assert_eq!(weight(3), weight(2).saturating_add(per_signature));
+	assert!(weight(0).all_gte(PriceOracleOcwExample::update_sensors_data_weight(1, 1, 0, 0)));
+
+	let reading = sensor(1, SensorType::Digital, SensorValue::Bool(true));
+	let signatures = signed_by(&["//Alice", "//Bob"], &reading);
+	let call = crate::Call::<Test>::submit_with_signatures { reading, signatures };
+	assert_eq!(call.get_dispatch_info().weight, weight(2));
+}
+
+#[test]
+fn submit_with_signatures_counts_against_the_block_writes() {
+	new_signing_test_ext().execute_with(|| {
+		MaxWritesPerBlock::set(1);
+		let first = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+		let second = sensor(2, SensorType::Temperature, SensorValue::Number(22));
+
+		assert_ok!(PriceOracleOcwExample::submit_with_signatures(
+			RuntimeOrigin::signed(account(9)),
+			first.clone(),
+			signed_by(&["//Alice", "//Bob"], &first)
+		));
+		assert_eq!(PriceOracleOcwExample::remaining_writes(), 0);
+		assert_noop!(
+			PriceOracleOcwExample::submit_with_signatures(
+				RuntimeOrigin::signed(account(9)),
+				second.clone(),
+				signed_by(&["//Alice", "//Bob"], &second)
+			),
+			Error::<Test>::BlockWritesExhausted
+		);
+	});
+}
+
+#[test]
+fn submit_with_signatures_enforces_the_threshold() {
+	new_signing_test_ext().execute_with(|| {
+		let reading = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+
+		assert_noop!(
+			PriceOracleOcwExample::submit_with_signatures(
+				RuntimeOrigin::signed(account(9)),
+				reading.clone(),
+				signed_by(&["//Alice"], &reading)
+			),
+			Error::<Test>::NotEnoughSignatures
+		);
+	});
+}
+
+#[test]
+fn submit_with_signatures_rejects_invalid_and_duplicate_signatures() {
+	new_signing_test_ext().execute_with(|| {
+		let reading = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+		let submit = |signatures| {
+			PriceOracleOcwExample::submit_with_signatures(
+				RuntimeOrigin::signed(account(9)),
+				reading.clone(),
+				signatures,
+			)
+		};
+
+		// Signatures over another reading.
+		let other = sensor(1, SensorType::Temperature, SensorValue::Number(22));
+		assert_noop!(
+			submit(signed_by(&["//Alice", "//Bob"], &other)),
+			Error::<Test>::InvalidSignature
+		);
+		// A valid signature under another authority's key.
+		let mut swapped = signed_by(&["//Alice", "//Bob"], &reading);
+		swapped[1].0 = public_of("//Charlie");
+		assert_noop!(submit(swapped), Error::<Test>::InvalidSignature);
+		// The same authority twice.
+		assert_noop!(
+			submit(signed_by(&["//Alice", "//Alice"], &reading)),
+			Error::<Test>::DuplicateSignature
+		);
+		// A signer that isn't an authority.
+		assert_noop!(
+			submit(signed_by(&["//Alice", "//Dave"], &reading)),
+			Error::<Test>::NotAuthority
+		);
+	});
+}
+
+#[test]
+fn latest_by_type_tracks_the_newest_reading_of_each_type() {
+	new_test_ext().execute_with(|| {
+		let at = |id, timestamp, value| SensorData {
+			timestamp,
+			..sensor(id, SensorType::Temperature, SensorValue::Number(value))
+		};
+		assert_eq!(PriceOracleOcwExample::latest_by_type(SensorType::Temperature), None);
+
+		PriceOracleOcwExample::add_sensor_data(at(1, 10, 20));
+		PriceOracleOcwExample::add_sensor_data(at(2, 30, 21));
+		assert_eq!(
+			PriceOracleOcwExample::latest_by_type(SensorType::Temperature),
+			Some(at(2, 30, 21))
+		);
+
+		// An older reading of another sensor doesn't replace it, and neither does a tie.
+		PriceOracleOcwExample::add_sensor_data(at(3, 20, 22));
+		PriceOracleOcwExample::add_sensor_data(at(1, 30, 23));
+		assert_eq!(
+			PriceOracleOcwExample::latest_by_type(SensorType::Temperature),
+			Some(at(2, 30, 21))
+		);
+
+		PriceOracleOcwExample::add_sensor_data(at(3, 31, 24));
+		assert_eq!(
+			PriceOracleOcwExample::latest_by_type(SensorType::Temperature),
+			Some(at(3, 31, 24))
+		);
+		// Other types are cached separately.
+		assert_eq!(PriceOracleOcwExample::latest_by_type(SensorType::Pressure), None);
+	});
+}
+
+#[test]
+fn calls_reject_adversarial_inputs_without_panicking() {
+	new_signing_test_ext().execute_with(|| {
+		let root = || RuntimeOrigin::root();
+		let max = <<Test as Config>::MaxAuthorities as Get<u32>>::get();
+		let accounts = |n: u32| (0..n).map(|i| account(100 + i as u8)).collect::<Vec<_>>();
+
+		// Empty lists are no-ops, too many authorities or duplicates fail without a trace.
+		assert_ok!(PriceOracleOcwExample::add_authorities(root(), vec![]));
+		assert_ok!(PriceOracleOcwExample::remove_authorities(root(), vec![]));
+		assert_noop!(
+			PriceOracleOcwExample::add_authorities(root(), accounts(max)),
+			Error::<Test>::TooManyAuthorities
+		);
+		assert_noop!(
+			PriceOracleOcwExample::add_authorities(root(), vec![account(9), account(9)]),
+			Error::<Test>::AlreadyAuthority
+		);
+		assert_ok!(PriceOracleOcwExample::remove_authorities(
+			root(),
+			vec![public_of("//Charlie"), public_of("//Charlie"), account(9)]
+		));
+		assert_eq!(PriceOracleOcwExample::authorities().len(), 2);
+		assert_noop!(
+			PriceOracleOcwExample::remove_authority(root(), account(9)),
+			Error::<Test>::NotAuthority
+		);
+		for threshold in [0, 3, u32::MAX] {
+			assert_noop!(
+				PriceOracleOcwExample::set_confirmation_threshold(root(), threshold),
+				Error::<Test>::InvalidConfirmationThreshold
+			);
+		}
+
+		// Extreme values are stored or rejected like any other.
+		assert_ok!(PriceOracleOcwExample::set_debounce(root(), SELF_TEST_SENSOR_ID - 1, u32::MAX));
+		assert_ok!(PriceOracleOcwExample::set_alert_threshold(
+			root(),
+			SELF_TEST_SENSOR_ID - 1,
+			SensorType::Digital,
+			Some(u32::MAX),
+			Some(0)
+		));
+		assert_ok!(PriceOracleOcwExample::set_sensor_metadata(
+			root(),
+			SELF_TEST_SENSOR_ID - 1,
+			SensorInfo { unit: Some(Unit::Hectopascal), ..Default::default() }
+		));
+
+		let alice = public_of("//Alice");
+		let submit = |batch: Vec<SensorData>| {
+			let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+			PriceOracleOcwExample::update_sensors_data(
+				RuntimeOrigin::signed(alice),
+				batch,
+				checksum,
+			)
+		};
+		assert_ok!(submit(vec![]));
+		assert_noop!(
+			PriceOracleOcwExample::update_sensors_data(
+				RuntimeOrigin::signed(alice),
+				vec![],
+				[0; 32]
+			),
+			Error::<Test>::ChecksumMismatch
+		);
+		let extreme =
+			|id, type_, value| SensorData { timestamp: u64::MAX, ..sensor(id, type_, value) };
+		let batch = vec![
+			extreme(SELF_TEST_SENSOR_ID - 1, SensorType::Pressure, SensorValue::Number(u32::MAX)),
+			extreme(SELF_TEST_SENSOR_ID - 1, SensorType::Pressure, SensorValue::Number(u32::MAX)),
+			extreme(SELF_TEST_SENSOR_ID - 1, SensorType::Digital, SensorValue::Number(u32::MAX)),
+			extreme(SELF_TEST_SENSOR_ID, SensorType::Digital, SensorValue::Bool(true)),
+			sensor(1, SensorType::Temperature, SensorValue::Number(0)),
+		];
+		assert_ok!(submit(batch));
+		assert_eq!(
+			PriceOracleOcwExample::sensors(SELF_TEST_SENSOR_ID - 1, SensorType::Pressure)
+				.map(|s| s.value),
+			Some(SensorValue::Number(u32::MAX))
+		);
+		// Many more readings of a sensor than its history holds.
+		let long = (0..=u64::from(<<Test as Config>::MaxHistory as Get<u32>>::get()) * 4)
+			.map(|timestamp| SensorData {
+				timestamp,
+				..sensor(7, SensorType::Humidity, SensorValue::Percent(Percent::from_percent(50)))
+			})
+			.collect();
+		assert_ok!(submit(long));
+
+		let reading = sensor(2, SensorType::Digital, SensorValue::Bool(true));
+		let signatures = signed_by(&["//Alice"; 2], &reading);
+		assert_noop!(
+			PriceOracleOcwExample::submit_with_signatures(
+				RuntimeOrigin::signed(alice),
+				reading.clone(),
+				signatures
+			),
+			Error::<Test>::DuplicateSignature
+		);
+		assert_noop!(
+			PriceOracleOcwExample::submit_with_signatures(
+				RuntimeOrigin::signed(alice),
+				reading,
+				BoundedVec::default()
+			),
+			Error::<Test>::NotEnoughSignatures
+		);
+	});
+}
+
+#[test]
+fn reject_reason_codes_are_stable() {
+	let reasons = [
+		(RejectReason::OutOfScope, 0),
+		(RejectReason::OutOfRange, 1),
+		(RejectReason::StaleTimestamp, 2),
+		(RejectReason::ValueTypeMismatch, 3),
+		(RejectReason::Unregistered, 4),
+		(RejectReason::Duplicate, 5),
+	];
+	for (reason, code) in reasons {
+		assert_eq!(reason.code(), code);
+		assert_eq!(reason.encode(), vec![code]);
+		assert_eq!(RejectReason::decode(&mut &[code][..]), Ok(reason));
+	}
+	assert!(RejectReason::decode(&mut &[6u8][..]).is_err());
+}
+
+#[test]
+fn propose_remove_authority_records_a_proposal_for_a_silent_authority() {
+	new_test_ext().execute_with(|| {
+		for seed in [1, 2, 3, 4] {
+			assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(seed)));
+		}
+		let propose = |proposer, candidate| {
+			PriceOracleOcwExample::propose_remove_authority(
+				RuntimeOrigin::signed(account(proposer)),
+				account(candidate),
+			)
+		};
+
+		// Added at block 1, so silent for more than 20 blocks from block 22 on.
+		System::set_block_number(21);
+		assert_noop!(propose(1, 2), Error::<Test>::AuthorityNotSilent);
+
+		// Authority 3 submits a batch at block 21.
+		assert_ok!(PriceOracleOcwExample::update_sensors_data(
+			RuntimeOrigin::signed(account(3)),
+			vec![],
+			PriceOracleOcwExample::readings_checksum(&[])
+		));
+		System::set_block_number(22);
+		assert_noop!(propose(1, 3), Error::<Test>::AuthorityNotSilent);
+		assert_noop!(propose(9, 2), Error::<Test>::NotAuthority);
+		assert_noop!(propose(1, 9), Error::<Test>::NotAuthority);
+
+		assert_ok!(propose(1, 2));
+		System::assert_last_event(
+			Event::RemovalProposed { candidate: account(2), proposer: account(1) }.into(),
+		);
+		assert_eq!(PriceOracleOcwExample::removal_proposal(account(2)), Some(account(1)));
+		assert_eq!(PriceOracleOcwExample::pending_proposal(account(1)), Some(account(2)));
+		assert_noop!(propose(3, 2), Error::<Test>::RemovalAlreadyProposed);
+		// Authority 4 is silent too, but authority 1 already has a proposal pending.
+		assert_noop!(propose(1, 4), Error::<Test>::ProposalPending);
+		assert_ok!(propose(3, 4));
+		// A proposal removes nobody by itself.
+		assert!(PriceOracleOcwExample::is_authority(&account(2)));
+	});
+}
+
+#[test]
+fn removal_proposals_are_weighed_by_their_reads_and_writes() {
+	use frame_support::dispatch::GetDispatchInfo;
+
+	let db = <Test as frame_system::Config>::DbWeight::get();
+	assert_eq!(
+		crate::Call::<Test>::propose_remove_authority { candidate: account(2) }
+			.get_dispatch_info()
+			.weight,
+		db.reads_writes(4, 2)
+	);
+	assert_eq!(
+		crate::Call::<Test>::ratify_remove_authority { candidate: account(2) }
+			.get_dispatch_info()
+			.weight,
+		db.reads_writes(3, 7)
+	);
+}
+
+#[test]
+fn ratify_remove_authority_removes_the_proposed_authority() {
+	new_test_ext().execute_with(|| {
+		for seed in [1, 2] {
+			assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(seed)));
+		}
+		System::set_block_number(30);
+		assert_noop!(
+			PriceOracleOcwExample::ratify_remove_authority(RuntimeOrigin::root(), account(2)),
+			Error::<Test>::NoRemovalProposal
+		);
+		assert_ok!(PriceOracleOcwExample::propose_remove_authority(
+			RuntimeOrigin::signed(account(1)),
+			account(2)
+		));
+
+		assert_noop!(
+			PriceOracleOcwExample::ratify_remove_authority(
+				RuntimeOrigin::signed(account(1)),
+				account(2)
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(PriceOracleOcwExample::ratify_remove_authority(
+			RuntimeOrigin::root(),
+			account(2)
+		));
+		System::assert_last_event(Event::AuthorityRemoved { authority: account(2) }.into());
+		assert_eq!(PriceOracleOcwExample::authorities().into_inner(), vec![account(1)]);
+		assert_eq!(PriceOracleOcwExample::removal_proposal(account(2)), None);
+		assert_eq!(PriceOracleOcwExample::pending_proposal(account(1)), None);
+		assert_eq!(PriceOracleOcwExample::last_submission(account(2)), None);
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn offchain_worker_proposes_the_removal_of_silent_authorities() {
+	let (offchain, _state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = MemoryKeystore::new();
+	let oracle = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		for authority in [oracle, account(2), account(3)] {
+			assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), authority));
+		}
+		System::set_block_number(10);
+		assert_ok!(PriceOracleOcwExample::update_sensors_data(
+			RuntimeOrigin::signed(account(3)),
+			vec![],
+			PriceOracleOcwExample::readings_checksum(&[])
+		));
+
+		assert_ok!(PriceOracleOcwExample::propose_silent_authorities(21));
+		assert!(pool_state.read().transactions.is_empty());
+
+		// Authority 2 has been silent since block 1, authority 3 since block 10 and the node's
+		// own authority is never proposed.
+		assert_ok!(PriceOracleOcwExample::propose_silent_authorities(22));
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(
+			tx.call,
+			RuntimeCall::PriceOracleOcwExample(crate::Call::propose_remove_authority {
+				candidate: account(2)
+			})
+		);
+
+		// Once the proposal is on chain, the node's authority has one pending and proposes
+		// nobody else, even though authority 3 is silent by now.
+		System::set_block_number(31);
+		assert_ok!(PriceOracleOcwExample::propose_remove_authority(
+			RuntimeOrigin::signed(oracle),
+			account(2)
+		));
+		assert_ok!(PriceOracleOcwExample::propose_silent_authorities(31));
+		assert!(pool_state.read().transactions.is_empty());
+	});
+}
+
+fn parse_timestamp(json: &str) -> Result<u64, serde_json::Error> {
+	de_timestamp(&mut serde_json::Deserializer::from_str(json))
+}
+
+#[test]
+fn timestamps_are_read_from_numbers_and_rfc3339_strings() {
+	assert_eq!(parse_timestamp("1680000000000").unwrap(), 1_680_000_000_000);
+	assert_eq!(parse_timestamp("1680000000").unwrap(), 1_680_000_000);
+	// Both forms give milliseconds.
+	for (json, timestamp) in [
+		(r#""2023-03-28T00:00:00Z""#, 1_679_961_600_000),
+		(r#""2023-03-28t00:00:00z""#, 1_679_961_600_000),
+		(r#""2023-03-28T02:00:00+02:00""#, 1_679_961_600_000),
+		(r#""2023-03-27T19:30:00-04:30""#, 1_679_961_600_000),
+		(r#""2023-03-28T00:00:00.5Z""#, 1_679_961_600_500),
+		(r#""2023-03-28T00:00:00.999Z""#, 1_679_961_600_999),
+		(r#""2023-03-28T00:00:00.0019Z""#, 1_679_961_600_001),
+		(r#""1970-01-01T00:00:00Z""#, 0),
+		(r#""2024-02-29T12:00:00Z""#, 1_709_208_000_000),
+	] {
+		assert_eq!(parse_timestamp(json).unwrap(), timestamp, "{json}");
+	}
+
+	for json in [
+		r#""not a date""#,
+		r#""""#,
+		r#""2023-03-28""#,
+		r#""2023-03-28T00:00:00""#,
+		r#""2023-03-28 00:00:00Z""#,
+		r#""2023-02-29T00:00:00Z""#,
+		r#""2023-13-01T00:00:00Z""#,
+		r#""2023-03-28T24:00:00Z""#,
+		r#""2023-03-28T00:00:00.Z""#,
+		r#""2023-03-28T00:00:00+2:00""#,
+		r#""1969-12-31T23:59:59Z""#,
+		r#""1970-01-01T00:00:00+00:01""#,
+		r#""2023-03-28T00:00:00Zé""#,
+		"-1",
+		"1.5",
+	] {
+		assert!(parse_timestamp(json).is_err(), "{json} was accepted");
+	}
+
+	let reading: SensorData = serde_json::from_str(
+		r#"{"id":1,"type_":"Digital","geolocation":{"lat":10,"lon":20},"value":"true","timestamp":"1970-01-01T00:00:01Z"}"#,
+	)
+	.unwrap();
+	assert_eq!(
+		reading,
+		SensorData { timestamp: 1_000, ..sensor(1, SensorType::Digital, SensorValue::Bool(true)) }
+	);
+}
+
+#[test]
+fn atomic_batches_are_stored_all_or_nothing() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let batch = |timestamp| {
+			vec![
+				SensorData { timestamp, ..sensor(1, SensorType::Pressure, SensorValue::Number(1)) },
+				SensorData {
+					timestamp,
+					..sensor(2, SensorType::Pressure, SensorValue::Bool(true))
+				},
+				SensorData { timestamp, ..sensor(3, SensorType::Pressure, SensorValue::Number(3)) },
+			]
+		};
+		let rejected = Event::ReadingRejected {
+			id: 2,
+			type_: SensorType::Pressure,
+			reason: RejectReason::ValueTypeMismatch,
+		};
+
+		// Off by default: the valid readings around the bad one are stored.
+		assert!(!PriceOracleOcwExample::atomic_batches());
+		submit_batch(batch(1));
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Pressure).map(|s| s.timestamp),
+			Some(1)
+		);
+		assert_eq!(
+			PriceOracleOcwExample::sensors(3, SensorType::Pressure).map(|s| s.timestamp),
+			Some(1)
+		);
+		System::assert_has_event(rejected.clone().into());
+
+		assert_ok!(PriceOracleOcwExample::set_atomic_batches(RuntimeOrigin::root(), true));
+		System::assert_last_event(Event::AtomicBatchesSet { atomic: true }.into());
+		System::reset_events();
+		let seq = PriceOracleOcwExample::current_event_seq();
+		submit_batch(batch(2));
+
+		// None of the batch is stored, and its bad reading is still reported.
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Pressure).map(|s| s.timestamp),
+			Some(1)
+		);
+		assert_eq!(
+			PriceOracleOcwExample::sensors(3, SensorType::Pressure).map(|s| s.timestamp),
+			Some(1)
+		);
+		assert_eq!(PriceOracleOcwExample::sensor_history(1, SensorType::Pressure).len(), 1);
+		assert_eq!(
+			PriceOracleOcwExample::rejection_counts(),
+			vec![(RejectReason::ValueTypeMismatch, 2)]
+		);
+		assert_eq!(
+			System::events().into_iter().map(|r| r.event).collect::<Vec<_>>(),
+			vec![
+				RuntimeEvent::from(rejected),
+				Event::SensorsDataUpdated { count: 0, seq: seq + 1 }.into(),
+			]
+		);
+
+		// A batch without bad readings is stored whole.
+		let mut valid = batch(3);
+		valid.remove(1);
+		submit_batch(valid);
+		assert_eq!(
+			PriceOracleOcwExample::sensors(1, SensorType::Pressure).map(|s| s.timestamp),
+			Some(3)
+		);
+		assert_eq!(
+			PriceOracleOcwExample::sensors(3, SensorType::Pressure).map(|s| s.timestamp),
+			Some(3)
+		);
+	});
+}
+
+#[test]
+fn set_atomic_batches_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PriceOracleOcwExample::set_atomic_batches(RuntimeOrigin::signed(test_pub()), true),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn high_priority_types_are_written_first_when_the_block_budget_is_hit() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		MaxWritesPerBlock::set(2);
+		assert_ok!(PriceOracleOcwExample::set_type_priority(
+			RuntimeOrigin::root(),
+			SensorType::Pressure,
+			2
+		));
+		System::assert_last_event(
+			Event::TypePrioritySet { type_: SensorType::Pressure, priority: 2 }.into(),
+		);
+		assert_ok!(PriceOracleOcwExample::set_type_priority(
+			RuntimeOrigin::root(),
+			SensorType::Humidity,
+			1
+		));
+		let batch = vec![
+			sensor(1, SensorType::Temperature, SensorValue::Number(1)),
+			sensor(2, SensorType::Humidity, SensorValue::Number(2)),
+			sensor(3, SensorType::Pressure, SensorValue::Number(3)),
+			sensor(4, SensorType::Temperature, SensorValue::Number(4)),
+		];
+		let stored = |id, type_| PriceOracleOcwExample::sensors(id, type_).is_some();
+
+		submit_batch(batch.clone());
+		assert!(stored(3, SensorType::Pressure));
+		assert!(stored(2, SensorType::Humidity));
+		assert!(!stored(1, SensorType::Temperature));
+		assert!(!stored(4, SensorType::Temperature));
+		System::assert_has_event(
+			Event::BatchPartiallyProcessed { processed: 2, skipped: 2 }.into(),
+		);
+		assert_eq!(PriceOracleOcwExample::remaining_writes(), 0);
+
+		// The budget is spent for the rest of the block.
+		submit_batch(vec![sensor(5, SensorType::Pressure, SensorValue::Number(5))]);
+		assert!(!stored(5, SensorType::Pressure));
+		System::assert_has_event(
+			Event::BatchPartiallyProcessed { processed: 0, skipped: 1 }.into(),
+		);
+
+		// And renewed in the next, where the readings of the same priority keep their order.
+		System::set_block_number(2);
+		System::reset_events();
+		submit_batch(batch);
+		assert!(stored(1, SensorType::Temperature));
+		assert!(stored(4, SensorType::Temperature));
+		assert_eq!(
+			UPDATES.with(|updates| updates.borrow().iter().map(|r| r.id).collect::<Vec<_>>()),
+			vec![3, 2, 1, 4]
+		);
+		assert!(!System::events().into_iter().any(|r| matches!(
+			r.event,
+			RuntimeEvent::PriceOracleOcwExample(Event::BatchPartiallyProcessed { .. })
+		)));
+	});
+}
+
+#[test]
+fn atomic_batches_cut_short_by_the_block_budget_are_not_stored() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		assert_ok!(PriceOracleOcwExample::set_atomic_batches(RuntimeOrigin::root(), true));
+		MaxWritesPerBlock::set(1);
+
+		submit_batch(vec![
+			sensor(1, SensorType::Pressure, SensorValue::Number(1)),
+			sensor(2, SensorType::Pressure, SensorValue::Number(2)),
+		]);
+
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Pressure), None);
+		assert_eq!(PriceOracleOcwExample::remaining_writes(), 1);
+		System::assert_has_event(
+			Event::BatchPartiallyProcessed { processed: 0, skipped: 2 }.into(),
+		);
+	});
+}
+
+#[test]
+fn set_type_priority_requires_root_and_zero_removes_it() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PriceOracleOcwExample::set_type_priority(
+				RuntimeOrigin::signed(test_pub()),
+				SensorType::Pressure,
+				1
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(PriceOracleOcwExample::set_type_priority(
+			RuntimeOrigin::root(),
+			SensorType::Pressure,
+			1
+		));
+		assert_eq!(PriceOracleOcwExample::type_priority(SensorType::Pressure), 1);
+		assert_ok!(PriceOracleOcwExample::set_type_priority(
+			RuntimeOrigin::root(),
+			SensorType::Pressure,
+			0
+		));
+		assert!(!TypePriority::<Test>::contains_key(SensorType::Pressure));
+	});
+}
+
+#[cfg(feature = "ocw")]
+#[test]
+fn ocw_config_reads_the_runtime_and_local_storage_without_fetching() {
+	let (offchain, state) = testing::TestOffchainExt::new();
+	let keystore = MemoryKeystore::new();
+	let oracle = keystore.sr25519_generate_new(crate::crypto::Public::ID, None).unwrap();
+	let mut t = new_test_ext();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(KeystoreExt::new(keystore));
+
+	t.execute_with(|| {
+		assert_eq!(PriceOracleOcwExample::ocw_config().signer, None);
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), oracle));
+		assert_eq!(
+			PriceOracleOcwExample::ocw_config(),
+			OcwConfig {
+				endpoint: SENSORS_API_URL,
+				sensor_ids: vec![],
+				timeout_ms: ocw::FETCH_TIMEOUT_MS,
+				max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+				method: HttpMethod::Get,
+				format: ResponseFormat::Json,
+				local_fixture: false,
+				sample_every: 1,
+				max_failures: 3,
+				grace_period: 5,
+				signer_strategy: SignerStrategy::Any,
+				signer: Some(oracle),
+			}
+		);
+
+		let set = |key, value: &[u8]| {
+			sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, key, value)
+		};
+		set(SENSOR_IDS_KEY, &vec![1u32, 2].encode());
+		set(MAX_CONCURRENT_REQUESTS_KEY, &2u32.encode());
+		set(
+			REQUEST_CONFIG_KEY,
+			&RequestConfig { method: HttpMethod::Post, ..Default::default() }.encode(),
+		);
+		set(RESPONSE_FORMAT_KEY, &ResponseFormat::Csv.encode());
+		set(USE_LOCAL_FIXTURE_KEY, &[]);
+		set(SAMPLE_EVERY_KEY, &4u32.encode());
+		set(PREFERRED_SIGNER_KEY, &test_pub().encode());
+
+		PriceOracleOcwExample::log_ocw_config();
+		assert_eq!(
+			PriceOracleOcwExample::ocw_config(),
+			OcwConfig {
+				endpoint: SENSORS_API_URL,
+				sensor_ids: vec![1, 2],
+				timeout_ms: ocw::FETCH_TIMEOUT_MS,
+				max_concurrent_requests: 2,
+				method: HttpMethod::Post,
+				format: ResponseFormat::Csv,
+				local_fixture: true,
+				sample_every: 4,
+				max_failures: 3,
+				grace_period: 5,
+				signer_strategy: SignerStrategy::Preferred,
+				// The preferred signer isn't in the keystore, so nobody signs.
+				signer: None,
+			}
+		);
+
+		// Resolving the signer doesn't take the turn of the next one.
+		set(SIGNER_STRATEGY_KEY, &SignerStrategy::RoundRobin.encode());
+		PriceOracleOcwExample::log_ocw_config();
+		assert_eq!(PriceOracleOcwExample::ocw_config().signer, Some(oracle));
+		assert!(
+			sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, SIGNER_INDEX_KEY).is_none()
+		);
+	});
+	assert!(state.read().requests.is_empty());
+}
+
+#[test]
+fn sensors_are_stale_past_their_expected_interval_times_the_tolerance() {
+	new_test_ext().execute_with(|| {
+		// Expected every `DefaultExpectedInterval` (10), stale after 3 intervals.
+		assert!(PriceOracleOcwExample::is_stale(1, SensorType::Temperature, 0));
+		PriceOracleOcwExample::add_sensor_data(SensorData {
+			timestamp: 100,
+			..sensor(1, SensorType::Temperature, SensorValue::Number(20))
+		});
+		assert!(!PriceOracleOcwExample::is_stale(1, SensorType::Temperature, 100));
+		assert!(!PriceOracleOcwExample::is_stale(1, SensorType::Temperature, 130));
+		assert!(PriceOracleOcwExample::is_stale(1, SensorType::Temperature, 131));
+
+		assert_ok!(PriceOracleOcwExample::set_expected_interval(
+			RuntimeOrigin::root(),
+			1,
+			SensorType::Temperature,
+			Some(100)
+		));
+		System::assert_last_event(
+			Event::ExpectedIntervalSet {
+				id: 1,
+				type_: SensorType::Temperature,
+				interval: Some(100),
+			}
+			.into(),
+		);
+		assert!(!PriceOracleOcwExample::is_stale(1, SensorType::Temperature, 400));
+		assert!(PriceOracleOcwExample::is_stale(1, SensorType::Temperature, 401));
+		// Only that sensor and type are affected.
+		PriceOracleOcwExample::add_sensor_data(SensorData {
+			timestamp: 100,
+			..sensor(1, SensorType::Humidity, SensorValue::Number(50))
+		});
+		assert!(PriceOracleOcwExample::is_stale(1, SensorType::Humidity, 131));
+
+		assert_ok!(PriceOracleOcwExample::set_expected_interval(
+			RuntimeOrigin::root(),
+			1,
+			SensorType::Temperature,
+			None
+		));
+		assert_eq!(PriceOracleOcwExample::expected_interval(1, SensorType::Temperature), None);
+		assert!(PriceOracleOcwExample::is_stale(1, SensorType::Temperature, 131));
+	});
+}
+
+#[test]
+fn set_expected_interval_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			PriceOracleOcwExample::set_expected_interval(
+				RuntimeOrigin::signed(test_pub()),
+				1,
+				SensorType::Temperature,
+				Some(100)
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn last_batch_result_counts_the_stored_and_rejected_readings() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		assert_eq!(PriceOracleOcwExample::last_batch_result(test_pub()), None);
+		let stored = sensor(1, SensorType::Pressure, SensorValue::Number(1));
+		submit_batch(vec![stored.clone()]);
+		assert_eq!(
+			PriceOracleOcwExample::last_batch_result(test_pub()),
+			Some(BatchResult { accepted: 1, rejected: 0 })
+		);
+
+		// A duplicate of the stored reading counts as neither.
+		submit_batch(vec![
+			stored,
+			sensor(2, SensorType::Pressure, SensorValue::Number(2)),
+			sensor(3, SensorType::Pressure, SensorValue::Bool(true)),
+			sensor(SELF_TEST_SENSOR_ID, SensorType::Pressure, SensorValue::Number(4)),
+			sensor(5, SensorType::Pressure, SensorValue::Number(5)),
+		]);
+		assert_eq!(
+			PriceOracleOcwExample::last_batch_result(test_pub()),
+			Some(BatchResult { accepted: 2, rejected: 2 })
+		);
+
+		// Nothing of an atomic batch with a rejected reading is stored.
+		assert_ok!(PriceOracleOcwExample::set_atomic_batches(RuntimeOrigin::root(), true));
+		submit_batch(vec![
+			sensor(6, SensorType::Pressure, SensorValue::Number(6)),
+			sensor(7, SensorType::Pressure, SensorValue::Bool(true)),
+		]);
+		assert_eq!(
+			PriceOracleOcwExample::last_batch_result(test_pub()),
+			Some(BatchResult { accepted: 0, rejected: 1 })
+		);
+	});
+}
+
+#[cfg(not(feature = "ocw"))]
+#[test]
+fn extrinsics_store_readings_without_the_offchain_worker() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let reading = sensor(1, SensorType::Temperature, SensorValue::Number(21));
+		submit_batch(vec![reading.clone()]);
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Temperature), Some(reading));
+
+		// The hook is a no-op, so it runs without any offchain extension.
+		PriceOracleOcwExample::offchain_worker(1);
+	});
+}
+
+#[test]
+fn oracle_status_summarizes_the_current_state() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			PriceOracleOcwExample::oracle_status(),
+			OracleStatus {
+				authorities: 0,
+				sensors: 0,
+				readings_stored: 0,
+				readings_rejected: 0,
+				last_submission: None,
+				ocw_enabled: cfg!(feature = "ocw"),
+			}
+		);
+
+		System::set_block_number(3);
+		for seed in [1, 2] {
+			assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), account(seed)));
+		}
+		System::set_block_number(7);
+		let batch = vec![
+			sensor(1, SensorType::Temperature, SensorValue::Number(20)),
+			sensor(1, SensorType::Humidity, SensorValue::Number(50)),
+			sensor(2, SensorType::Temperature, SensorValue::Number(21)),
+			sensor(3, SensorType::Humidity, SensorValue::Bool(true)),
+		];
+		let checksum = PriceOracleOcwExample::readings_checksum(&batch);
+		assert_ok!(PriceOracleOcwExample::update_sensors_data(
+			RuntimeOrigin::signed(account(1)),
+			batch,
+			checksum
+		));
+		PriceOracleOcwExample::add_sensor_data(SensorData {
+			timestamp: 2,
+			..sensor(1, SensorType::Temperature, SensorValue::Number(22))
+		});
+		assert_ok!(PriceOracleOcwExample::run_self_test(RuntimeOrigin::root()));
+
+		assert_eq!(
+			PriceOracleOcwExample::oracle_status(),
+			OracleStatus {
+				authorities: 2,
+				// Sensor 1 reports two types.
+				sensors: 2,
+				readings_stored: 4,
+				readings_rejected: 1,
+				last_submission: Some(7),
+				ocw_enabled: cfg!(feature = "ocw"),
+			}
+		);
+	});
+}
+
+fn weather_station(channels: Vec<(SensorType, SensorValue)>) -> MultiReading {
+	MultiReading {
+		id: 1,
+		geolocation: Geolocation { lat: 10, lon: 20 },
+		timestamp: 100,
+		channels: channels.try_into().unwrap(),
+	}
+}
+
+#[test]
+fn submit_multi_reading_stores_every_channel_with_the_shared_timestamp() {
+	use frame_support::dispatch::GetDispatchInfo;
+
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+		let reading = weather_station(vec![
+			(SensorType::Temperature, SensorValue::Number(21)),
+			(SensorType::Humidity, SensorValue::Percent(Percent::from_percent(45))),
+			(SensorType::Pressure, SensorValue::Number(1013)),
+		]);
+
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		let stored = PriceOracleOcwExample::update_sensors_data_weight(3, 3, 0, 0)
+			.saturating_add(db.writes(1));
+		assert_eq!(
+			crate::Call::<Test>::submit_multi_reading { reading: reading.clone() }
+				.get_dispatch_info()
+				.weight,
+			stored
+		);
+		let result = PriceOracleOcwExample::submit_multi_reading(
+			RuntimeOrigin::signed(test_pub()),
+			reading.clone(),
+		)
+		.unwrap();
+		assert_eq!(result.actual_weight, Some(stored));
+
+		for (type_, value) in reading.channels.iter().cloned() {
+			let stored = PriceOracleOcwExample::sensors(1, &type_).unwrap();
+			assert_eq!((stored.value, stored.timestamp), (value, 100));
+		}
+		assert_eq!(PriceOracleOcwExample::multi_reading(1), Some(reading.clone()));
+		assert_eq!(PriceOracleOcwExample::remaining_writes(), u32::MAX - 3);
+		System::assert_last_event(Event::MultiReadingAdded { id: 1, channels: 3 }.into());
+
+		// Submitted again, every channel is a duplicate.
+		System::reset_events();
+		let result =
+			PriceOracleOcwExample::submit_multi_reading(RuntimeOrigin::signed(test_pub()), reading)
+				.unwrap();
+		assert_eq!(
+			result.actual_weight,
+			Some(PriceOracleOcwExample::update_sensors_data_weight(3, 0, 0, 0))
+		);
+		assert_no_events();
+	});
+}
+
+#[test]
+fn submit_multi_reading_stores_no_channel_if_one_is_rejected() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+
+		assert_ok!(PriceOracleOcwExample::submit_multi_reading(
+			RuntimeOrigin::signed(test_pub()),
+			weather_station(vec![
+				(SensorType::Temperature, SensorValue::Number(21)),
+				(SensorType::Pressure, SensorValue::Bool(true)),
+			])
+		));
+
+		assert_eq!(PriceOracleOcwExample::sensors(1, SensorType::Temperature), None);
+		assert_eq!(PriceOracleOcwExample::multi_reading(1), None);
+		System::assert_last_event(
+			Event::ReadingRejected {
+				id: 1,
+				type_: SensorType::Pressure,
+				reason: RejectReason::ValueTypeMismatch,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn submit_multi_reading_checks_its_channels_and_the_block_budget() {
+	new_test_ext().execute_with(|| {
+		let reading = weather_station(vec![
+			(SensorType::Temperature, SensorValue::Number(21)),
+			(SensorType::Pressure, SensorValue::Number(1013)),
+		]);
+		assert_noop!(
+			PriceOracleOcwExample::submit_multi_reading(
+				RuntimeOrigin::signed(test_pub()),
+				reading.clone()
+			),
+			Error::<Test>::NotAuthority
+		);
+		assert_ok!(PriceOracleOcwExample::add_authority(RuntimeOrigin::root(), test_pub()));
+
+		for channels in [
+			vec![],
+			vec![
+				(SensorType::Temperature, SensorValue::Number(21)),
+				(SensorType::Temperature, SensorValue::Number(22)),
+			],
+		] {
+			assert_noop!(
+				PriceOracleOcwExample::submit_multi_reading(
+					RuntimeOrigin::signed(test_pub()),
+					weather_station(channels)
+				),
+				Error::<Test>::InvalidMultiReading
+			);
+		}
+
+		MaxWritesPerBlock::set(1);
+		assert_noop!(
+			PriceOracleOcwExample::submit_multi_reading(RuntimeOrigin::signed(test_pub()), reading),
+			Error::<Test>::BlockWritesExhausted
+		);
+	});
+}
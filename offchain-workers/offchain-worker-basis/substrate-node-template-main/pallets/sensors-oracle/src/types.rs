@@ -1,35 +1,61 @@
 use codec::{Decode, Encode, MaxEncodedLen};
-use frame_support::sp_runtime::RuntimeDebug;
-use frame_system::{self as system, pallet_prelude::BlockNumberFor, Config};
+use frame_support::{sp_runtime::RuntimeDebug, traits::ConstU32, BoundedVec, PalletError};
 use scale_info::TypeInfo;
-use serde::de::Error as SerdeError;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Serialize};
+use sp_std::vec::Vec;
 
-#[cfg(feature = "std")]
-use serde::Serializer;
+/// Readings and their JSON format, shared with the off-chain clients.
+pub use sensors_common::*;
 
 pub(super) type SensorIdOf = u32;
 
-#[derive(
-    Clone,
-    Copy,
-    Encode,
-    Decode,
-    Eq,
-    PartialEq,
-    RuntimeDebug,
-    MaxEncodedLen,
-    TypeInfo,
-    Serialize,
-    Deserialize,
-)]
-pub enum SensorType {
-    Humidity = 0,
-    Temperature = 1,
-    Pressure = 2,
-    Digital = 3,
+/// Human-readable name of a sensor, e.g. where the device is installed.
+pub type SensorName = BoundedVec<u8, ConstU32<32>>;
+
+/// Type and value of each channel of a `MultiReading`, at most one per type.
+pub type Channels = BoundedVec<(SensorType, SensorValue), ConstU32<8>>;
+
+/// Readings a device takes together on several channels, e.g. the temperature, humidity and
+/// pressure of a weather station, submitted with `submit_multi_reading` so that they are stored
+/// all-or-nothing.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct MultiReading {
+    pub id: u32,
+    pub geolocation: Geolocation,
+    /// Milliseconds since the Unix epoch, shared by every channel.
+    pub timestamp: u64,
+    pub channels: Channels,
 }
 
+impl MultiReading {
+    /// The `SensorData` of every channel, in the order of `channels`.
+    pub fn readings(&self) -> impl Iterator<Item = SensorData> + '_ {
+        self.channels.iter().map(|(type_, value)| SensorData {
+            id: self.id,
+            type_: type_.clone(),
+            geolocation: self.geolocation,
+            value: *value,
+            timestamp: self.timestamp,
+            derived: false,
+            quality: None,
+        })
+    }
+
+    /// Whether the reading has at least one channel, and no two of the same type.
+    pub fn has_distinct_channels(&self) -> bool {
+        !self.channels.is_empty()
+            && self
+                .channels
+                .iter()
+                .enumerate()
+                .all(|(i, (type_, _))| self.channels[..i].iter().all(|(other, _)| other != type_))
+    }
+}
+
+/// Unit a device reports its numeric readings in.
+///
+/// Stored in `SensorMetadata`, so the SCALE discriminants are pinned: new variants must be
+/// appended with a higher `codec(index)` and existing ones never renumbered or removed.
 #[derive(
     Clone,
     Copy,
@@ -43,88 +69,375 @@ pub enum SensorType {
     Serialize,
     Deserialize,
 )]
-pub struct Geolocation {
-    pub lat: u32,
-    pub lon: u32,
+pub enum Unit {
+    #[codec(index = 0)]
+    Celsius,
+    #[codec(index = 1)]
+    Fahrenheit,
+    #[codec(index = 2)]
+    Pascal,
+    #[codec(index = 3)]
+    Hectopascal,
+}
+
+impl Unit {
+    /// Whether readings of `type_` are expressed in this unit.
+    pub fn measures(&self, type_: &SensorType) -> bool {
+        match self {
+            Unit::Celsius | Unit::Fahrenheit => *type_ == SensorType::Temperature,
+            Unit::Pascal | Unit::Hectopascal => *type_ == SensorType::Pressure,
+        }
+    }
+
+    /// Convert a `SensorValue::Number` value to the canonical unit of its quantity: Celsius for
+    /// temperatures and Pascal for pressures.
+    ///
+    /// A `Number` can't be negative, so a temperature below 0°C is converted with
+    /// `to_canonical_fixed` and returned as a `SensorValue::Fixed`, e.g. 14°F as -10.000°C.
+    pub fn to_canonical(&self, value: u32) -> SensorValue {
+        match self {
+            Unit::Celsius | Unit::Pascal => SensorValue::Number(value),
+            // Rounded to the nearest degree.
+            Unit::Fahrenheit if value >= 32 => {
+                SensorValue::Number((value - 32).saturating_mul(5).saturating_add(4) / 9)
+            }
+            Unit::Fahrenheit => SensorValue::Fixed(
+                self.to_canonical_fixed(i64::from(value) * 10i64.pow(FIXED_DECIMALS)),
+            ),
+            Unit::Hectopascal => SensorValue::Number(value.saturating_mul(100)),
+        }
+    }
+
+    /// Convert a `SensorValue::Fixed` value, in thousandths, like `to_canonical`. Signed, so
+    /// temperatures below 0°C are kept.
+    pub fn to_canonical_fixed(&self, value: i64) -> i64 {
+        match self {
+            Unit::Celsius | Unit::Pascal => value,
+            // Rounded to the nearest thousandth of a degree, half away from zero.
+            Unit::Fahrenheit => {
+                let scaled = value.saturating_sub(32_000).saturating_mul(5);
+                let half = if scaled < 0 { -4 } else { 4 };
+                scaled.saturating_add(half) / 9
+            }
+            Unit::Hectopascal => value.saturating_mul(100),
+        }
+    }
+}
+
+/// Metadata registered for a sensor.
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct SensorInfo {
+    /// Unit the device reports in. Readings are converted from it to the canonical unit of their
+    /// type on ingestion; `None` means they are stored as reported.
+    pub unit: Option<Unit>,
+    /// Empty for sensors registered without a name.
+    pub name: SensorName,
+    /// Where the device is installed, if known.
+    pub geolocation: Option<Geolocation>,
+    /// Whether the readings are sensitive (e.g. occupancy or motion) and left out of the public
+    /// query helpers. Storage itself stays readable by anyone.
+    pub private: bool,
+}
+
+/// HTTP method of the request to the sensors API.
+#[derive(Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+}
+
+/// Request to the sensors API, as configured by the node operator in offchain local storage
+/// under `REQUEST_CONFIG_KEY`.
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct RequestConfig {
+    pub method: HttpMethod,
+    /// Extra `(name, value)` headers, both UTF-8. Headers that aren't valid UTF-8 are skipped.
+    pub headers: Vec<(Vec<u8>, Vec<u8>)>,
+    pub body: Option<Vec<u8>>,
+}
+
+/// Format of the sensors API response, as configured by the node operator in offchain local
+/// storage under `RESPONSE_FORMAT_KEY`.
+#[derive(Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    /// One `id,type,lat,lon,value,timestamp` record per line, see `SensorData::from_csv_record`.
+    /// A first line starting with `id,` is taken as a header and skipped.
+    Csv,
+    /// A JSON array of `CompactSensorData` positional arrays instead of objects.
+    CompactJson,
+}
+
+/// How the offchain worker picks the oracle key signing its batches when the keystore holds
+/// several, as configured by the node operator in offchain local storage under
+/// `SIGNER_STRATEGY_KEY`.
+#[derive(Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub enum SignerStrategy {
+    /// Whichever authority key the keystore lists first, so the same one signs every batch.
+    #[default]
+    Any,
+    /// Each authority key in turn, one per run of the worker, to spread the transactions and
+    /// their nonces across the accounts.
+    RoundRobin,
+    /// Only the key under `PREFERRED_SIGNER_KEY`.
+    Preferred,
+}
+
+impl ResponseFormat {
+    /// Media type asked for in the `Accept` header of the request.
+    pub fn media_type(&self) -> &'static str {
+        match self {
+            ResponseFormat::Json | ResponseFormat::CompactJson => "application/json",
+            ResponseFormat::Csv => "text/csv",
+        }
+    }
+}
+
+/// Readings of a sensor the offchain worker counted for `SAMPLE_EVERY_KEY`, kept in offchain
+/// local storage under `SAMPLE_COUNTER_PREFIX`.
+#[derive(Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct SampleCounter {
+    /// Timestamp of the last reading counted.
+    pub timestamp: u64,
+    /// Readings counted, modulo the sampling interval. The reading counted as 0 is submitted.
+    pub count: u32,
 }
 
+/// State of the offchain worker's circuit breaker, kept in offchain local storage under
+/// `BREAKER_KEY`.
+#[derive(Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug)]
+pub struct BreakerState<BlockNumber> {
+    /// Consecutive failed attempts to fetch the sensors data.
+    pub failures: u32,
+    /// First block the worker tries again at while the breaker is open.
+    pub retry_at: BlockNumber,
+}
+
+/// Outcome of the last batch an authority submitted with `update_sensors_data`, for its offchain
+/// worker to report.
 #[derive(
-    Clone,
-    Copy,
-    Encode,
-    Decode,
-    Eq,
-    PartialEq,
-    RuntimeDebug,
-    MaxEncodedLen,
-    TypeInfo,
-    Serialize,
-    Deserialize,
+    Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen,
 )]
-pub enum SensorValue {
-    Number(u32),
-    Bool(bool),
+pub struct BatchResult {
+    /// Readings stored.
+    pub accepted: u32,
+    /// Readings rejected, see `RejectReason`. Duplicates of stored readings are neither.
+    pub rejected: u32,
+}
+
+/// Health summary of the oracle, assembled by `Pallet::oracle_status`.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct OracleStatus<BlockNumber> {
+    pub authorities: u32,
+    /// Sensors with a stored reading, counting a sensor with readings of several types once.
+    pub sensors: u32,
+    /// Readings stored by `add_sensor_data` since genesis.
+    pub readings_stored: u64,
+    /// Readings rejected since the rejection counts were last reset.
+    pub readings_rejected: u64,
+    /// Last block an authority was added or submitted a batch at, see `LastSubmission`.
+    pub last_submission: Option<BlockNumber>,
+    /// Whether the pallet was compiled with its offchain worker, the `ocw` feature.
+    pub ocw_enabled: bool,
+}
+
+/// Configuration the offchain worker runs with, gathered from the runtime and offchain local
+/// storage by `Pallet::ocw_config`.
+#[derive(Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct OcwConfig<BlockNumber, Public> {
+    pub endpoint: &'static str,
+    /// Sensors fetched one by one, or none to fetch the whole list.
+    pub sensor_ids: Vec<SensorIdOf>,
+    pub timeout_ms: u64,
+    pub max_concurrent_requests: u32,
+    pub method: HttpMethod,
+    pub format: ResponseFormat,
+    /// Whether the local fixture is served instead of the API.
+    pub local_fixture: bool,
+    pub sample_every: u32,
+    /// Failed fetches in a row before the circuit breaker opens.
+    pub max_failures: u32,
+    pub grace_period: BlockNumber,
+    pub signer_strategy: SignerStrategy,
+    /// Key the batch of this cycle is signed with, see `Pallet::next_signer`, or `None` if no
+    /// key in the keystore is allowed to sign for an authority.
+    pub signer: Option<Public>,
+}
+
+/// Why the offchain worker failed to submit a batch of readings.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo, PalletError)]
+pub enum SubmitFailure {
+    /// None of the oracle keys in the keystore belongs to an authority.
+    NotAuthority,
+    /// The transaction pool refused the transaction, e.g. because it is full.
+    PoolRejected,
 }
 
+/// Why a reading of a submitted batch was dropped.
+///
+/// Each reason is SCALE encoded as its numeric code, the discriminant below, in events and in the
+/// keys of `RejectionCounts`. The codes are stable: new reasons get a new code and existing ones
+/// are never renumbered or reused.
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+#[repr(u8)]
+pub enum RejectReason {
+    /// The reading uses `SELF_TEST_SENSOR_ID`, which is reserved for `run_self_test`.
+    OutOfScope = 0,
+    /// The value is outside the range of its sensor type, e.g. a humidity above 100%, or the
+    /// geolocation is outside `Geolocation::is_valid`.
+    OutOfRange = 1,
+    /// The reading is older than the one already stored for the sensor.
+    StaleTimestamp = 2,
+    /// The value isn't of the kind its sensor type reports, see `SensorType::accepts`.
+    ValueTypeMismatch = 3,
+    /// Registration is required and the sensor has no `SensorMetadata`.
+    Unregistered = 4,
+    /// The reading is the one already stored for the sensor. Not produced by the pallet yet:
+    /// duplicates are skipped without being counted as rejected.
+    Duplicate = 5,
+}
+
+impl RejectReason {
+    /// Every reason, in the order of their code.
+    pub const ALL: &'static [RejectReason] = &[
+        RejectReason::OutOfScope,
+        RejectReason::OutOfRange,
+        RejectReason::StaleTimestamp,
+        RejectReason::ValueTypeMismatch,
+        RejectReason::Unregistered,
+        RejectReason::Duplicate,
+    ];
+
+    /// Numeric code of the reason, as it's encoded.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Which events are emitted for incoming readings.
 #[derive(
-    Clone,
-    Copy,
-    Encode,
-    Decode,
-    Eq,
-    PartialEq,
-    RuntimeDebug,
-    MaxEncodedLen,
-    TypeInfo,
-    Serialize,
-    Deserialize,
+    Clone, Copy, Default, Encode, Decode, Eq, PartialEq, RuntimeDebug, MaxEncodedLen, TypeInfo,
 )]
-pub struct SensorData {
+pub enum EventVerbosity {
+    /// An event per reading, plus one per batch.
+    #[default]
+    Full,
+    /// Only one event per batch.
+    BatchOnly,
+    /// No events for readings at all.
+    Silent,
+}
+
+impl EventVerbosity {
+    /// Whether an event is emitted for every reading.
+    pub fn per_reading(&self) -> bool {
+        *self == EventVerbosity::Full
+    }
+
+    /// Whether an event is emitted for every batch.
+    pub fn per_batch(&self) -> bool {
+        *self != EventVerbosity::Silent
+    }
+}
+
+/// A page of a sensor's history, as returned by `Pallet::sensor_history_paged`.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct HistoryPage {
+    pub readings: Vec<SensorData>,
+    /// Cursor to pass as `start` to fetch the next page, `None` on the last page.
+    pub next: Option<u32>,
+}
+
+/// What changed between two snapshots of readings, as returned by `Pallet::diff_readings`. Sensors
+/// are keyed by id and type.
+#[derive(Clone, Default, Eq, PartialEq, RuntimeDebug)]
+pub struct ReadingDiff {
+    /// Sensors only in the new snapshot.
+    pub added: Vec<(u32, SensorType)>,
+    /// Sensors only in the old snapshot.
+    pub removed: Vec<(u32, SensorType)>,
+    /// Sensors in both snapshots whose value changed.
+    pub changed: Vec<ValueChange>,
+}
+
+impl ReadingDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The value of a sensor in two snapshots of readings.
+#[derive(Clone, Eq, PartialEq, RuntimeDebug)]
+pub struct ValueChange {
     pub id: u32,
-    #[serde(deserialize_with = "de_string_to_sensor_type")]
     pub type_: SensorType,
-    #[serde(deserialize_with = "de_string_to_geolocation")]
-    pub geolocation: Geolocation,
-    #[serde(deserialize_with = "de_string_to_sensor_value")]
-    pub value: SensorValue,
-    pub timestamp: u64,
+    pub old: SensorValue,
+    pub new: SensorValue,
 }
 
-fn de_string_to_sensor_type<'de, D>(de: D) -> Result<SensorType, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: &str = Deserialize::deserialize(de)?;
-    match s {
-        "Humidity" => Ok(SensorType::Humidity),
-        "Pressure" => Ok(SensorType::Pressure),
-        "Temperature" => Ok(SensorType::Temperature),
-        "Digital" => Ok(SensorType::Digital),
-        _ => Err(SerdeError::custom("Error decoding sensor type.")),
+/// Whether `url` is an absolute `http` or `https` URL with a host, and optionally a port, such as
+/// `"https://sensors.example:8443/api/v1"`. Characters outside of printable ASCII have to be
+/// percent-encoded, and user info isn't allowed.
+pub fn is_http_url(url: &str) -> bool {
+    let rest = match url.split_once("://") {
+        Some((scheme, rest))
+            if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") =>
+        {
+            rest
+        }
+        _ => return false,
+    };
+    if !url.bytes().all(|b| b.is_ascii_graphic()) {
+        return false;
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let port = match authority.strip_prefix('[') {
+        // An IPv6 address.
+        Some(address) => match address.split_once(']') {
+            Some((host, port))
+                if !host.is_empty() && host.bytes().all(|b| b.is_ascii_hexdigit() || b == b':') =>
+            {
+                port
+            }
+            _ => return false,
+        },
+        None => {
+            let (host, port) = authority.split_at(authority.find(':').unwrap_or(authority.len()));
+            let host_char = |b: u8| b.is_ascii_alphanumeric() || b == b'-' || b == b'.';
+            if host.is_empty() || !host.bytes().all(host_char) {
+                return false;
+            }
+            port
+        }
+    };
+    match port.strip_prefix(':') {
+        Some(port) => port.bytes().all(|b| b.is_ascii_digit()) && port.parse::<u16>().is_ok(),
+        None => port.is_empty(),
     }
 }
 
-fn de_string_to_geolocation<'de, D>(de: D) -> Result<Geolocation, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: Geolocation = Deserialize::deserialize(de)?;
-    Ok(s)
-}
-
-fn de_string_to_sensor_value<'de, D>(de: D) -> Result<SensorValue, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: &str = Deserialize::deserialize(de)?;
-    match s {
-        "true" => Ok(SensorValue::Bool(true)),
-        "false" => Ok(SensorValue::Bool(false)),
-        value => match value.parse::<u32>() {
-            Ok(x) => Ok(SensorValue::Number(x)),
-            // TODO: check later if this should be zero or not
-            _ => Ok(SensorValue::Number(0)),
-        },
-        _ => Err(SerdeError::custom("Error decoding sensor value.")),
+/// Heat index in degrees Celsius for a temperature in degrees Celsius and a relative humidity in
+/// percent, following the NWS approach: Steadman's simple formula, switching to the Rothfusz
+/// regression when the result is 80°F or more. The NWS low/high humidity adjustments are left out
+/// since they need `sqrt`, which isn't available in `no_std`.
+pub fn heat_index(celsius: u32, humidity: u32) -> u32 {
+    let t = celsius as f64 * 9.0 / 5.0 + 32.0;
+    let rh = humidity as f64;
+
+    let mut hi = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    if (hi + t) / 2.0 >= 80.0 {
+        hi = -42.379 + 2.04901523 * t + 10.14333127 * rh
+            - 0.22475541 * t * rh
+            - 0.00683783 * t * t
+            - 0.05481717 * rh * rh
+            + 0.00122874 * t * t * rh
+            + 0.00085282 * t * rh * rh
+            - 0.00000199 * t * t * rh * rh;
     }
+
+    let celsius = (hi - 32.0) * 5.0 / 9.0;
+    // `f64::round` needs `std`; the cast saturates negative values to zero.
+    (celsius + 0.5) as u32
 }
@@ -308,12 +308,31 @@ impl pallet_template::Config for Runtime {
     type MaxAuthorities = ConstU32<32>;
 }
 
+parameter_types! {
+    pub const SensorsApiEndpoint: &'static str = sensors_oracle::SENSORS_API_URL;
+}
+
 /// Configure the pallet-template in pallets/template.
 impl sensors_oracle::Config for Runtime {
     type RuntimeEvent = RuntimeEvent;
     type AuthorityId = sensors_oracle::crypto::TestAuthId;
     type GracePeriod = ConstU32<1>;
+    type SilenceThreshold = ConstU32<HOURS>;
+    type DefaultApiEndpoint = SensorsApiEndpoint;
     type MaxAuthorities = ConstU32<32>;
+    type MinAuthorities = ConstU32<0>;
+    type MaxHistory = ConstU32<64>;
+    type MaxHistoryPage = ConstU32<32>;
+    // 30 days of readings timestamped in milliseconds.
+    type HistoryRetention = ConstU64<{ 30 * 24 * 60 * 60 * 1_000 }>;
+    type AutoExpire = ConstBool<false>;
+    type MaxConsecutiveFailures = ConstU32<5>;
+    // Sensors reporting every minute, stale after missing 5 readings.
+    type DefaultExpectedInterval = ConstU64<60_000>;
+    type StalenessTolerance = ConstU32<5>;
+    type MaxWritesPerBlock = ConstU32<1000>;
+    type OnSensorUpdate = ();
+    type UnixTime = Timestamp;
 }
 
 use codec::Encode;
@@ -0,0 +1,811 @@
+//! Sensor readings as ingested by the `sensors-oracle` pallet, and the JSON format the sensors
+//! API serves them in. Shared by the pallet and the off-chain clients, so that a reading one of
+//! them accepts is accepted by all of them.
+//!
+//! `no_std` without the `std` feature, for the runtime, which SCALE encodes readings in storage.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// Every arm in the custom deserializers below must be reachable; a shadowed fallback arm
+// silently changes which inputs are accepted.
+#![deny(unreachable_patterns)]
+
+extern crate alloc;
+
+use bounded_collections::{BoundedVec, ConstU32};
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use serde::de::{value::BorrowedStrDeserializer, Error as SerdeError, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A proportion between 0 and 100 percent, e.g. a relative humidity. Its SCALE decoding rejects
+/// values above 100.
+pub use sp_arithmetic::Percent;
+
+/// Name of a `SensorType::Custom` sensor type.
+pub type CustomSensorName = BoundedVec<u8, ConstU32<16>>;
+
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Hash))]
+pub enum SensorType {
+    #[codec(index = 0)]
+    Humidity,
+    #[codec(index = 1)]
+    Temperature,
+    #[codec(index = 2)]
+    Pressure,
+    #[codec(index = 3)]
+    Digital,
+    /// Derived by the offchain worker from a `Temperature` and a `Humidity` reading.
+    #[codec(index = 4)]
+    HeatIndex,
+    /// A sensor type the runtime doesn't know about, identified by its name. Lets new kinds of
+    /// devices report without a runtime upgrade.
+    #[codec(index = 5)]
+    Custom(CustomSensorName),
+}
+
+/// Serialized as its name, e.g. `"Temperature"`, the way the API reports it.
+impl Serialize for SensorType {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser_sensor_type_to_string(self, ser)
+    }
+}
+
+impl<'de> Deserialize<'de> for SensorType {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de_string_to_sensor_type(de)
+    }
+}
+
+impl SensorType {
+    /// Every sensor type known to the runtime, in the order of their codec index, i.e. all but
+    /// `Custom` ones, which can't be listed.
+    pub const ALL: &'static [SensorType] = &[
+        SensorType::Humidity,
+        SensorType::Temperature,
+        SensorType::Pressure,
+        SensorType::Digital,
+        SensorType::HeatIndex,
+    ];
+
+    /// Number of sensor types in `ALL`.
+    pub const fn count() -> usize {
+        Self::ALL.len()
+    }
+
+    /// Whether `value` is the kind of value a sensor of this type reports:
+    ///
+    /// - `Digital`: a `Bool`;
+    /// - `Temperature`, `Pressure` and `HeatIndex`: a `Number` or a `Fixed`;
+    /// - `Humidity`: a `Percent`, or a `Number` for readings that bypass deserialization;
+    /// - `Custom`: anything, since the runtime doesn't know what it measures.
+    ///
+    /// The match is exhaustive so that a new sensor type can't be added without deciding its
+    /// values.
+    pub fn accepts(&self, value: &SensorValue) -> bool {
+        match self {
+            SensorType::Digital => matches!(value, SensorValue::Bool(_)),
+            SensorType::Temperature | SensorType::Pressure | SensorType::HeatIndex => {
+                matches!(value, SensorValue::Number(_) | SensorValue::Fixed(_))
+            }
+            SensorType::Humidity => {
+                matches!(value, SensorValue::Number(_) | SensorValue::Percent(_))
+            }
+            SensorType::Custom(_) => true,
+        }
+    }
+}
+
+/// Position of a sensor in hundredths of a degree, e.g. `{"lat":4581,"lon":1523}` for 45.81°N
+/// 15.23°E. Latitudes south of the equator and longitudes west of Greenwich are negative, e.g.
+/// `{"lat":-3387,"lon":15121}` for 33.87°S 151.21°E.
+#[derive(
+    Clone,
+    Copy,
+    Encode,
+    Decode,
+    Eq,
+    PartialEq,
+    Debug,
+    MaxEncodedLen,
+    TypeInfo,
+    Serialize,
+    Deserialize,
+)]
+pub struct Geolocation {
+    pub lat: i32,
+    pub lon: i32,
+}
+
+impl Geolocation {
+    /// Latitude of the North Pole, 90°. The South Pole is at `-MAX_LAT`.
+    pub const MAX_LAT: i32 = 9_000;
+    /// Longitude of the antimeridian, 180°, east or west (`-MAX_LON`).
+    pub const MAX_LON: i32 = 18_000;
+
+    /// Whether the latitude is within ±`MAX_LAT` and the longitude within ±`MAX_LON`.
+    pub fn is_valid(&self) -> bool {
+        (-Self::MAX_LAT..=Self::MAX_LAT).contains(&self.lat)
+            && (-Self::MAX_LON..=Self::MAX_LON).contains(&self.lon)
+    }
+}
+
+/// Value of a reading.
+///
+/// Stored in `Sensors` and `SensorHistory`, so the SCALE discriminants are pinned: new variants
+/// must be appended with a higher `codec(index)` and existing ones never renumbered or removed.
+#[derive(
+    Clone,
+    Copy,
+    Encode,
+    Decode,
+    Eq,
+    PartialEq,
+    Debug,
+    MaxEncodedLen,
+    TypeInfo,
+    Serialize,
+    Deserialize,
+)]
+pub enum SensorValue {
+    #[codec(index = 0)]
+    Number(u32),
+    #[codec(index = 1)]
+    Bool(bool),
+    /// A reading bounded to 0–100, e.g. a relative humidity.
+    #[codec(index = 2)]
+    #[serde(with = "percent")]
+    Percent(Percent),
+    /// A decimal reading in thousandths, e.g. `21.75` is `21_750`, see `parse_fixed`. Only
+    /// deserialized with the `fixed-point` feature; without it decimal readings are rejected.
+    #[codec(index = 3)]
+    Fixed(i64),
+}
+
+impl SensorValue {
+    /// The value as a number, or `None` for a `Bool` or a `Fixed`. A `Percent` gives its number
+    /// of percents.
+    pub fn as_number(&self) -> Option<u32> {
+        match self {
+            SensorValue::Number(x) => Some(*x),
+            SensorValue::Percent(p) => Some(p.deconstruct().into()),
+            SensorValue::Bool(_) | SensorValue::Fixed(_) => None,
+        }
+    }
+
+    /// The value in thousandths like a `Fixed`, e.g. `Number(21)` gives `21_000`, or `None` for a
+    /// `Bool`.
+    pub fn as_fixed(&self) -> Option<i64> {
+        match self {
+            SensorValue::Fixed(x) => Some(*x),
+            value => value
+                .as_number()
+                .map(|x| i64::from(x) * 10i64.pow(FIXED_DECIMALS)),
+        }
+    }
+}
+
+/// Deserialized through `RawSensorData`, which parses the fields in the format of the API.
+#[derive(
+    Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo, Serialize, Deserialize,
+)]
+#[serde(try_from = "RawSensorData")]
+pub struct SensorData {
+    pub id: u32,
+    #[serde(serialize_with = "ser_sensor_type_to_string")]
+    pub type_: SensorType,
+    pub geolocation: Geolocation,
+    #[serde(serialize_with = "ser_sensor_value_to_string")]
+    pub value: SensorValue,
+    /// Milliseconds since the Unix epoch. The API may send it as a number or an RFC 3339 string,
+    /// see `de_timestamp`.
+    pub timestamp: u64,
+    /// Whether the reading was computed by the offchain worker rather than reported by a device.
+    /// Serialized, but never read from JSON: a reading of the API is never derived.
+    pub derived: bool,
+    /// Quality indicator reported by the device, e.g. signal strength or a self-diagnostic.
+    /// Higher is better; the scale is up to the device.
+    pub quality: Option<u8>,
+}
+
+impl SensorData {
+    /// Parse a `id,type,lat,lon,value,timestamp` CSV record, with the fields in the same format as
+    /// the JSON served by the API. Returns `None` if the record is malformed.
+    pub fn from_csv_record(record: &str) -> Option<SensorData> {
+        type StrDeserializer<'a> = BorrowedStrDeserializer<'a, serde::de::value::Error>;
+
+        let mut fields = record.split(',').map(str::trim);
+        let id = fields.next()?.parse().ok()?;
+        let type_ = de_string_to_sensor_type(StrDeserializer::new(fields.next()?)).ok()?;
+        let geolocation = Geolocation {
+            lat: fields.next()?.parse().ok()?,
+            lon: fields.next()?.parse().ok()?,
+        };
+        if !geolocation.is_valid() {
+            return None;
+        }
+        let value = de_string_to_sensor_value(StrDeserializer::new(fields.next()?)).ok()?;
+        let timestamp = fields.next()?.parse().ok()?;
+        if fields.next().is_some() {
+            return None;
+        }
+
+        Some(SensorData {
+            id,
+            value: typed_sensor_value(&type_, value).ok()?,
+            type_,
+            geolocation,
+            timestamp,
+            derived: false,
+            quality: None,
+        })
+    }
+}
+
+/// `SensorData` as reported by the API, before its value is checked against its type. There is
+/// no `derived` field, so that the API can't pass off a reading as computed by the offchain
+/// worker.
+#[derive(Deserialize)]
+struct RawSensorData {
+    id: u32,
+    #[serde(deserialize_with = "de_string_to_sensor_type")]
+    type_: SensorType,
+    #[serde(deserialize_with = "de_string_to_geolocation")]
+    geolocation: Geolocation,
+    #[serde(deserialize_with = "de_string_to_sensor_value")]
+    value: SensorValue,
+    #[serde(deserialize_with = "de_timestamp")]
+    timestamp: u64,
+    #[serde(default)]
+    quality: Option<u8>,
+}
+
+impl TryFrom<RawSensorData> for SensorData {
+    type Error = &'static str;
+
+    fn try_from(raw: RawSensorData) -> Result<Self, Self::Error> {
+        Ok(SensorData {
+            value: typed_sensor_value(&raw.type_, raw.value)?,
+            id: raw.id,
+            type_: raw.type_,
+            geolocation: raw.geolocation,
+            timestamp: raw.timestamp,
+            derived: false,
+            quality: raw.quality,
+        })
+    }
+}
+
+/// `SensorData` in the compact form of the API, a positional array
+/// `[id, type_, [lat, lon], value, timestamp]` with the fields in the same format as in the object
+/// form, e.g. `[1,"Temperature",[4581,1523],"30",1700000000]`. Readings in this form are never
+/// derived and carry no quality.
+#[derive(Deserialize)]
+pub struct CompactSensorData(
+    u32,
+    #[serde(deserialize_with = "de_string_to_sensor_type")] SensorType,
+    (i32, i32),
+    #[serde(deserialize_with = "de_string_to_sensor_value")] SensorValue,
+    #[serde(deserialize_with = "de_timestamp")] u64,
+);
+
+impl TryFrom<CompactSensorData> for SensorData {
+    type Error = &'static str;
+
+    fn try_from(compact: CompactSensorData) -> Result<Self, Self::Error> {
+        let CompactSensorData(id, type_, (lat, lon), value, timestamp) = compact;
+        let geolocation = Geolocation { lat, lon };
+        if !geolocation.is_valid() {
+            return Err("Geolocation is out of range.");
+        }
+        Ok(SensorData {
+            value: typed_sensor_value(&type_, value)?,
+            id,
+            type_,
+            geolocation,
+            timestamp,
+            derived: false,
+            quality: None,
+        })
+    }
+}
+
+pub fn de_string_to_sensor_type<'de, D>(de: D) -> Result<SensorType, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: &str = Deserialize::deserialize(de)?;
+    match s {
+        "Humidity" => Ok(SensorType::Humidity),
+        "Pressure" => Ok(SensorType::Pressure),
+        "Temperature" => Ok(SensorType::Temperature),
+        "Digital" => Ok(SensorType::Digital),
+        "HeatIndex" => Ok(SensorType::HeatIndex),
+        "" => Err(SerdeError::custom("Error decoding sensor type.")),
+        name => custom_sensor_name::from_str(name).map(SensorType::Custom),
+    }
+}
+
+fn ser_sensor_type_to_string<S: Serializer>(type_: &SensorType, ser: S) -> Result<S::Ok, S::Error> {
+    match type_ {
+        SensorType::Humidity => ser.serialize_str("Humidity"),
+        SensorType::Temperature => ser.serialize_str("Temperature"),
+        SensorType::Pressure => ser.serialize_str("Pressure"),
+        SensorType::Digital => ser.serialize_str("Digital"),
+        SensorType::HeatIndex => ser.serialize_str("HeatIndex"),
+        SensorType::Custom(name) => custom_sensor_name::serialize(name, ser),
+    }
+}
+
+/// (De)serializes a `CustomSensorName` as a plain string rather than a byte array.
+mod custom_sensor_name {
+    use super::CustomSensorName;
+    use serde::{de::Error as SerdeError, Serializer};
+
+    pub fn serialize<S: Serializer>(name: &CustomSensorName, ser: S) -> Result<S::Ok, S::Error> {
+        let name = core::str::from_utf8(name)
+            .map_err(|_| serde::ser::Error::custom("Sensor type name is not UTF-8."))?;
+        ser.serialize_str(name)
+    }
+
+    pub fn from_str<E: SerdeError>(name: &str) -> Result<CustomSensorName, E> {
+        name.as_bytes()
+            .to_vec()
+            .try_into()
+            .map_err(|_| E::custom("Sensor type name is too long."))
+    }
+}
+
+fn de_string_to_geolocation<'de, D>(de: D) -> Result<Geolocation, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let geolocation: Geolocation = Deserialize::deserialize(de)?;
+    match geolocation.is_valid() {
+        true => Ok(geolocation),
+        false => Err(D::Error::custom("Geolocation is out of range.")),
+    }
+}
+
+fn ser_sensor_value_to_string<S: Serializer>(
+    value: &SensorValue,
+    ser: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        SensorValue::Number(x) => ser.collect_str(x),
+        SensorValue::Bool(b) => ser.collect_str(b),
+        SensorValue::Percent(p) => ser.collect_str(&p.deconstruct()),
+        SensorValue::Fixed(x) => ser.collect_str(&FixedDisplay(*x)),
+    }
+}
+
+pub fn de_string_to_sensor_value<'de, D>(de: D) -> Result<SensorValue, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    de.deserialize_any(SensorValueVisitor)
+}
+
+/// Reads a `SensorValue` from the string the API sends it as, e.g. `"42"` or `"true"`, or from a
+/// native integer or boolean. Decimals are only read from strings, to keep floats out of it.
+struct SensorValueVisitor;
+
+impl<'de> Visitor<'de> for SensorValueVisitor {
+    type Value = SensorValue;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("a sensor value, as a string, an integer or a boolean")
+    }
+
+    fn visit_str<E: SerdeError>(self, s: &str) -> Result<SensorValue, E> {
+        match s {
+            "true" => Ok(SensorValue::Bool(true)),
+            "false" => Ok(SensorValue::Bool(false)),
+            value => match value.parse::<u32>() {
+                Ok(x) => Ok(SensorValue::Number(x)),
+                #[cfg(feature = "fixed-point")]
+                Err(_) => parse_fixed(value)
+                    .map(SensorValue::Fixed)
+                    .ok_or_else(|| E::custom("Error decoding sensor value.")),
+                #[cfg(not(feature = "fixed-point"))]
+                Err(_) => Err(E::custom("Error decoding sensor value.")),
+            },
+        }
+    }
+
+    fn visit_bool<E: SerdeError>(self, b: bool) -> Result<SensorValue, E> {
+        Ok(SensorValue::Bool(b))
+    }
+
+    fn visit_u64<E: SerdeError>(self, x: u64) -> Result<SensorValue, E> {
+        match i64::try_from(x) {
+            Ok(x) => self.visit_i64(x),
+            Err(_) => Err(E::invalid_value(Unexpected::Unsigned(x), &self)),
+        }
+    }
+
+    fn visit_i64<E: SerdeError>(self, x: i64) -> Result<SensorValue, E> {
+        if let Ok(x) = u32::try_from(x) {
+            return Ok(SensorValue::Number(x));
+        }
+        // Like `"-5"` with `fixed-point`.
+        #[cfg(feature = "fixed-point")]
+        if let Some(x) = x.checked_mul(10i64.pow(FIXED_DECIMALS)) {
+            return Ok(SensorValue::Fixed(x));
+        }
+        Err(E::invalid_value(Unexpected::Signed(x), &self))
+    }
+}
+
+/// Read a timestamp in milliseconds since the Unix epoch, sent either as a number, taken as is,
+/// or as an RFC 3339 string such as `"2023-03-28T02:00:00+02:00"`, converted with
+/// `parse_rfc3339`.
+pub fn de_timestamp<'de, D>(de: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    de.deserialize_any(TimestampVisitor)
+}
+
+struct TimestampVisitor;
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("a timestamp, as a number or an RFC 3339 string")
+    }
+
+    fn visit_u64<E: SerdeError>(self, x: u64) -> Result<u64, E> {
+        Ok(x)
+    }
+
+    fn visit_str<E: SerdeError>(self, s: &str) -> Result<u64, E> {
+        parse_rfc3339(s).ok_or_else(|| E::invalid_value(Unexpected::Str(s), &self))
+    }
+}
+
+/// Parse an RFC 3339 date-time such as `"2023-03-28T00:00:00Z"` or
+/// `"2023-03-28T02:00:00.250+02:00"` into milliseconds since the Unix epoch, applying its
+/// offset. Digits of the fraction past the milliseconds are dropped.
+///
+/// Returns `None` for anything else, for dates that don't exist and for times before the epoch.
+pub fn parse_rfc3339(s: &str) -> Option<u64> {
+    // Only ASCII is valid, which also keeps the byte indexing below on char boundaries.
+    if !s.is_ascii() {
+        return None;
+    }
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let number = |part: &str| -> Option<i64> { is_digits(part).then(|| part.parse().ok())? };
+    let (date, time) = s.split_once(['T', 't'])?;
+    if date.len() != 10 || &date[4..5] != "-" || &date[7..8] != "-" {
+        return None;
+    }
+    let (year, month, day) = (
+        number(&date[..4])?,
+        number(&date[5..7])?,
+        number(&date[8..])?,
+    );
+
+    let (time, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let (time, offset) = time.split_at(time.len().checked_sub(6)?);
+            let sign = match &offset[..1] {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            if &offset[3..4] != ":" {
+                return None;
+            }
+            let (hours, minutes) = (number(&offset[1..3])?, number(&offset[4..])?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            (time, sign * (hours * 3600 + minutes * 60))
+        }
+    };
+    let (time, fraction) = time.split_once('.').unwrap_or((time, "0"));
+    if !is_digits(fraction) {
+        return None;
+    }
+    if time.len() != 8 || &time[2..3] != ":" || &time[5..6] != ":" {
+        return None;
+    }
+    let (hour, minute, second) = (
+        number(&time[..2])?,
+        number(&time[3..5])?,
+        number(&time[6..])?,
+    );
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    // Up to 60 seconds for leap seconds.
+    if day < 1 || day > days_in_month || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Days since the epoch of the civil date, counting years from March so that the leap day
+    // comes last.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    let millis = fraction
+        .bytes()
+        .chain(core::iter::repeat(b'0'))
+        .take(3)
+        .fold(0, |millis, digit| millis * 10 + i64::from(digit - b'0'));
+    u64::try_from(seconds * 1_000 + millis).ok()
+}
+
+/// Number of decimals kept by `SensorValue::Fixed`.
+pub const FIXED_DECIMALS: u32 = 3;
+
+/// Parse a decimal string such as `"21.75"`, `"-0.5"`, `".5"` or `"21"` into thousandths,
+/// without floats so it behaves the same in `no_std`.
+///
+/// An optional `+` or `-` sign comes first, and either the integer or the fractional part may be
+/// left out, but not both. Returns `None` for anything else, for more than `FIXED_DECIMALS`
+/// decimals rather than rounding them away, and for values that overflow an `i64`.
+pub fn parse_fixed(s: &str) -> Option<i64> {
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if (int.is_empty() && frac.is_empty())
+        || !is_digits(int)
+        || !is_digits(frac)
+        || frac.len() > FIXED_DECIMALS as usize
+    {
+        return None;
+    }
+
+    // Accumulate towards the sign so that `i64::MIN` parses too.
+    let sign = if negative { -1 } else { 1 };
+    let padding = 10i64.pow(FIXED_DECIMALS - frac.len() as u32);
+    int.bytes()
+        .chain(frac.bytes())
+        .try_fold(0i64, |acc, b| {
+            acc.checked_mul(10)?.checked_add(sign * (b - b'0') as i64)
+        })?
+        .checked_mul(padding)
+}
+
+/// Formats thousandths as a decimal `parse_fixed` reads back, e.g. `-500` as `-0.500`.
+struct FixedDisplay(i64);
+
+impl core::fmt::Display for FixedDisplay {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let scale = 10u64.pow(FIXED_DECIMALS);
+        let abs = self.0.unsigned_abs();
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let width = FIXED_DECIMALS as usize;
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            abs / scale,
+            abs % scale,
+            width = width
+        )
+    }
+}
+
+/// Narrow a value parsed by `de_string_to_sensor_value` to the variant its `type_` calls for:
+/// humidities become a `Percent`, and are rejected above 100.
+pub fn typed_sensor_value(
+    type_: &SensorType,
+    value: SensorValue,
+) -> Result<SensorValue, &'static str> {
+    match (type_, value) {
+        (SensorType::Humidity, SensorValue::Number(x)) => u8::try_from(x)
+            .ok()
+            .filter(|x| *x <= 100)
+            .map(|x| SensorValue::Percent(Percent::from_percent(x)))
+            .ok_or("Humidity is not a percentage."),
+        (_, value) => Ok(value),
+    }
+}
+
+/// (De)serializes a `Percent` as its number of percents.
+mod percent {
+    use super::Percent;
+    use serde::{de::Error as SerdeError, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(p: &Percent, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_u8(p.deconstruct())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Percent, D::Error> {
+        match u8::deserialize(de)? {
+            x if x <= 100 => Ok(Percent::from_percent(x)),
+            _ => Err(SerdeError::custom("Percent is above 100.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<SensorData, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn parses_a_reading_of_the_api() {
+        assert_eq!(
+            parse(
+                r#"{"id":1,"type_":"Temperature","geolocation":{"lat":4581,"lon":1523},"value":"30","timestamp":1700000000}"#
+            )
+            .unwrap(),
+            SensorData {
+                id: 1,
+                type_: SensorType::Temperature,
+                geolocation: Geolocation { lat: 4581, lon: 1523 },
+                value: SensorValue::Number(30),
+                timestamp: 1700000000,
+                derived: false,
+                quality: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_native_json_values_and_rfc3339_timestamps() {
+        let reading = parse(
+            r#"{"id":1,"type_":"Digital","geolocation":{"lat":1,"lon":2},"value":true,"timestamp":"2023-03-28T02:00:00+02:00","quality":7}"#,
+        )
+        .unwrap();
+
+        assert_eq!(reading.value, SensorValue::Bool(true));
+        assert_eq!(reading.timestamp, 1_679_961_600_000);
+        assert_eq!(reading.quality, Some(7));
+    }
+
+    #[test]
+    fn humidities_are_percentages() {
+        let humidity = |value: &str| {
+            parse(&format!(
+                r#"{{"id":1,"type_":"Humidity","geolocation":{{"lat":1,"lon":2}},"value":{value},"timestamp":3}}"#
+            ))
+            .map(|reading| reading.value)
+        };
+
+        assert_eq!(
+            humidity(r#""45""#).unwrap(),
+            SensorValue::Percent(Percent::from_percent(45))
+        );
+        assert_eq!(
+            humidity("100").unwrap(),
+            SensorValue::Percent(Percent::from_percent(100))
+        );
+        assert!(humidity("101").is_err());
+    }
+
+    #[test]
+    fn parses_custom_sensor_types() {
+        let reading = parse(
+            r#"{"id":1,"type_":"Radiation","geolocation":{"lat":1,"lon":2},"value":"12","timestamp":3}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            reading.type_,
+            SensorType::Custom(b"Radiation".to_vec().try_into().unwrap())
+        );
+        assert!(parse(
+            r#"{"id":1,"type_":"ARadiationCounter","geolocation":{"lat":1,"lon":2},"value":"12","timestamp":3}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_geolocations_out_of_range() {
+        let geolocation = |lat: i32, lon: i32| {
+            parse(&format!(
+                r#"{{"id":1,"type_":"Pressure","geolocation":{{"lat":{lat},"lon":{lon}}},"value":"1","timestamp":3}}"#
+            ))
+            .map(|reading| reading.geolocation)
+        };
+
+        for (lat, lon) in [(-3387, 15121), (9_000, -18_000), (-9_000, 18_000)] {
+            assert_eq!(geolocation(lat, lon).unwrap(), Geolocation { lat, lon });
+        }
+        for (lat, lon) in [(9_001, 0), (-9_001, 0), (0, 18_001), (0, -18_001)] {
+            assert!(geolocation(lat, lon).is_err(), "accepted {lat},{lon}");
+        }
+        assert_eq!(
+            SensorData::from_csv_record("1,Temperature,0,-18001,1,3"),
+            None
+        );
+    }
+
+    #[test]
+    fn the_compact_and_csv_forms_parse_like_the_object_form() {
+        let object = parse(
+            r#"{"id":1,"type_":"Humidity","geolocation":{"lat":4581,"lon":1523},"value":"30","timestamp":1700000000}"#,
+        )
+        .unwrap();
+        let compact: CompactSensorData =
+            serde_json::from_str(r#"[1,"Humidity",[4581,1523],"30",1700000000]"#).unwrap();
+
+        assert_eq!(SensorData::try_from(compact).unwrap(), object);
+        assert_eq!(
+            SensorData::from_csv_record("1,Humidity,4581,1523,30,1700000000").unwrap(),
+            object
+        );
+    }
+
+    #[test]
+    fn serializes_in_the_shape_it_parses() {
+        let reading = SensorData {
+            id: 2,
+            type_: SensorType::HeatIndex,
+            geolocation: Geolocation { lat: 1, lon: 2 },
+            value: SensorValue::Number(31),
+            timestamp: 3,
+            derived: true,
+            quality: None,
+        };
+
+        let json = serde_json::to_string(&reading).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"id":2,"type_":"HeatIndex","geolocation":{"lat":1,"lon":2},"value":"31","timestamp":3,"derived":true,"quality":null}"#
+        );
+        // Except for `derived`, which only the offchain worker sets.
+        assert_eq!(
+            parse(&json).unwrap(),
+            SensorData {
+                derived: false,
+                ..reading
+            }
+        );
+    }
+
+    #[test]
+    fn as_fixed_scales_numbers_to_thousandths() {
+        assert_eq!(SensorValue::Number(21).as_fixed(), Some(21_000));
+        assert_eq!(
+            SensorValue::Percent(Percent::from_percent(45)).as_fixed(),
+            Some(45_000)
+        );
+        assert_eq!(SensorValue::Fixed(-500).as_fixed(), Some(-500));
+        assert_eq!(SensorValue::Bool(true).as_fixed(), None);
+    }
+
+    #[test]
+    fn percent_decodes_only_up_to_100() {
+        assert_eq!(Percent::from_percent(150).deconstruct(), 100);
+        assert_eq!(
+            SensorValue::Percent(Percent::from_percent(60)).encode(),
+            vec![2, 60]
+        );
+        assert_eq!(
+            Percent::decode(&mut &[100][..]),
+            Ok(Percent::from_percent(100))
+        );
+        assert!(Percent::decode(&mut &[101][..]).is_err());
+    }
+}